@@ -0,0 +1,2 @@
+DROP TABLE if exists attempt_answer;
+DROP TABLE if exists attempt;
\ No newline at end of file
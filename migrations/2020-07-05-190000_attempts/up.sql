@@ -0,0 +1,21 @@
+CREATE TABLE attempt (
+    id INTEGER AUTO_INCREMENT PRIMARY KEY,
+    qz_id INTEGER NOT NULL,
+    u_id INTEGER,
+    r_id INTEGER,
+    -- What the result was called when the attempt landed on it, kept in case it's edited away
+    result_header VARCHAR(64),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(qz_id) REFERENCES quiz(id) ON DELETE CASCADE,
+    FOREIGN KEY(u_id) REFERENCES user(id) ON DELETE SET NULL,
+    FOREIGN KEY(r_id) REFERENCES result(id) ON DELETE SET NULL
+);
+CREATE TABLE attempt_answer (
+    id INTEGER AUTO_INCREMENT PRIMARY KEY,
+    at_id INTEGER NOT NULL,
+    a_id INTEGER,
+    -- What the answer said when it was picked, kept in case it's edited away
+    description VARCHAR(240) NOT NULL,
+    FOREIGN KEY(at_id) REFERENCES attempt(id) ON DELETE CASCADE,
+    FOREIGN KEY(a_id) REFERENCES answer(id) ON DELETE SET NULL
+);
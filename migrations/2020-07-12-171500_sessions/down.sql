@@ -0,0 +1 @@
+DROP TABLE if exists session;
\ No newline at end of file
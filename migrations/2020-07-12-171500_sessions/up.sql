@@ -0,0 +1,10 @@
+-- 'id' is the SHA-256 of the token kept in the session cookie, so the table alone can't be used to log in
+CREATE TABLE session (
+    id VARCHAR(64) PRIMARY KEY,
+    u_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    user_agent VARCHAR(240),
+    FOREIGN KEY(u_id) REFERENCES user(id) ON DELETE CASCADE
+);
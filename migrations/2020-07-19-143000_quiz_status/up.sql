@@ -0,0 +1,7 @@
+-- 'draft' quizzes are only seen by their author, 'unlisted' ones by anyone with the link, and
+-- 'archived' ones stay around for their attempts. Only 'published' quizzes are listed.
+ALTER TABLE quiz ADD COLUMN status VARCHAR(16) NOT NULL DEFAULT 'draft';
+ALTER TABLE quiz ADD COLUMN published_at TIMESTAMP NULL;
+CREATE INDEX quiz_status ON quiz(status);
+-- Everything posted before drafts existed went out straight away
+UPDATE quiz SET status = 'published', published_at = CURRENT_TIMESTAMP;
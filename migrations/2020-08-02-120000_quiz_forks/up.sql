@@ -0,0 +1,4 @@
+-- A fork remembers the quiz it was copied from, for as long as that quiz is around
+ALTER TABLE quiz ADD COLUMN forked_from_id INTEGER NULL;
+ALTER TABLE quiz ADD CONSTRAINT quiz_forked_from FOREIGN KEY (forked_from_id) REFERENCES quiz(id) ON DELETE SET NULL;
+ALTER TABLE quiz ADD COLUMN allow_forks BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,4 @@
+DROP INDEX answer_position ON answer;
+DROP INDEX question_position ON question;
+ALTER TABLE answer DROP COLUMN position;
+ALTER TABLE question DROP COLUMN position;
@@ -0,0 +1,3 @@
+DROP TABLE if exists attempt_response;
+ALTER TABLE question DROP COLUMN config;
+ALTER TABLE question DROP COLUMN kind;
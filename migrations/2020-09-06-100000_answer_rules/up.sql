@@ -0,0 +1,10 @@
+-- Where picking an answer takes the taker, when it isn't just the next question. A rule without
+-- a 'next_q_id' ends the quiz there.
+CREATE TABLE answer_rule (
+    id INTEGER AUTO_INCREMENT PRIMARY KEY,
+    a_id INTEGER NOT NULL,
+    next_q_id INTEGER,
+    FOREIGN KEY(a_id) REFERENCES answer(id) ON DELETE CASCADE,
+    FOREIGN KEY(next_q_id) REFERENCES question(id) ON DELETE CASCADE,
+    UNIQUE(a_id)
+);
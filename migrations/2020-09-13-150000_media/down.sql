@@ -0,0 +1,9 @@
+ALTER TABLE result DROP FOREIGN KEY result_media;
+ALTER TABLE result DROP COLUMN media_id;
+ALTER TABLE answer DROP FOREIGN KEY answer_media;
+ALTER TABLE answer DROP COLUMN media_id;
+ALTER TABLE question DROP FOREIGN KEY question_media;
+ALTER TABLE question DROP COLUMN media_id;
+ALTER TABLE quiz DROP FOREIGN KEY quiz_media;
+ALTER TABLE quiz DROP COLUMN media_id;
+DROP TABLE if exists media;
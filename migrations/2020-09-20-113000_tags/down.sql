@@ -0,0 +1,4 @@
+DROP INDEX quiz_category ON quiz;
+ALTER TABLE quiz DROP COLUMN category;
+DROP TABLE if exists quiz_tag;
+DROP TABLE if exists tag;
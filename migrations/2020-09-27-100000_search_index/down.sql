@@ -0,0 +1 @@
+CREATE FULLTEXT INDEX search ON quiz(name, description);
@@ -0,0 +1,2 @@
+-- Search has its own index now, kept next to the server, see SearchIndex
+DROP INDEX search ON quiz;
@@ -0,0 +1,10 @@
+DROP TRIGGER if exists quiz_fts_update;
+DROP TRIGGER if exists quiz_fts_delete;
+DROP TRIGGER if exists quiz_fts_insert;
+DROP TABLE if exists quiz_fts;
+DROP TABLE if exists answer;
+DROP TABLE if exists result;
+DROP TABLE if exists question;
+DROP TABLE if exists quiz;
+DROP TABLE if exists user;
+DROP TABLE if exists auth_info;
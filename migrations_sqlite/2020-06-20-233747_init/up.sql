@@ -0,0 +1,50 @@
+CREATE TABLE user (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(240) NOT NULL,
+    email VARCHAR(240) NOT NULL
+);
+CREATE TABLE quiz (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(240) NOT NULL,
+    description VARCHAR(240) NOT NULL,
+    u_id INTEGER NOT NULL,
+    FOREIGN KEY(u_id) REFERENCES user(id) ON DELETE CASCADE
+);
+CREATE TABLE question (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    description VARCHAR(240) NOT NULL,
+    qz_id INTEGER NOT NULL,
+    FOREIGN KEY(qz_id) REFERENCES quiz(id) ON DELETE CASCADE
+);
+CREATE TABLE answer (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    description VARCHAR(240) NOT NULL,
+    val INTEGER NOT NULL,
+    q_id INTEGER NOT NULL,
+    FOREIGN KEY(q_id) REFERENCES question(id) ON DELETE CASCADE
+);
+CREATE TABLE result (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    num INTEGER NOT NULL,
+    header VARCHAR(64) NOT NULL,
+    description VARCHAR(240) NOT NULL,
+    qz_id INTEGER NOT NULL,
+    FOREIGN KEY(qz_id) REFERENCES quiz(id) ON DELETE CASCADE
+);
+CREATE TABLE auth_info (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uid INTEGER NOT NULL,
+    password_hash TEXT NOT NULL
+);
+-- Stands in for MySQL's fulltext index, the triggers keep it in step with quiz
+CREATE VIRTUAL TABLE quiz_fts USING fts5(name, description, content='quiz', content_rowid='id');
+CREATE TRIGGER quiz_fts_insert AFTER INSERT ON quiz BEGIN
+    INSERT INTO quiz_fts(rowid, name, description) VALUES (new.id, new.name, new.description);
+END;
+CREATE TRIGGER quiz_fts_delete AFTER DELETE ON quiz BEGIN
+    INSERT INTO quiz_fts(quiz_fts, rowid, name, description) VALUES ('delete', old.id, old.name, old.description);
+END;
+CREATE TRIGGER quiz_fts_update AFTER UPDATE ON quiz BEGIN
+    INSERT INTO quiz_fts(quiz_fts, rowid, name, description) VALUES ('delete', old.id, old.name, old.description);
+    INSERT INTO quiz_fts(rowid, name, description) VALUES (new.id, new.name, new.description);
+END;
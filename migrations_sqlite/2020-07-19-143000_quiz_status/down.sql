@@ -0,0 +1,3 @@
+DROP INDEX quiz_status;
+ALTER TABLE quiz DROP COLUMN published_at;
+ALTER TABLE quiz DROP COLUMN status;
@@ -0,0 +1,14 @@
+-- Every save of a quiz tree, 'snapshot' being the whole quiz as JSON in the v1 FullQuiz shape.
+-- 'num' counts up from 1 per quiz.
+CREATE TABLE quiz_version (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    qz_id INTEGER NOT NULL,
+    num INTEGER NOT NULL,
+    u_id INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    summary VARCHAR(240) NOT NULL,
+    snapshot TEXT NOT NULL,
+    UNIQUE(qz_id, num),
+    FOREIGN KEY(qz_id) REFERENCES quiz(id) ON DELETE CASCADE,
+    FOREIGN KEY(u_id) REFERENCES user(id) ON DELETE SET NULL
+);
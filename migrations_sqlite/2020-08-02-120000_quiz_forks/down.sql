@@ -0,0 +1,3 @@
+ALTER TABLE quiz DROP COLUMN allow_forks;
+DROP INDEX quiz_forked_from;
+ALTER TABLE quiz DROP COLUMN forked_from_id;
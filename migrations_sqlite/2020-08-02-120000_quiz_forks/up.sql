@@ -0,0 +1,4 @@
+-- A fork remembers the quiz it was copied from, for as long as that quiz is around
+ALTER TABLE quiz ADD COLUMN forked_from_id INTEGER REFERENCES quiz(id) ON DELETE SET NULL;
+CREATE INDEX quiz_forked_from ON quiz(forked_from_id);
+ALTER TABLE quiz ADD COLUMN allow_forks BOOLEAN NOT NULL DEFAULT 1;
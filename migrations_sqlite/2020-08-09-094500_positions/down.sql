@@ -0,0 +1,4 @@
+DROP INDEX answer_position;
+DROP INDEX question_position;
+ALTER TABLE answer DROP COLUMN position;
+ALTER TABLE question DROP COLUMN position;
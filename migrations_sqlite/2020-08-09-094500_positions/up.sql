@@ -0,0 +1,8 @@
+-- Where a question sits in its quiz and an answer in its question, counting up from 0. Rows
+-- from before keep the order they had, which was by id.
+ALTER TABLE question ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE answer ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+UPDATE question SET position = id;
+UPDATE answer SET position = id;
+CREATE INDEX question_position ON question(qz_id, position);
+CREATE INDEX answer_position ON answer(q_id, position);
@@ -0,0 +1,15 @@
+-- How a question is answered, see QuestionKind. 'config' holds the settings only some kinds
+-- use, as JSON.
+ALTER TABLE question ADD COLUMN kind VARCHAR(16) NOT NULL DEFAULT 'single_choice';
+ALTER TABLE question ADD COLUMN config VARCHAR(1000) NOT NULL DEFAULT '{}';
+-- What a taker gave for a question that isn't answered by picking answers, a number on a scale
+-- or some words of their own
+CREATE TABLE attempt_response (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    at_id INTEGER NOT NULL,
+    q_id INTEGER NOT NULL,
+    scale_value INTEGER,
+    free_text VARCHAR(1000),
+    FOREIGN KEY(at_id) REFERENCES attempt(id) ON DELETE CASCADE,
+    FOREIGN KEY(q_id) REFERENCES question(id) ON DELETE CASCADE
+);
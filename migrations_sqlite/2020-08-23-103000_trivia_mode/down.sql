@@ -0,0 +1,4 @@
+ALTER TABLE result DROP COLUMN max_percent;
+ALTER TABLE result DROP COLUMN min_percent;
+ALTER TABLE answer DROP COLUMN is_correct;
+ALTER TABLE quiz DROP COLUMN mode;
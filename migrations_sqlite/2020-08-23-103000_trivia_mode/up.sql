@@ -0,0 +1,7 @@
+-- Personality quizzes land on a result by adding up answers' 'val', trivia quizzes by the share
+-- of questions answered correctly, see QuizMode
+ALTER TABLE quiz ADD COLUMN mode VARCHAR(16) NOT NULL DEFAULT 'personality';
+ALTER TABLE answer ADD COLUMN is_correct BOOLEAN NOT NULL DEFAULT 0;
+-- The band of percentages a trivia result covers, both ends included. Empty for personality quizzes.
+ALTER TABLE result ADD COLUMN min_percent INTEGER;
+ALTER TABLE result ADD COLUMN max_percent INTEGER;
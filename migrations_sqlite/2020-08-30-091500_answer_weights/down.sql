@@ -0,0 +1 @@
+DROP TABLE if exists answer_weight;
@@ -0,0 +1,11 @@
+-- How much an answer counts towards each result of a personality quiz. An answer with weights
+-- counts towards all of them instead of just the one its 'val' points at.
+CREATE TABLE answer_weight (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    a_id INTEGER NOT NULL,
+    r_id INTEGER NOT NULL,
+    weight INTEGER NOT NULL,
+    FOREIGN KEY(a_id) REFERENCES answer(id) ON DELETE CASCADE,
+    FOREIGN KEY(r_id) REFERENCES result(id) ON DELETE CASCADE,
+    UNIQUE(a_id, r_id)
+);
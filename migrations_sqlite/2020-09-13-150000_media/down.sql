@@ -0,0 +1,5 @@
+ALTER TABLE result DROP COLUMN media_id;
+ALTER TABLE answer DROP COLUMN media_id;
+ALTER TABLE question DROP COLUMN media_id;
+ALTER TABLE quiz DROP COLUMN media_id;
+DROP TABLE if exists media;
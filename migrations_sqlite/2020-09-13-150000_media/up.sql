@@ -0,0 +1,17 @@
+-- Uploaded pictures. The files themselves live in the media directory, named after the id.
+CREATE TABLE media (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    u_id INTEGER NOT NULL,
+    content_type VARCHAR(32) NOT NULL,
+    byte_size INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(u_id) REFERENCES user(id) ON DELETE CASCADE
+);
+
+-- Anything shown to a taker can have a picture
+ALTER TABLE quiz ADD COLUMN media_id INTEGER REFERENCES media(id) ON DELETE SET NULL;
+ALTER TABLE question ADD COLUMN media_id INTEGER REFERENCES media(id) ON DELETE SET NULL;
+ALTER TABLE answer ADD COLUMN media_id INTEGER REFERENCES media(id) ON DELETE SET NULL;
+ALTER TABLE result ADD COLUMN media_id INTEGER REFERENCES media(id) ON DELETE SET NULL;
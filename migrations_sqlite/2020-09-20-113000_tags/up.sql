@@ -0,0 +1,17 @@
+-- Free form labels a quiz can carry any number of, stored once each and shared between quizzes
+CREATE TABLE tag (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(32) NOT NULL,
+    UNIQUE(name)
+);
+CREATE TABLE quiz_tag (
+    qz_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    PRIMARY KEY(qz_id, tag_id),
+    FOREIGN KEY(qz_id) REFERENCES quiz(id) ON DELETE CASCADE,
+    FOREIGN KEY(tag_id) REFERENCES tag(id) ON DELETE CASCADE
+);
+CREATE INDEX quiz_tag_tag ON quiz_tag(tag_id);
+-- A quiz sits in at most one category
+ALTER TABLE quiz ADD COLUMN category VARCHAR(32);
+CREATE INDEX quiz_category ON quiz(category);
@@ -0,0 +1,12 @@
+CREATE VIRTUAL TABLE quiz_fts USING fts5(name, description, content='quiz', content_rowid='id');
+CREATE TRIGGER quiz_fts_insert AFTER INSERT ON quiz BEGIN
+    INSERT INTO quiz_fts(rowid, name, description) VALUES (new.id, new.name, new.description);
+END;
+CREATE TRIGGER quiz_fts_delete AFTER DELETE ON quiz BEGIN
+    INSERT INTO quiz_fts(quiz_fts, rowid, name, description) VALUES ('delete', old.id, old.name, old.description);
+END;
+CREATE TRIGGER quiz_fts_update AFTER UPDATE ON quiz BEGIN
+    INSERT INTO quiz_fts(quiz_fts, rowid, name, description) VALUES ('delete', old.id, old.name, old.description);
+    INSERT INTO quiz_fts(rowid, name, description) VALUES (new.id, new.name, new.description);
+END;
+INSERT INTO quiz_fts(quiz_fts) VALUES ('rebuild');
@@ -0,0 +1,5 @@
+-- Search has its own index now, kept next to the server, see SearchIndex
+DROP TRIGGER if exists quiz_fts_update;
+DROP TRIGGER if exists quiz_fts_delete;
+DROP TRIGGER if exists quiz_fts_insert;
+DROP TABLE if exists quiz_fts;
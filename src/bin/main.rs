@@ -1,10 +1,13 @@
-#[macro_use]
 extern crate rocket; // framework
 
 use rocket::http::Method;
 use rocket_cors::{AllowedOrigins, CorsOptions}; // must appease our CORS overlords
 
-use quizzes_backend::routing::{auth_routes::*, quiz_routes::*};
+use quizzes_backend::repository::Storage;
+use quizzes_backend::routing;
+use quizzes_backend::routing::media_functions::MediaStore;
+use quizzes_backend::routing::search_functions::{rebuild_index, SearchIndex};
+use quizzes_backend::routing::submission_functions::AttemptSigner;
 
 fn make_cors() -> rocket_cors::Cors {
     let allowed_origins = AllowedOrigins::some_exact(&["http://localhost:3000/*"]);
@@ -12,7 +15,7 @@ fn make_cors() -> rocket_cors::Cors {
     // You can also deserialize this
     CorsOptions {
         allowed_origins,
-        allowed_methods: vec![Method::Get, Method::Post, Method::Delete]
+        allowed_methods: vec![Method::Get, Method::Post, Method::Put, Method::Patch, Method::Delete]
             .into_iter()
             .map(From::from)
             .collect(),
@@ -24,27 +27,50 @@ fn make_cors() -> rocket_cors::Cors {
 }
 
 fn rocket() -> rocket::Rocket {
-    rocket::ignite()
-        .mount(
-            "/",
-            routes![
-                index,
-                get_full_quiz_route,
-                insert_quiz,
-                browse,
-                search,
-                get_quizzes_by_user_id,
-                delete,
-                create,
-                login,
-                fetch_info_by_user_id,
-                logout,
-            ],
-        )
+    let rocket = rocket::ignite()
+        .mount("/", routing::routes())
+        .register(routing::catchers())
+        .manage(Storage::Database)
         .attach(quizzes_backend::DbConn::fairing())
-        .attach(make_cors())
+        .attach(AttemptSigner::fairing())
+        .attach(MediaStore::fairing())
+        .attach(SearchIndex::fairing())
+        .attach(make_cors());
+
+    // Without MySQL the server runs off a SQLite file, which is migrated on the spot
+    #[cfg(not(feature = "mysql"))]
+    let rocket = rocket.attach(quizzes_backend::repository::sqlite::migrations_fairing());
+
+    rocket
+}
+
+// 'cargo run -- reindex' builds the search index again from the database, e.g. after a restore
+// from backup or if the index got out of step. Only one process can write to the index, so the
+// server has to be stopped first.
+fn reindex(rocket: &rocket::Rocket) -> Result<usize, String> {
+    let index = rocket
+        .state::<SearchIndex>()
+        .ok_or("The search index couldn't be opened, is the server still running?")?;
+    let conn = quizzes_backend::DbConn::get_one(rocket).ok_or("Couldn't connect to the database")?;
+    rebuild_index(index, &*conn).map_err(|err| err.message)
 }
 
 fn main() {
-    rocket().launch();
+    let rocket = rocket();
+    match std::env::args().nth(1).as_deref() {
+        None => {
+            rocket.launch();
+        }
+        Some("reindex") => match reindex(&rocket) {
+            Ok(count) => println!("Indexed {} quizzes", count),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        Some(other) => {
+            eprintln!("Unknown command '{}', the only one is 'reindex'", other);
+            std::process::exit(2);
+        }
+    }
 }
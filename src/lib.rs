@@ -3,6 +3,10 @@
 #[macro_use]
 extern crate diesel; // ORM and query builder
 
+#[cfg(feature = "sqlite")]
+#[macro_use]
+extern crate diesel_migrations; // embeds the SQLite migrations
+
 #[macro_use]
 extern crate rocket; // framework
 
@@ -13,16 +17,31 @@ extern crate rocket_contrib; // useful community libraries
 extern crate serde;
 extern crate serde_json;
 
+extern crate argon2;
+extern crate base64;
+extern crate chrono;
 extern crate crypto;
+extern crate image;
+extern crate log;
+extern crate rand;
+extern crate tantivy;
+extern crate time;
 
 #[macro_use]
 extern crate serde_derive; // to be able to derive
 
 // Managed struct that holds the db connection, specifically to a database called 'quizzes_db'
+#[cfg(feature = "mysql")]
 #[database("quizzes_db")]
 pub struct DbConn(diesel::MysqlConnection);
 
+// Without MySQL, 'quizzes_db' is a SQLite file
+#[cfg(all(feature = "sqlite", not(feature = "mysql")))]
+#[database("quizzes_db")]
+pub struct DbConn(diesel::SqliteConnection);
+
 pub mod models;
+pub mod repository;
 pub mod routing;
 pub mod schema;
 pub mod utils;
@@ -0,0 +1,69 @@
+use crate::schema::*;
+use chrono::NaiveDateTime;
+
+/* -------------------------------------------------------------------------- */
+/*        Models for query results, analagous to the records in the db.       */
+/* -------------------------------------------------------------------------- */
+
+// One run through a quiz. 'u_id' is empty for anonymous takers, 'r_id' is the result they
+// landed on (empty if the author has since deleted it). 'result_header' is what that result was
+// called at the time, so the attempt still says where it landed after the result is gone.
+#[derive(Serialize, Queryable, Clone, Debug)]
+pub struct Attempt {
+    pub id: i32,
+    pub qz_id: i32,
+    pub u_id: Option<i32>,
+    pub r_id: Option<i32>,
+    pub result_header: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+// An answer picked in an attempt, with its description as it was at the time. 'a_id' is empty
+// once the author has deleted the answer.
+#[derive(Serialize, Queryable, Clone, Debug)]
+pub struct AttemptAnswer {
+    pub id: i32,
+    pub at_id: i32,
+    pub a_id: Option<i32>,
+    pub description: String,
+}
+
+// What was given for a scale or free text question, whichever the question is
+#[derive(Serialize, Queryable, Clone, Debug)]
+pub struct AttemptResponse {
+    pub id: i32,
+    pub at_id: i32,
+    pub q_id: i32,
+    pub scale_value: Option<i32>,
+    pub free_text: Option<String>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*         Models for data to be inserted. Adds calculated db fields.         */
+/* -------------------------------------------------------------------------- */
+
+#[derive(Insertable, Debug)]
+#[table_name = "attempt"]
+pub struct NewAttempt {
+    pub qz_id: i32,
+    pub u_id: Option<i32>,
+    pub r_id: Option<i32>,
+    pub result_header: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "attempt_answer"]
+pub struct NewAttemptAnswer {
+    pub at_id: i32,
+    pub a_id: i32,
+    pub description: String,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "attempt_response"]
+pub struct NewAttemptResponse {
+    pub at_id: i32,
+    pub q_id: i32,
+    pub scale_value: Option<i32>,
+    pub free_text: Option<String>,
+}
@@ -1,23 +1,35 @@
 use crate::schema::*;
+use chrono::NaiveDateTime;
 
 /* -------------------------------------------------------------------------- */
 /*        Models for query results, analagous to the records in the db.       */
 /* -------------------------------------------------------------------------- */
 
-#[derive(Serialize, Queryable)]
+#[derive(Serialize, Queryable, Clone, Debug)]
 pub struct User {
     pub id: i32,
     pub name: String,
     pub email: String,
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Clone, Debug)]
 pub struct AuthInfo {
     pub id: i32,
     pub uid: i32,
     pub password_hash: String,
 }
 
+// A logged in device. 'id' is the SHA-256 of the token in the session cookie, never the token.
+#[derive(Serialize, Queryable, Clone, Debug)]
+pub struct Session {
+    pub id: String,
+    pub u_id: i32,
+    pub created_at: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub user_agent: Option<String>,
+}
+
 /* -------------------------------------------------------------------------- */
 /*         Models for data to be inserted. Adds calculated db fields.         */
 /* -------------------------------------------------------------------------- */
@@ -35,3 +47,14 @@ pub struct NewAuthInfo {
     pub uid: i32,
     pub password_hash: String,
 }
+
+#[derive(Insertable, Debug)]
+#[table_name = "session"]
+pub struct NewSession {
+    pub id: String,
+    pub u_id: i32,
+    pub created_at: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub user_agent: Option<String>,
+}
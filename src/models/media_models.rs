@@ -0,0 +1,33 @@
+use crate::schema::*;
+use chrono::NaiveDateTime;
+
+/* -------------------------------------------------------------------------- */
+/*        Models for query results, analagous to the records in the db.       */
+/* -------------------------------------------------------------------------- */
+
+// An uploaded picture. 'content_type' is what the file turned out to be, not what the uploader
+// said it was.
+#[derive(Serialize, Queryable, Clone, Debug, PartialEq)]
+pub struct Media {
+    pub id: i32,
+    pub u_id: i32,
+    pub content_type: String,
+    pub byte_size: i32,
+    pub width: i32,
+    pub height: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/* -------------------------------------------------------------------------- */
+/*         Models for data to be inserted. Adds calculated db fields.         */
+/* -------------------------------------------------------------------------- */
+
+#[derive(Insertable, Debug)]
+#[table_name = "media"]
+pub struct NewMedia {
+    pub u_id: i32,
+    pub content_type: String,
+    pub byte_size: i32,
+    pub width: i32,
+    pub height: i32,
+}
@@ -1,2 +1,6 @@
+pub mod attempt_models;
 pub mod auth_models;
+pub mod media_models;
 pub mod quiz_models;
+pub mod tag_models;
+pub mod version_models;
@@ -1,41 +1,482 @@
 use crate::schema::*;
+use chrono::{NaiveDateTime, Utc};
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql, Queryable};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use std::collections::BTreeMap;
+use std::io::Write;
 
 /* -------------------------------------------------------------------------- */
 /*        Models for query results, analagous to the records in the db.       */
 /* -------------------------------------------------------------------------- */
 
-#[derive(Serialize, Queryable, Debug)]
+#[derive(Serialize, Deserialize, Identifiable, Associations, Clone, Debug, PartialEq)]
+#[belongs_to(Question, foreign_key = "q_id")]
+#[table_name = "answer"]
 pub struct Answer {
     pub id: i32,
     pub description: String,
     pub val: i32, // value used for determining overall result pub q_id: i32,
     pub q_id: i32,
+    // Order within the question. Versions saved before it existed list answers in order already.
+    #[serde(default)]
+    pub position: i32,
+    // Trivia quizzes only. Left out when false, so it can be hidden by clearing it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_correct: bool,
+    #[serde(default)]
+    pub media_id: Option<i32>, // a picture to go with it
+    // Personality quizzes only, how much it counts towards each result by 'num', in place of
+    // 'val'. Kept in answer_weight rather than a column, get_full_quiz fills it in.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub weights: BTreeMap<i32, i32>,
+    // Single choice questions only, where the quiz goes after this answer when it isn't just the
+    // next question. Kept in answer_rule, get_full_quiz fills it in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_question: Option<NextQuestion>,
 }
 
-#[derive(Serialize, Queryable, Debug)]
+// The answer table holds everything but the weights and rules, an answer comes out of it
+// without any
+impl<DB: Backend> Queryable<answer::SqlType, DB> for Answer
+where
+    (i32, String, i32, i32, i32, bool, Option<i32>): Queryable<answer::SqlType, DB>,
+{
+    type Row =
+        <(i32, String, i32, i32, i32, bool, Option<i32>) as Queryable<answer::SqlType, DB>>::Row;
+
+    fn build(row: Self::Row) -> Self {
+        let (id, description, val, q_id, position, is_correct, media_id) = Queryable::build(row);
+        Answer {
+            id,
+            description,
+            val,
+            q_id,
+            position,
+            is_correct,
+            media_id,
+            weights: BTreeMap::new(),
+            next_question: None,
+        }
+    }
+}
+
+#[derive(Serialize, Queryable, Clone, Debug, PartialEq)]
+pub struct AnswerWeight {
+    pub id: i32,
+    pub a_id: i32,
+    pub r_id: i32,
+    pub weight: i32,
+}
+
+// A 'next_q_id' of None ends the quiz
+#[derive(Serialize, Queryable, Clone, Debug, PartialEq)]
+pub struct AnswerRule {
+    pub id: i32,
+    pub a_id: i32,
+    pub next_q_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Queryable, Identifiable, Clone, Debug, PartialEq)]
+#[table_name = "question"]
 pub struct Question {
     pub id: i32,
     pub description: String,
     pub qz_id: i32,
+    #[serde(default)]
+    pub position: i32, // order within the quiz
+    // Versions saved before there were kinds only had single choice questions
+    #[serde(default = "single_choice")]
+    pub kind: QuestionKind,
+    #[serde(default)]
+    pub config: QuestionConfig,
+    #[serde(default)]
+    pub media_id: Option<i32>,
 }
 
-#[derive(Serialize, Queryable, QueryableByName, Debug)]
-#[table_name = "quiz"]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Quiz {
     pub id: i32,
     pub name: String,
     pub description: String,
     pub u_id: i32,
+    pub status: QuizStatus,
+    pub published_at: Option<NaiveDateTime>, // when it first went out, empty for drafts
+    // The quiz this one was copied from, cleared if that one is deleted. Versions saved before
+    // forks existed don't carry these two.
+    #[serde(default)]
+    pub forked_from_id: Option<i32>,
+    #[serde(default = "forks_allowed")]
+    pub allow_forks: bool, // whether other users may fork it, its author always can
+    #[serde(default = "personality")]
+    pub mode: QuizMode,
+    #[serde(default)]
+    pub media_id: Option<i32>, // a cover picture
+    #[serde(default)]
+    pub category: Option<String>,
+    // Sorted by name. Kept in quiz_tag, get_full_quiz and list_quizzes fill them in.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+type QuizRow = (
+    i32,
+    String,
+    String,
+    i32,
+    QuizStatus,
+    Option<NaiveDateTime>,
+    Option<i32>,
+    bool,
+    QuizMode,
+    Option<i32>,
+    Option<String>,
+);
+
+// The quiz table holds everything but the tags, a quiz comes out of it without any
+impl<DB: Backend> Queryable<quiz::SqlType, DB> for Quiz
+where
+    QuizRow: Queryable<quiz::SqlType, DB>,
+{
+    type Row = <QuizRow as Queryable<quiz::SqlType, DB>>::Row;
+
+    fn build(row: Self::Row) -> Self {
+        let (
+            id,
+            name,
+            description,
+            u_id,
+            status,
+            published_at,
+            forked_from_id,
+            allow_forks,
+            mode,
+            media_id,
+            category,
+        ) = QuizRow::build(row);
+        Quiz {
+            id,
+            name,
+            description,
+            u_id,
+            status,
+            published_at,
+            forked_from_id,
+            allow_forks,
+            mode,
+            media_id,
+            category,
+            tags: Vec::new(),
+        }
+    }
+}
+
+fn forks_allowed() -> bool {
+    true
+}
+
+fn personality() -> QuizMode {
+    QuizMode::Personality
+}
+
+// How a quiz lands on a result. A personality quiz adds up the answers given, each counting
+// towards the result its 'val' points at. A trivia quiz marks answers correct instead, and the
+// result is whichever one's band of percentages the share of correct questions falls in.
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Text"]
+pub enum QuizMode {
+    Personality,
+    Trivia,
+}
+
+impl QuizMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuizMode::Personality => "personality",
+            QuizMode::Trivia => "trivia",
+        }
+    }
+}
+
+impl<DB: Backend> ToSql<Text, DB> for QuizMode
+where
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+        self.as_str().to_sql(out)
+    }
+}
+
+impl<DB: Backend> FromSql<Text, DB> for QuizMode
+where
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+        match String::from_sql(bytes)?.as_str() {
+            "personality" => Ok(QuizMode::Personality),
+            "trivia" => Ok(QuizMode::Trivia),
+            other => Err(format!("unknown quiz mode '{}'", other).into()),
+        }
+    }
+}
+
+// Where picking an answer takes the taker, a question of the same quiz by its position or the
+// end of the quiz. Comes in as '{"question": 3}' or '"end"'.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NextQuestion {
+    Question(i32),
+    End,
+}
+
+impl NextQuestion {
+    // The position of the question that comes after the one at 'from', out of 'count', or None
+    // once the quiz is over. Without a rule, or with one for a question that isn't there, that's
+    // just the next one.
+    pub fn follow(rule: Option<NextQuestion>, from: usize, count: usize) -> Option<usize> {
+        match rule {
+            Some(NextQuestion::End) => None,
+            Some(NextQuestion::Question(to)) if to >= 0 && (to as usize) < count => {
+                Some(to as usize)
+            }
+            _ if from + 1 < count => Some(from + 1),
+            _ => None,
+        }
+    }
+}
+
+// Who gets to see a quiz. Drafts are private to their author, unlisted quizzes are reachable by
+// link but left out of listings, archived ones stay readable but take no new attempts.
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Text"]
+pub enum QuizStatus {
+    Draft,
+    Published,
+    Unlisted,
+    Archived,
+}
+
+impl QuizStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuizStatus::Draft => "draft",
+            QuizStatus::Published => "published",
+            QuizStatus::Unlisted => "unlisted",
+            QuizStatus::Archived => "archived",
+        }
+    }
+}
+
+impl<DB: Backend> ToSql<Text, DB> for QuizStatus
+where
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+        self.as_str().to_sql(out)
+    }
+}
+
+impl<DB: Backend> FromSql<Text, DB> for QuizStatus
+where
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+        match String::from_sql(bytes)?.as_str() {
+            "draft" => Ok(QuizStatus::Draft),
+            "published" => Ok(QuizStatus::Published),
+            "unlisted" => Ok(QuizStatus::Unlisted),
+            "archived" => Ok(QuizStatus::Archived),
+            other => Err(format!("unknown quiz status '{}'", other).into()),
+        }
+    }
+}
+
+// How a question is answered. Single choice, multi select and ranking questions are answered
+// by picking from their answers, a scale by choosing a whole number, with its answers splitting
+// the scale into bands, and free text in the taker's own words, which never counts towards a
+// result.
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum QuestionKind {
+    SingleChoice,
+    MultiSelect,
+    Scale,
+    Ranking,
+    FreeText,
+}
+
+fn single_choice() -> QuestionKind {
+    QuestionKind::SingleChoice
+}
+
+impl QuestionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuestionKind::SingleChoice => "single_choice",
+            QuestionKind::MultiSelect => "multi_select",
+            QuestionKind::Scale => "scale",
+            QuestionKind::Ranking => "ranking",
+            QuestionKind::FreeText => "free_text",
+        }
+    }
+
+    // Whether it's answered by picking some of its answers
+    pub fn takes_picks(self) -> bool {
+        match self {
+            QuestionKind::SingleChoice | QuestionKind::MultiSelect | QuestionKind::Ranking => true,
+            QuestionKind::Scale | QuestionKind::FreeText => false,
+        }
+    }
+
+    // The QuestionConfig fields this kind reads
+    pub fn config_fields(self) -> &'static [&'static str] {
+        match self {
+            QuestionKind::SingleChoice | QuestionKind::Ranking => &[],
+            QuestionKind::MultiSelect => &["min_picks", "max_picks"],
+            QuestionKind::Scale => &["min", "max", "min_label", "max_label"],
+            QuestionKind::FreeText => &["max_length"],
+        }
+    }
+}
+
+impl<DB: Backend> ToSql<Text, DB> for QuestionKind
+where
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+        self.as_str().to_sql(out)
+    }
+}
+
+impl<DB: Backend> FromSql<Text, DB> for QuestionKind
+where
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+        match String::from_sql(bytes)?.as_str() {
+            "single_choice" => Ok(QuestionKind::SingleChoice),
+            "multi_select" => Ok(QuestionKind::MultiSelect),
+            "scale" => Ok(QuestionKind::Scale),
+            "ranking" => Ok(QuestionKind::Ranking),
+            "free_text" => Ok(QuestionKind::FreeText),
+            other => Err(format!("unknown question kind '{}'", other).into()),
+        }
+    }
+}
+
+// The settings only some kinds of question use, stored as JSON. QuestionKind::config_fields says
+// which kind reads what, anything left out takes its default.
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+#[sql_type = "Text"]
+pub struct QuestionConfig {
+    // multi_select: how many answers a taker picks, from 1 up to all of them by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_picks: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_picks: Option<i32>,
+    // scale: the whole numbers a taker chooses from, both required, and what the two ends mean
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_label: Option<String>,
+    // free_text: how many characters an answer can have, at most what attempts can store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<i32>,
+}
+
+impl QuestionConfig {
+    // The names of the fields that are set, to hold against QuestionKind::config_fields
+    pub fn fields_set(&self) -> Vec<&'static str> {
+        let set = [
+            ("min_picks", self.min_picks.is_some()),
+            ("max_picks", self.max_picks.is_some()),
+            ("min", self.min.is_some()),
+            ("max", self.max.is_some()),
+            ("min_label", self.min_label.is_some()),
+            ("max_label", self.max_label.is_some()),
+            ("max_length", self.max_length.is_some()),
+        ];
+        set.iter()
+            .filter(|(_, is_set)| *is_set)
+            .map(|(field, _)| *field)
+            .collect()
+    }
+
+    // How many of 'answers' answers a multi_select question takes, at least and at most
+    pub fn pick_range(&self, answers: usize) -> (usize, usize) {
+        let min = self.min_picks.map_or(1, |picks| picks.max(0) as usize);
+        let max = self
+            .max_picks
+            .map_or(answers, |picks| picks.max(0) as usize);
+        (min, max)
+    }
+
+    // Which of 'bands' equal bands of a scale 'value' falls in, lowest first. Bands differ in
+    // size by one at most when the scale doesn't divide evenly. None if 'value' is off the
+    // scale, or there's no scale to speak of.
+    pub fn scale_band(&self, value: i32, bands: usize) -> Option<usize> {
+        let (min, max) = (self.min?, self.max?);
+        if bands == 0 || value < min || value > max {
+            return None;
+        }
+        let width = i64::from(max) - i64::from(min) + 1;
+        let offset = i64::from(value) - i64::from(min);
+        Some((offset * bands as i64 / width) as usize)
+    }
+}
+
+impl<DB: Backend> ToSql<Text, DB> for QuestionConfig
+where
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+        serde_json::to_string(self)?.as_str().to_sql(out)
+    }
+}
+
+impl<DB: Backend> FromSql<Text, DB> for QuestionConfig
+where
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+        Ok(serde_json::from_str(&String::from_sql(bytes)?)?)
+    }
 }
 
 //TODO make description optional
-#[derive(Serialize, Queryable, Debug)]
+// Results have no position column, 'num' is their order as well as what answers' 'val' points at.
+#[derive(Serialize, Deserialize, Queryable, Clone, Debug, PartialEq)]
 pub struct QuizResult {
     pub id: i32,
     pub num: i32, // the corresponding field to 'val' in Answer. 'val' is used to calculate which result 'num'.
     pub header: String,
     pub description: String,
     pub qz_id: i32,
+    // Trivia quizzes only, the percentages this result covers, both ends included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_percent: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_percent: Option<i32>,
+    #[serde(default)]
+    pub media_id: Option<i32>,
+}
+
+impl QuizResult {
+    pub fn covers(&self, percentage: i32) -> bool {
+        match (self.min_percent, self.max_percent) {
+            (Some(min), Some(max)) => min <= percentage && percentage <= max,
+            _ => false,
+        }
+    }
 }
 /* -------------------------------------------------------------------------- */
 /*         Models for data to be inserted. Adds calculated db fields.         */
@@ -47,6 +488,24 @@ pub struct NewAnswer {
     pub description: String,
     pub val: i32,
     pub q_id: i32,
+    pub position: i32,
+    pub is_correct: bool,
+    pub media_id: Option<i32>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "answer_rule"]
+pub struct NewAnswerRule {
+    pub a_id: i32,
+    pub next_q_id: Option<i32>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "answer_weight"]
+pub struct NewAnswerWeight {
+    pub a_id: i32,
+    pub r_id: i32,
+    pub weight: i32,
 }
 
 #[derive(Insertable, Debug)]
@@ -54,6 +513,10 @@ pub struct NewAnswer {
 pub struct NewQuestion {
     pub description: String,
     pub qz_id: i32,
+    pub position: i32,
+    pub kind: QuestionKind,
+    pub config: QuestionConfig,
+    pub media_id: Option<i32>,
 }
 
 #[derive(Insertable, Debug)]
@@ -62,14 +525,32 @@ pub struct NewQuiz {
     pub name: String,
     pub description: String,
     pub u_id: i32,
+    pub status: QuizStatus,
+    pub published_at: Option<NaiveDateTime>,
+    pub forked_from_id: Option<i32>,
+    pub allow_forks: bool,
+    pub mode: QuizMode,
+    pub media_id: Option<i32>,
+    pub category: Option<String>,
 }
 
-impl From<IncomingQuiz> for NewQuiz {
-    fn from(item: IncomingQuiz) -> Self {
+impl NewQuiz {
+    // Anything but a draft counts as published the moment it's stored
+    pub fn new(item: IncomingQuiz, u_id: i32, status: QuizStatus) -> Self {
         Self {
             name: item.name,
             description: item.description,
-            u_id: item.u_id,
+            u_id,
+            status,
+            published_at: match status {
+                QuizStatus::Draft => None,
+                _ => Some(Utc::now().naive_utc()),
+            },
+            forked_from_id: None,
+            allow_forks: true,
+            mode: item.mode,
+            media_id: item.media_id,
+            category: item.category,
         }
     }
 }
@@ -81,30 +562,71 @@ pub struct NewQuizResult {
     pub header: String,
     pub description: String,
     pub qz_id: i32,
+    pub min_percent: Option<i32>,
+    pub max_percent: Option<i32>,
+    pub media_id: Option<i32>,
 }
 /* -------------------------------------------------------------------------- */
 /*                          Models for incoming data                          */
 /* -------------------------------------------------------------------------- */
+// The optional ids are only read when editing an existing quiz, to tell records that should be
+// updated apart from ones that should be created.
 #[derive(Deserialize, Debug)]
 pub struct IncomingAnswer {
+    pub id: Option<i32>,
     pub description: String,
+    // Personality quizzes read 'val', or 'weights' when there are any, trivia quizzes 'is_correct'
+    #[serde(default)]
     pub val: i32,
+    #[serde(default)]
+    pub is_correct: bool,
+    // An uploaded picture, by its id
+    #[serde(default)]
+    pub media_id: Option<i32>,
+    // How much it counts towards each result, keyed by the result's position like 'val' is
+    #[serde(default)]
+    pub weights: BTreeMap<i32, i32>,
+    // Only single choice answers can branch, to a question by position like 'val' is
+    #[serde(default)]
+    pub next_question: Option<NextQuestion>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct IncomingQuestion {
+    pub id: Option<i32>,
     pub description: String,
+    #[serde(default = "single_choice")]
+    pub kind: QuestionKind,
+    #[serde(default)]
+    pub config: QuestionConfig,
+    #[serde(default)]
+    pub media_id: Option<i32>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct IncomingQuiz {
     pub name: String,
     pub description: String,
-    pub u_id: i32,
+    #[serde(default = "personality")]
+    pub mode: QuizMode,
+    #[serde(default)]
+    pub media_id: Option<i32>,
+    #[serde(default)]
+    pub category: Option<String>,
+    // Replaces whatever tags the quiz had, by name
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct IncomingQuizResult {
+    pub id: Option<i32>,
     pub header: String,
     pub description: String,
+    #[serde(default)]
+    pub min_percent: Option<i32>,
+    #[serde(default)]
+    pub max_percent: Option<i32>,
+    #[serde(default)]
+    pub media_id: Option<i32>,
 }
@@ -0,0 +1,56 @@
+use crate::schema::*;
+
+/* -------------------------------------------------------------------------- */
+/*        Models for query results, analagous to the records in the db.       */
+/* -------------------------------------------------------------------------- */
+
+#[derive(Serialize, Queryable, Clone, Debug, PartialEq)]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+}
+
+// A tag by name, along with the quiz carrying it
+#[derive(Queryable, Clone, Debug, PartialEq)]
+pub struct QuizTag {
+    pub qz_id: i32,
+    pub name: String,
+}
+
+// How many published quizzes carry a tag
+#[derive(Serialize, Queryable, Clone, Debug, PartialEq)]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
+}
+
+// How many quizzes out of a listing share a tag or category, for narrowing the listing down
+#[derive(Serialize, Queryable, Clone, Debug, PartialEq)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+// Both kinds of facet counts, each with the most common first
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Facets {
+    pub tags: Vec<FacetCount>,
+    pub categories: Vec<FacetCount>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*         Models for data to be inserted. Adds calculated db fields.         */
+/* -------------------------------------------------------------------------- */
+
+#[derive(Insertable, Debug)]
+#[table_name = "tag"]
+pub struct NewTag {
+    pub name: String,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "quiz_tag"]
+pub struct NewQuizTag {
+    pub qz_id: i32,
+    pub tag_id: i32,
+}
@@ -0,0 +1,56 @@
+use crate::schema::*;
+use chrono::NaiveDateTime;
+
+/* -------------------------------------------------------------------------- */
+/*        Models for query results, analagous to the records in the db.       */
+/* -------------------------------------------------------------------------- */
+
+// One save of a quiz tree. 'snapshot' is the FullQuiz as it stood right after the save, as JSON.
+#[derive(Queryable, Clone, Debug)]
+pub struct QuizVersion {
+    pub id: i32,
+    pub qz_id: i32,
+    pub num: i32, // counts up from 1 for each quiz
+    pub u_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub summary: String,
+    pub snapshot: String,
+}
+
+// A version without its snapshot, for listing a quiz's history.
+#[derive(Serialize, Queryable, Clone, Debug)]
+pub struct QuizVersionInfo {
+    pub id: i32,
+    pub qz_id: i32,
+    pub num: i32,
+    pub u_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub summary: String,
+}
+
+impl From<QuizVersion> for QuizVersionInfo {
+    fn from(version: QuizVersion) -> Self {
+        Self {
+            id: version.id,
+            qz_id: version.qz_id,
+            num: version.num,
+            u_id: version.u_id,
+            created_at: version.created_at,
+            summary: version.summary,
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*         Models for data to be inserted. Adds calculated db fields.         */
+/* -------------------------------------------------------------------------- */
+
+#[derive(Insertable, Debug)]
+#[table_name = "quiz_version"]
+pub struct NewQuizVersion {
+    pub qz_id: i32,
+    pub num: i32,
+    pub u_id: Option<i32>,
+    pub summary: String,
+    pub snapshot: String,
+}
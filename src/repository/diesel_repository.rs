@@ -0,0 +1,839 @@
+// Implements Repository for a diesel connection type. The queries are the same for every
+// backend diesel supports, so only the bits that differ are passed in:
+//   - last_insert_id: fn(&Conn) -> QueryResult<i32>, the id of the row just inserted
+// The models and diesel's prelude have to be in scope wherever it's used.
+macro_rules! diesel_repository {
+    ($conn:ty, $backend:ty, last_insert_id: $last_insert_id:path) => {
+        // The quizzes a listing covers, before it's sorted and paged
+        fn filtered_quizzes<'a>(
+            filter: &'a crate::repository::QuizFilter,
+            facets: &'a crate::repository::QuizFacets,
+        ) -> crate::schema::quiz::BoxedQuery<'a, $backend> {
+            use crate::repository::QuizFilter;
+            use crate::schema::quiz::dsl::{category, id, quiz as quiz_table, status, u_id};
+            use crate::schema::{quiz_tag, tag};
+
+            let query = quiz_table.into_boxed::<$backend>();
+            let mut query = match filter {
+                QuizFilter::All => query,
+                QuizFilter::Published => query.filter(status.eq(QuizStatus::Published)),
+                QuizFilter::ByUser(user_id) => query.filter(u_id.eq(*user_id)),
+                QuizFilter::Among(quiz_ids) => query
+                    .filter(status.eq(QuizStatus::Published))
+                    .filter(id.eq_any(quiz_ids)),
+            };
+            if let Some(tag_name) = &facets.tag {
+                query = query.filter(
+                    id.eq_any(
+                        quiz_tag::table
+                            .inner_join(tag::table)
+                            .filter(tag::name.eq(tag_name))
+                            .select(quiz_tag::qz_id),
+                    ),
+                );
+            }
+            if let Some(category_name) = &facets.category {
+                query = query.filter(category.eq(category_name));
+            }
+            query
+        }
+
+        impl crate::repository::Repository for $conn {
+            fn transaction(
+                &self,
+                f: &mut dyn FnMut() -> diesel::result::QueryResult<()>,
+            ) -> diesel::result::QueryResult<()> {
+                diesel::Connection::transaction(self, || f())
+            }
+
+            /* ---------------------------- Quizzes ----------------------------- */
+            fn get_quiz(&self, quiz_id: i32) -> diesel::result::QueryResult<Quiz> {
+                use crate::schema::quiz::dsl::quiz as quiz_table;
+                quiz_table.find(quiz_id).first::<Quiz>(self)
+            }
+
+            fn list_quizzes(
+                &self,
+                filter: &crate::repository::QuizFilter,
+                facets: &crate::repository::QuizFacets,
+                page: &crate::routing::pagination::PageRequest,
+            ) -> diesel::result::QueryResult<(
+                Vec<Quiz>,
+                i64,
+                Option<crate::routing::pagination::Cursor>,
+            )> {
+                use crate::routing::pagination::{Cursor, SortOrder};
+                use crate::schema::quiz::dsl::{id, name};
+                use diesel::dsl::sql;
+                use diesel::sql_types::BigInt;
+
+                let filtered = || filtered_quizzes(filter, facets);
+                let total: i64 = filtered().count().get_result(self)?;
+                let attempts = || {
+                    sql::<BigInt>("(SELECT COUNT(*) FROM attempt WHERE attempt.qz_id = quiz.id)")
+                };
+
+                // The page starts right after the last quiz of the one before it. Every order
+                // ends on the id, so that's always one spot.
+                let query = match (page.sort, &page.after) {
+                    (SortOrder::Name, Some(Cursor::Name(last_name, last_id))) => filtered()
+                        .filter(
+                            name.gt(last_name)
+                                .or(name.eq(last_name).and(id.gt(*last_id))),
+                        ),
+                    (SortOrder::Newest, Some(Cursor::Newest(last_id))) => {
+                        filtered().filter(id.lt(*last_id))
+                    }
+                    (SortOrder::Popular, Some(Cursor::Popular(last_attempts, last_id))) => {
+                        filtered().filter(
+                            attempts()
+                                .lt(*last_attempts)
+                                .or(attempts().eq(*last_attempts).and(id.lt(*last_id))),
+                        )
+                    }
+                    _ => filtered(),
+                };
+                let query = match page.sort {
+                    SortOrder::Name => query.order((name.asc(), id.asc())),
+                    SortOrder::Newest => query.order(id.desc()),
+                    SortOrder::Popular => query.order((attempts().desc(), id.desc())),
+                };
+                // One more than fits, to tell whether there's a page after this one
+                let mut rows = query
+                    .select((crate::schema::quiz::all_columns, attempts()))
+                    .limit(page.limit + 1)
+                    .load::<(Quiz, i64)>(self)?;
+                let next = if rows.len() as i64 > page.limit {
+                    rows.truncate(page.limit as usize);
+                    rows.last()
+                        .map(|(qz, count)| Cursor::after(page.sort, qz, *count))
+                } else {
+                    None
+                };
+                Ok((rows.into_iter().map(|(qz, _)| qz).collect(), total, next))
+            }
+
+            fn count_facets(
+                &self,
+                filter: &crate::repository::QuizFilter,
+                facets: &crate::repository::QuizFacets,
+            ) -> diesel::result::QueryResult<Facets> {
+                use crate::schema::quiz::dsl::{category, id};
+                use crate::schema::{quiz_tag, tag};
+                use diesel::dsl::sql;
+                use diesel::sql_types::BigInt;
+
+                let count = || sql::<BigInt>("COUNT(*)");
+
+                let tags = quiz_tag::table
+                    .inner_join(tag::table)
+                    .filter(quiz_tag::qz_id.eq_any(filtered_quizzes(filter, facets).select(id)))
+                    .group_by(tag::name)
+                    .select((tag::name, count()))
+                    .order((count().desc(), tag::name.asc()))
+                    .load::<FacetCount>(self)?;
+                let categories = filtered_quizzes(filter, facets)
+                    .filter(category.is_not_null())
+                    .group_by(category)
+                    .select((category, count()))
+                    .order((count().desc(), category.asc()))
+                    .load::<(Option<String>, i64)>(self)?
+                    .into_iter()
+                    .filter_map(|(value, count)| Some(FacetCount { value: value?, count }))
+                    .collect();
+                Ok(Facets { tags, categories })
+            }
+
+            fn insert_quiz(&self, new_quiz: NewQuiz) -> diesel::result::QueryResult<i32> {
+                use crate::schema::quiz::dsl::quiz as quiz_table;
+                diesel::insert_into(quiz_table)
+                    .values(new_quiz)
+                    .execute(self)?;
+                $last_insert_id(self)
+            }
+
+            fn update_quiz(
+                &self,
+                quiz_id: i32,
+                new_name: &str,
+                new_description: &str,
+                new_mode: QuizMode,
+                new_category: Option<&str>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::quiz::dsl::{
+                    category, description, mode, name, quiz as quiz_table,
+                };
+                diesel::update(quiz_table.find(quiz_id))
+                    .set((
+                        name.eq(new_name),
+                        description.eq(new_description),
+                        mode.eq(new_mode),
+                        category.eq(new_category),
+                    ))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn update_quiz_status(
+                &self,
+                quiz_id: i32,
+                new_status: QuizStatus,
+                new_published_at: Option<chrono::NaiveDateTime>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::quiz::dsl::{published_at, quiz as quiz_table, status};
+                diesel::update(quiz_table.find(quiz_id))
+                    .set((status.eq(new_status), published_at.eq(new_published_at)))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn update_quiz_forking(
+                &self,
+                quiz_id: i32,
+                forks_allowed: bool,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::quiz::dsl::{allow_forks, quiz as quiz_table};
+                diesel::update(quiz_table.find(quiz_id))
+                    .set(allow_forks.eq(forks_allowed))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn set_quiz_media(
+                &self,
+                quiz_id: i32,
+                new_media_id: Option<i32>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::quiz::dsl::{media_id, quiz as quiz_table};
+                diesel::update(quiz_table.find(quiz_id))
+                    .set(media_id.eq(new_media_id))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn count_forks(&self, quiz_id: i32) -> diesel::result::QueryResult<i64> {
+                use crate::schema::quiz::dsl::{forked_from_id, quiz as quiz_table};
+                quiz_table
+                    .filter(forked_from_id.eq(quiz_id))
+                    .count()
+                    .get_result(self)
+            }
+
+            fn delete_quiz(&self, quiz_id: i32) -> diesel::result::QueryResult<()> {
+                use crate::schema::quiz::dsl::quiz as quiz_table;
+                diesel::delete(quiz_table.find(quiz_id)).execute(self)?;
+                Ok(())
+            }
+
+            fn get_questions(&self, quiz_id: i32) -> diesel::result::QueryResult<Vec<Question>> {
+                use crate::schema::question::dsl::{
+                    id, position, question as question_table, qz_id,
+                };
+                question_table
+                    .filter(qz_id.eq(quiz_id))
+                    .order((position.asc(), id.asc()))
+                    .load::<Question>(self)
+            }
+
+            fn insert_question(
+                &self,
+                new_question: NewQuestion,
+            ) -> diesel::result::QueryResult<i32> {
+                use crate::schema::question::dsl::question as question_table;
+                diesel::insert_into(question_table)
+                    .values(new_question)
+                    .execute(self)?;
+                $last_insert_id(self)
+            }
+
+            fn update_question(
+                &self,
+                question_id: i32,
+                new_description: &str,
+                new_kind: QuestionKind,
+                new_config: &QuestionConfig,
+                new_position: i32,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::question::dsl::{
+                    config, description, kind, position, question as question_table,
+                };
+                diesel::update(question_table.find(question_id))
+                    .set((
+                        description.eq(new_description),
+                        kind.eq(new_kind),
+                        config.eq(new_config),
+                        position.eq(new_position),
+                    ))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn move_question(
+                &self,
+                question_id: i32,
+                new_position: i32,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::question::dsl::{position, question as question_table};
+                diesel::update(question_table.find(question_id))
+                    .set(position.eq(new_position))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn set_question_media(
+                &self,
+                question_id: i32,
+                new_media_id: Option<i32>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::question::dsl::{media_id, question as question_table};
+                diesel::update(question_table.find(question_id))
+                    .set(media_id.eq(new_media_id))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn delete_question(&self, question_id: i32) -> diesel::result::QueryResult<()> {
+                use crate::schema::question::dsl::question as question_table;
+                diesel::delete(question_table.find(question_id)).execute(self)?;
+                Ok(())
+            }
+
+            fn get_answers(
+                &self,
+                question_ids: &[i32],
+            ) -> diesel::result::QueryResult<Vec<Answer>> {
+                use crate::schema::answer::dsl::{answer as answer_table, id, position, q_id};
+                answer_table
+                    .filter(q_id.eq_any(question_ids))
+                    .order((position.asc(), id.asc()))
+                    .load::<Answer>(self)
+            }
+
+            fn insert_answers(
+                &self,
+                new_answers: Vec<NewAnswer>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer::dsl::answer as answer_table;
+                diesel::insert_into(answer_table)
+                    .values(new_answers)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn update_answer(
+                &self,
+                answer_id: i32,
+                new_description: &str,
+                new_val: i32,
+                new_is_correct: bool,
+                new_position: i32,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer::dsl::{
+                    answer as answer_table, description, is_correct, position, val,
+                };
+                diesel::update(answer_table.find(answer_id))
+                    .set((
+                        description.eq(new_description),
+                        val.eq(new_val),
+                        is_correct.eq(new_is_correct),
+                        position.eq(new_position),
+                    ))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn move_answer(
+                &self,
+                answer_id: i32,
+                new_position: i32,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer::dsl::{answer as answer_table, position};
+                diesel::update(answer_table.find(answer_id))
+                    .set(position.eq(new_position))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn set_answer_media(
+                &self,
+                answer_id: i32,
+                new_media_id: Option<i32>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer::dsl::{media_id, answer as answer_table};
+                diesel::update(answer_table.find(answer_id))
+                    .set(media_id.eq(new_media_id))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn delete_answer(&self, answer_id: i32) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer::dsl::answer as answer_table;
+                diesel::delete(answer_table.find(answer_id)).execute(self)?;
+                Ok(())
+            }
+
+            fn get_answer_weights(
+                &self,
+                answer_ids: &[i32],
+            ) -> diesel::result::QueryResult<Vec<AnswerWeight>> {
+                use crate::schema::answer_weight::dsl::{
+                    a_id, answer_weight as answer_weight_table, id,
+                };
+                answer_weight_table
+                    .filter(a_id.eq_any(answer_ids))
+                    .order(id.asc())
+                    .load::<AnswerWeight>(self)
+            }
+
+            fn insert_answer_weights(
+                &self,
+                new_weights: Vec<NewAnswerWeight>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer_weight::dsl::answer_weight as answer_weight_table;
+                diesel::insert_into(answer_weight_table)
+                    .values(new_weights)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn delete_answer_weights(
+                &self,
+                answer_ids: &[i32],
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer_weight::dsl::{a_id, answer_weight as answer_weight_table};
+                diesel::delete(answer_weight_table.filter(a_id.eq_any(answer_ids)))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn get_answer_rules(
+                &self,
+                answer_ids: &[i32],
+            ) -> diesel::result::QueryResult<Vec<AnswerRule>> {
+                use crate::schema::answer_rule::dsl::{
+                    a_id, answer_rule as answer_rule_table, id,
+                };
+                answer_rule_table
+                    .filter(a_id.eq_any(answer_ids))
+                    .order(id.asc())
+                    .load::<AnswerRule>(self)
+            }
+
+            fn insert_answer_rules(
+                &self,
+                new_rules: Vec<NewAnswerRule>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer_rule::dsl::answer_rule as answer_rule_table;
+                diesel::insert_into(answer_rule_table)
+                    .values(new_rules)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn delete_answer_rules(
+                &self,
+                answer_ids: &[i32],
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::answer_rule::dsl::{a_id, answer_rule as answer_rule_table};
+                diesel::delete(answer_rule_table.filter(a_id.eq_any(answer_ids)))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn get_results(&self, quiz_id: i32) -> diesel::result::QueryResult<Vec<QuizResult>> {
+                use crate::schema::result::dsl::{num, qz_id, result as result_table};
+                result_table
+                    .filter(qz_id.eq(quiz_id))
+                    .order(num.asc())
+                    .load::<QuizResult>(self)
+            }
+
+            fn get_results_by_ids(
+                &self,
+                result_ids: &[i32],
+            ) -> diesel::result::QueryResult<Vec<QuizResult>> {
+                use crate::schema::result::dsl::{id, result as result_table};
+                result_table
+                    .filter(id.eq_any(result_ids))
+                    .load::<QuizResult>(self)
+            }
+
+            fn insert_results(
+                &self,
+                new_results: Vec<NewQuizResult>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::result::dsl::result as result_table;
+                diesel::insert_into(result_table)
+                    .values(new_results)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn update_result(
+                &self,
+                result_id: i32,
+                new_num: i32,
+                new_header: &str,
+                new_description: &str,
+                new_min_percent: Option<i32>,
+                new_max_percent: Option<i32>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::result::dsl::{
+                    description, header, max_percent, min_percent, num, result as result_table,
+                };
+                diesel::update(result_table.find(result_id))
+                    .set((
+                        num.eq(new_num),
+                        header.eq(new_header),
+                        description.eq(new_description),
+                        min_percent.eq(new_min_percent),
+                        max_percent.eq(new_max_percent),
+                    ))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn set_result_media(
+                &self,
+                result_id: i32,
+                new_media_id: Option<i32>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::result::dsl::{media_id, result as result_table};
+                diesel::update(result_table.find(result_id))
+                    .set(media_id.eq(new_media_id))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn delete_result(&self, result_id: i32) -> diesel::result::QueryResult<()> {
+                use crate::schema::result::dsl::result as result_table;
+                diesel::delete(result_table.find(result_id)).execute(self)?;
+                Ok(())
+            }
+
+            /* ------------------------------ Tags ------------------------------ */
+            fn get_tags_by_names(
+                &self,
+                names: &[String],
+            ) -> diesel::result::QueryResult<Vec<Tag>> {
+                use crate::schema::tag::dsl::{name, tag as tag_table};
+                tag_table
+                    .filter(name.eq_any(names))
+                    .order(name.asc())
+                    .load::<Tag>(self)
+            }
+
+            fn insert_tags(&self, new_tags: Vec<NewTag>) -> diesel::result::QueryResult<()> {
+                use crate::schema::tag::dsl::tag as tag_table;
+                diesel::insert_into(tag_table)
+                    .values(new_tags)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn get_quiz_tags(
+                &self,
+                quiz_ids: &[i32],
+            ) -> diesel::result::QueryResult<Vec<QuizTag>> {
+                use crate::schema::{quiz_tag, tag};
+                quiz_tag::table
+                    .inner_join(tag::table)
+                    .filter(quiz_tag::qz_id.eq_any(quiz_ids))
+                    .order((quiz_tag::qz_id.asc(), tag::name.asc()))
+                    .select((quiz_tag::qz_id, tag::name))
+                    .load::<QuizTag>(self)
+            }
+
+            fn insert_quiz_tags(
+                &self,
+                new_quiz_tags: Vec<NewQuizTag>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::quiz_tag::dsl::quiz_tag;
+                diesel::insert_into(quiz_tag)
+                    .values(new_quiz_tags)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn delete_quiz_tags(&self, quiz_id: i32) -> diesel::result::QueryResult<()> {
+                use crate::schema::quiz_tag::dsl::{quiz_tag, qz_id};
+                diesel::delete(quiz_tag.filter(qz_id.eq(quiz_id))).execute(self)?;
+                Ok(())
+            }
+
+            fn count_tags(&self) -> diesel::result::QueryResult<Vec<TagCount>> {
+                use crate::schema::{quiz, quiz_tag, tag};
+                use diesel::dsl::sql;
+                use diesel::sql_types::BigInt;
+
+                let count = || sql::<BigInt>("COUNT(*)");
+                quiz_tag::table
+                    .inner_join(tag::table)
+                    .inner_join(quiz::table)
+                    .filter(quiz::status.eq(QuizStatus::Published))
+                    .group_by(tag::name)
+                    .select((tag::name, count()))
+                    .order((count().desc(), tag::name.asc()))
+                    .load::<TagCount>(self)
+            }
+
+            /* ----------------------------- Media ------------------------------ */
+            fn insert_media(&self, new_media: NewMedia) -> diesel::result::QueryResult<i32> {
+                use crate::schema::media::dsl::media as media_table;
+                diesel::insert_into(media_table)
+                    .values(new_media)
+                    .execute(self)?;
+                $last_insert_id(self)
+            }
+
+            fn get_media(&self, media_id: i32) -> diesel::result::QueryResult<Media> {
+                use crate::schema::media::dsl::media as media_table;
+                media_table.find(media_id).first::<Media>(self)
+            }
+
+            fn get_media_by_ids(
+                &self,
+                media_ids: &[i32],
+            ) -> diesel::result::QueryResult<Vec<Media>> {
+                use crate::schema::media::dsl::{id, media as media_table};
+                media_table
+                    .filter(id.eq_any(media_ids))
+                    .order(id.asc())
+                    .load::<Media>(self)
+            }
+
+            /* ---------------------------- Versions ---------------------------- */
+            fn insert_version(
+                &self,
+                new_version: NewQuizVersion,
+            ) -> diesel::result::QueryResult<i32> {
+                use crate::schema::quiz_version::dsl::quiz_version as quiz_version_table;
+                diesel::insert_into(quiz_version_table)
+                    .values(new_version)
+                    .execute(self)?;
+                $last_insert_id(self)
+            }
+
+            fn get_versions(
+                &self,
+                quiz_id: i32,
+            ) -> diesel::result::QueryResult<Vec<QuizVersionInfo>> {
+                use crate::schema::quiz_version::dsl::{
+                    created_at, id, num, quiz_version as quiz_version_table, qz_id, summary, u_id,
+                };
+                quiz_version_table
+                    .select((id, qz_id, num, u_id, created_at, summary))
+                    .filter(qz_id.eq(quiz_id))
+                    .order(num.desc())
+                    .load::<QuizVersionInfo>(self)
+            }
+
+            fn get_version(
+                &self,
+                quiz_id: i32,
+                version_num: i32,
+            ) -> diesel::result::QueryResult<QuizVersion> {
+                use crate::schema::quiz_version::dsl::{
+                    num, quiz_version as quiz_version_table, qz_id,
+                };
+                quiz_version_table
+                    .filter(qz_id.eq(quiz_id))
+                    .filter(num.eq(version_num))
+                    .first::<QuizVersion>(self)
+            }
+
+            /* ---------------------------- Attempts ---------------------------- */
+            fn insert_attempt(&self, new_attempt: NewAttempt) -> diesel::result::QueryResult<i32> {
+                use crate::schema::attempt::dsl::attempt as attempt_table;
+                diesel::insert_into(attempt_table)
+                    .values(new_attempt)
+                    .execute(self)?;
+                $last_insert_id(self)
+            }
+
+            fn insert_attempt_answers(
+                &self,
+                new_answers: Vec<NewAttemptAnswer>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::attempt_answer::dsl::attempt_answer as attempt_answer_table;
+                diesel::insert_into(attempt_answer_table)
+                    .values(new_answers)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn get_attempt(&self, attempt_id: i32) -> diesel::result::QueryResult<Attempt> {
+                use crate::schema::attempt::dsl::attempt as attempt_table;
+                attempt_table.find(attempt_id).first::<Attempt>(self)
+            }
+
+            fn get_attempts_by_user_id(
+                &self,
+                user_id: i32,
+            ) -> diesel::result::QueryResult<Vec<Attempt>> {
+                use crate::schema::attempt::dsl::{attempt as attempt_table, created_at, id, u_id};
+                attempt_table
+                    .filter(u_id.eq(user_id))
+                    .order((created_at.desc(), id.desc()))
+                    .load::<Attempt>(self)
+            }
+
+            fn get_attempt_answers(
+                &self,
+                attempt_ids: &[i32],
+            ) -> diesel::result::QueryResult<Vec<AttemptAnswer>> {
+                use crate::schema::attempt_answer::dsl::{
+                    at_id, attempt_answer as attempt_answer_table, id,
+                };
+                attempt_answer_table
+                    .filter(at_id.eq_any(attempt_ids))
+                    .order(id.asc())
+                    .load::<AttemptAnswer>(self)
+            }
+
+            fn insert_attempt_responses(
+                &self,
+                new_responses: Vec<NewAttemptResponse>,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::attempt_response::dsl::attempt_response as attempt_response_table;
+                diesel::insert_into(attempt_response_table)
+                    .values(new_responses)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn get_attempt_responses(
+                &self,
+                attempt_ids: &[i32],
+            ) -> diesel::result::QueryResult<Vec<AttemptResponse>> {
+                use crate::schema::attempt_response::dsl::{
+                    at_id, attempt_response as attempt_response_table, id,
+                };
+                attempt_response_table
+                    .filter(at_id.eq_any(attempt_ids))
+                    .order(id.asc())
+                    .load::<AttemptResponse>(self)
+            }
+
+            /* ------------------------- Users and auth ------------------------- */
+            fn insert_user(&self, new_user: NewUser) -> diesel::result::QueryResult<i32> {
+                use crate::schema::user::dsl::user as user_table;
+                diesel::insert_into(user_table)
+                    .values(new_user)
+                    .execute(self)?;
+                $last_insert_id(self)
+            }
+
+            fn fetch_user_by_id(&self, user_id: i32) -> diesel::result::QueryResult<Option<User>> {
+                use crate::schema::user::dsl::user as user_table;
+                user_table.find(user_id).first::<User>(self).optional()
+            }
+
+            fn fetch_user_by_email(
+                &self,
+                input_email: &str,
+            ) -> diesel::result::QueryResult<Option<User>> {
+                use crate::schema::user::dsl::{email, user as user_table};
+                user_table
+                    .filter(email.eq(input_email))
+                    .first::<User>(self)
+                    .optional()
+            }
+
+            fn insert_auth_info(
+                &self,
+                new_auth_info: NewAuthInfo,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::auth_info::dsl::auth_info as auth_info_table;
+                diesel::insert_into(auth_info_table)
+                    .values(new_auth_info)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn fetch_auth_info_by_user_id(
+                &self,
+                user_id: i32,
+            ) -> diesel::result::QueryResult<Option<AuthInfo>> {
+                use crate::schema::auth_info::dsl::{auth_info as auth_info_table, uid};
+                auth_info_table
+                    .filter(uid.eq(user_id))
+                    .first::<AuthInfo>(self)
+                    .optional()
+            }
+
+            fn update_password_hash(
+                &self,
+                user_id: i32,
+                new_hash: &str,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::auth_info::dsl::{
+                    auth_info as auth_info_table, password_hash, uid,
+                };
+                diesel::update(auth_info_table.filter(uid.eq(user_id)))
+                    .set(password_hash.eq(new_hash))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            /* ---------------------------- Sessions ---------------------------- */
+            fn insert_session(&self, new_session: NewSession) -> diesel::result::QueryResult<()> {
+                use crate::schema::session::dsl::session as session_table;
+                diesel::insert_into(session_table)
+                    .values(new_session)
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn fetch_session(
+                &self,
+                session_id: &str,
+            ) -> diesel::result::QueryResult<Option<Session>> {
+                use crate::schema::session::dsl::session as session_table;
+                session_table
+                    .find(session_id)
+                    .first::<Session>(self)
+                    .optional()
+            }
+
+            fn touch_session(
+                &self,
+                session_id: &str,
+                now: chrono::NaiveDateTime,
+            ) -> diesel::result::QueryResult<()> {
+                use crate::schema::session::dsl::{last_seen, session as session_table};
+                diesel::update(session_table.find(session_id))
+                    .set(last_seen.eq(now))
+                    .execute(self)?;
+                Ok(())
+            }
+
+            fn fetch_sessions_by_user_id(
+                &self,
+                user_id: i32,
+                now: chrono::NaiveDateTime,
+            ) -> diesel::result::QueryResult<Vec<Session>> {
+                use crate::schema::session::dsl::{
+                    expires_at, last_seen, session as session_table, u_id,
+                };
+                session_table
+                    .filter(u_id.eq(user_id))
+                    .filter(expires_at.gt(now))
+                    .order(last_seen.desc())
+                    .load::<Session>(self)
+            }
+
+            fn delete_session(&self, session_id: &str) -> diesel::result::QueryResult<()> {
+                use crate::schema::session::dsl::session as session_table;
+                diesel::delete(session_table.find(session_id)).execute(self)?;
+                Ok(())
+            }
+
+            fn delete_sessions_by_user_id(&self, user_id: i32) -> diesel::result::QueryResult<()> {
+                use crate::schema::session::dsl::{session as session_table, u_id};
+                diesel::delete(session_table.filter(u_id.eq(user_id))).execute(self)?;
+                Ok(())
+            }
+        }
+    };
+}
@@ -0,0 +1,1187 @@
+use super::{QuizFacets, QuizFilter, Repository};
+use crate::models::attempt_models::*;
+use crate::models::auth_models::*;
+use crate::models::media_models::*;
+use crate::models::quiz_models::*;
+use crate::models::tag_models::*;
+use crate::models::version_models::*;
+use crate::routing::pagination::{Cursor, PageRequest, SortOrder};
+use chrono::{NaiveDateTime, Timelike, Utc};
+use diesel::result::{DatabaseErrorKind, Error, QueryResult};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+// Repository kept entirely in memory, for tests that shouldn't need a database. It mirrors the
+// schema's behaviour: ids count up per table, foreign keys are checked on insert, and deletes
+// cascade (or null out references) the same way the migrations' foreign keys do.
+#[derive(Default)]
+pub struct MemoryRepository {
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Clone, Default)]
+struct MemoryState {
+    last_ids: HashMap<&'static str, i32>,
+    users: HashMap<i32, User>,
+    auth_infos: HashMap<i32, AuthInfo>,
+    sessions: HashMap<String, Session>,
+    quizzes: HashMap<i32, Quiz>,
+    questions: HashMap<i32, Question>,
+    answers: HashMap<i32, Answer>,
+    answer_weights: HashMap<i32, AnswerWeight>,
+    answer_rules: HashMap<i32, AnswerRule>,
+    media: HashMap<i32, Media>,
+    tags: HashMap<i32, Tag>,
+    quiz_tags: BTreeSet<(i32, i32)>, // (qz_id, tag_id)
+    results: HashMap<i32, QuizResult>,
+    versions: HashMap<i32, QuizVersion>,
+    attempts: HashMap<i32, Attempt>,
+    attempt_answers: HashMap<i32, AttemptAnswer>,
+    attempt_responses: HashMap<i32, AttemptResponse>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MemoryState> {
+        self.state.lock().expect("memory repository lock poisoned")
+    }
+}
+
+fn foreign_key_violation(table: &str, column: &str) -> Error {
+    Error::DatabaseError(
+        DatabaseErrorKind::ForeignKeyViolation,
+        Box::new(format!("{}.{} refers to a missing row", table, column)),
+    )
+}
+
+// Sorted the way the diesel backends order their rows, which is by id unless there's a position
+fn sorted_by_key<T: Clone, K: Ord, F: Fn(&T) -> K>(rows: Vec<&T>, key: F) -> Vec<T> {
+    let mut rows: Vec<T> = rows.into_iter().cloned().collect();
+    rows.sort_by_key(|row| key(row));
+    rows
+}
+
+// Most common first, then alphabetical, the way the diesel backends order facet counts
+fn counted<I: IntoIterator<Item = String>>(values: I) -> Vec<FacetCount> {
+    let mut counts: BTreeMap<String, i64> = BTreeMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    let mut counts: Vec<FacetCount> = counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect();
+    counts.sort_by_key(|facet| Reverse(facet.count));
+    counts
+}
+
+impl MemoryState {
+    fn next_id(&mut self, table: &'static str) -> i32 {
+        let last_id = self.last_ids.entry(table).or_insert(0);
+        *last_id += 1;
+        *last_id
+    }
+
+    fn tag_names(&self, quiz_id: i32) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .quiz_tags
+            .iter()
+            .filter(|(qz_id, _)| *qz_id == quiz_id)
+            .filter_map(|(_, tag_id)| self.tags.get(tag_id))
+            .map(|tag| tag.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn matching_quizzes(&self, filter: &QuizFilter, facets: &QuizFacets) -> Vec<&Quiz> {
+        self.quizzes
+            .values()
+            .filter(|qz| match filter {
+                QuizFilter::All => true,
+                QuizFilter::Published => qz.status == QuizStatus::Published,
+                QuizFilter::ByUser(user_id) => qz.u_id == *user_id,
+                QuizFilter::Among(quiz_ids) => {
+                    qz.status == QuizStatus::Published && quiz_ids.contains(&qz.id)
+                }
+            })
+            .filter(|qz| match &facets.tag {
+                Some(tag) => self.tag_names(qz.id).contains(tag),
+                None => true,
+            })
+            .filter(|qz| match &facets.category {
+                Some(category) => qz.category.as_ref() == Some(category),
+                None => true,
+            })
+            .collect()
+    }
+
+    // The foreign key check for the 'media_id' every table showing a picture has
+    fn check_media(&self, table: &'static str, media_id: Option<i32>) -> QueryResult<()> {
+        match media_id {
+            Some(media_id) if !self.media.contains_key(&media_id) => {
+                Err(foreign_key_violation(table, "media_id"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /* ---------- Cascades, matching the ON DELETE clauses in the schema ---------- */
+    fn remove_quiz(&mut self, quiz_id: i32) {
+        self.quizzes.remove(&quiz_id);
+        self.quiz_tags.retain(|(qz_id, _)| *qz_id != quiz_id);
+        for fork in self.quizzes.values_mut() {
+            if fork.forked_from_id == Some(quiz_id) {
+                fork.forked_from_id = None;
+            }
+        }
+        let question_ids: Vec<i32> = self
+            .questions
+            .values()
+            .filter(|qs| qs.qz_id == quiz_id)
+            .map(|qs| qs.id)
+            .collect();
+        for question_id in question_ids {
+            self.remove_question(question_id);
+        }
+        let result_ids: Vec<i32> = self
+            .results
+            .values()
+            .filter(|res| res.qz_id == quiz_id)
+            .map(|res| res.id)
+            .collect();
+        for result_id in result_ids {
+            self.remove_result(result_id);
+        }
+        let attempt_ids: Vec<i32> = self
+            .attempts
+            .values()
+            .filter(|at| at.qz_id == quiz_id)
+            .map(|at| at.id)
+            .collect();
+        for attempt_id in attempt_ids {
+            self.remove_attempt(attempt_id);
+        }
+        self.versions.retain(|_, version| version.qz_id != quiz_id);
+    }
+
+    fn remove_question(&mut self, question_id: i32) {
+        self.questions.remove(&question_id);
+        self.answer_rules
+            .retain(|_, rule| rule.next_q_id != Some(question_id));
+        self.attempt_responses
+            .retain(|_, response| response.q_id != question_id);
+        let answer_ids: Vec<i32> = self
+            .answers
+            .values()
+            .filter(|ans| ans.q_id == question_id)
+            .map(|ans| ans.id)
+            .collect();
+        for answer_id in answer_ids {
+            self.remove_answer(answer_id);
+        }
+    }
+
+    fn remove_answer(&mut self, answer_id: i32) {
+        self.answers.remove(&answer_id);
+        self.answer_weights
+            .retain(|_, weight| weight.a_id != answer_id);
+        self.answer_rules.retain(|_, rule| rule.a_id != answer_id);
+        for picked in self.attempt_answers.values_mut() {
+            if picked.a_id == Some(answer_id) {
+                picked.a_id = None;
+            }
+        }
+    }
+
+    fn remove_result(&mut self, result_id: i32) {
+        self.results.remove(&result_id);
+        self.answer_weights
+            .retain(|_, weight| weight.r_id != result_id);
+        for at in self.attempts.values_mut() {
+            if at.r_id == Some(result_id) {
+                at.r_id = None;
+            }
+        }
+    }
+
+    fn remove_attempt(&mut self, attempt_id: i32) {
+        self.attempts.remove(&attempt_id);
+        self.attempt_answers
+            .retain(|_, picked| picked.at_id != attempt_id);
+        self.attempt_responses
+            .retain(|_, response| response.at_id != attempt_id);
+    }
+}
+
+impl Repository for MemoryRepository {
+    // Nothing is locked while 'f' runs, so this only gives all-or-nothing, not isolation
+    fn transaction(&self, f: &mut dyn FnMut() -> QueryResult<()>) -> QueryResult<()> {
+        let snapshot = self.state().clone();
+        let outcome = f();
+        if outcome.is_err() {
+            *self.state() = snapshot;
+        }
+        outcome
+    }
+
+    /* ------------------------------ Quizzes ------------------------------- */
+    fn get_quiz(&self, quiz_id: i32) -> QueryResult<Quiz> {
+        self.state()
+            .quizzes
+            .get(&quiz_id)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn list_quizzes(
+        &self,
+        filter: &QuizFilter,
+        facets: &QuizFacets,
+        page: &PageRequest,
+    ) -> QueryResult<(Vec<Quiz>, i64, Option<Cursor>)> {
+        let state = self.state();
+        let quizzes = state.matching_quizzes(filter, facets);
+        let total = quizzes.len() as i64;
+
+        let attempts = |quiz_id: i32| {
+            state
+                .attempts
+                .values()
+                .filter(|at| at.qz_id == quiz_id)
+                .count() as i64
+        };
+        let mut positions: Vec<(Cursor, &Quiz)> = quizzes
+            .into_iter()
+            .map(|qz| (Cursor::after(page.sort, qz, attempts(qz.id)), qz))
+            .filter(|(position, _)| match &page.after {
+                Some(last) => position.is_past(last),
+                None => true,
+            })
+            .collect();
+        match page.sort {
+            SortOrder::Name => {
+                positions.sort_by(|(_, a), (_, b)| (&a.name, a.id).cmp(&(&b.name, b.id)))
+            }
+            SortOrder::Newest => positions.sort_by_key(|(_, qz)| Reverse(qz.id)),
+            SortOrder::Popular => {
+                positions.sort_by_key(|(_, qz)| Reverse((attempts(qz.id), qz.id)))
+            }
+        }
+        let next = if positions.len() as i64 > page.limit {
+            positions.truncate(page.limit as usize);
+            positions.last().map(|(position, _)| position.clone())
+        } else {
+            None
+        };
+        let items = positions.into_iter().map(|(_, qz)| qz.clone()).collect();
+        Ok((items, total, next))
+    }
+
+    fn count_facets(&self, filter: &QuizFilter, facets: &QuizFacets) -> QueryResult<Facets> {
+        let state = self.state();
+        let quizzes = state.matching_quizzes(filter, facets);
+        Ok(Facets {
+            tags: counted(quizzes.iter().flat_map(|qz| state.tag_names(qz.id))),
+            categories: counted(quizzes.iter().filter_map(|qz| qz.category.clone())),
+        })
+    }
+
+    fn insert_quiz(&self, new_quiz: NewQuiz) -> QueryResult<i32> {
+        let mut state = self.state();
+        if !state.users.contains_key(&new_quiz.u_id) {
+            return Err(foreign_key_violation("quiz", "u_id"));
+        }
+        if let Some(forked_from_id) = new_quiz.forked_from_id {
+            if !state.quizzes.contains_key(&forked_from_id) {
+                return Err(foreign_key_violation("quiz", "forked_from_id"));
+            }
+        }
+        state.check_media("quiz", new_quiz.media_id)?;
+        let id = state.next_id("quiz");
+        state.quizzes.insert(
+            id,
+            Quiz {
+                id,
+                name: new_quiz.name,
+                description: new_quiz.description,
+                u_id: new_quiz.u_id,
+                status: new_quiz.status,
+                published_at: new_quiz.published_at,
+                forked_from_id: new_quiz.forked_from_id,
+                allow_forks: new_quiz.allow_forks,
+                mode: new_quiz.mode,
+                media_id: new_quiz.media_id,
+                category: new_quiz.category,
+                tags: Vec::new(),
+            },
+        );
+        Ok(id)
+    }
+
+    fn update_quiz(
+        &self,
+        quiz_id: i32,
+        name: &str,
+        description: &str,
+        mode: QuizMode,
+        category: Option<&str>,
+    ) -> QueryResult<()> {
+        if let Some(qz) = self.state().quizzes.get_mut(&quiz_id) {
+            qz.name = String::from(name);
+            qz.description = String::from(description);
+            qz.mode = mode;
+            qz.category = category.map(String::from);
+        }
+        Ok(())
+    }
+
+    fn update_quiz_status(
+        &self,
+        quiz_id: i32,
+        status: QuizStatus,
+        published_at: Option<NaiveDateTime>,
+    ) -> QueryResult<()> {
+        if let Some(qz) = self.state().quizzes.get_mut(&quiz_id) {
+            qz.status = status;
+            qz.published_at = published_at;
+        }
+        Ok(())
+    }
+
+    fn update_quiz_forking(&self, quiz_id: i32, allow_forks: bool) -> QueryResult<()> {
+        if let Some(qz) = self.state().quizzes.get_mut(&quiz_id) {
+            qz.allow_forks = allow_forks;
+        }
+        Ok(())
+    }
+
+    fn set_quiz_media(&self, quiz_id: i32, media_id: Option<i32>) -> QueryResult<()> {
+        let mut state = self.state();
+        state.check_media("quiz", media_id)?;
+        if let Some(qz) = state.quizzes.get_mut(&quiz_id) {
+            qz.media_id = media_id;
+        }
+        Ok(())
+    }
+
+    fn count_forks(&self, quiz_id: i32) -> QueryResult<i64> {
+        let state = self.state();
+        let forks = state
+            .quizzes
+            .values()
+            .filter(|qz| qz.forked_from_id == Some(quiz_id));
+        Ok(forks.count() as i64)
+    }
+
+    fn delete_quiz(&self, quiz_id: i32) -> QueryResult<()> {
+        self.state().remove_quiz(quiz_id);
+        Ok(())
+    }
+
+    fn get_questions(&self, quiz_id: i32) -> QueryResult<Vec<Question>> {
+        let state = self.state();
+        let questions = state
+            .questions
+            .values()
+            .filter(|qs| qs.qz_id == quiz_id)
+            .collect();
+        Ok(sorted_by_key(questions, |qs| (qs.position, qs.id)))
+    }
+
+    fn insert_question(&self, new_question: NewQuestion) -> QueryResult<i32> {
+        let mut state = self.state();
+        if !state.quizzes.contains_key(&new_question.qz_id) {
+            return Err(foreign_key_violation("question", "qz_id"));
+        }
+        state.check_media("question", new_question.media_id)?;
+        let id = state.next_id("question");
+        state.questions.insert(
+            id,
+            Question {
+                id,
+                description: new_question.description,
+                qz_id: new_question.qz_id,
+                position: new_question.position,
+                kind: new_question.kind,
+                config: new_question.config,
+                media_id: new_question.media_id,
+            },
+        );
+        Ok(id)
+    }
+
+    fn update_question(
+        &self,
+        question_id: i32,
+        description: &str,
+        kind: QuestionKind,
+        config: &QuestionConfig,
+        position: i32,
+    ) -> QueryResult<()> {
+        if let Some(qs) = self.state().questions.get_mut(&question_id) {
+            qs.description = String::from(description);
+            qs.kind = kind;
+            qs.config = config.clone();
+            qs.position = position;
+        }
+        Ok(())
+    }
+
+    fn move_question(&self, question_id: i32, position: i32) -> QueryResult<()> {
+        if let Some(qs) = self.state().questions.get_mut(&question_id) {
+            qs.position = position;
+        }
+        Ok(())
+    }
+
+    fn set_question_media(&self, question_id: i32, media_id: Option<i32>) -> QueryResult<()> {
+        let mut state = self.state();
+        state.check_media("question", media_id)?;
+        if let Some(qs) = state.questions.get_mut(&question_id) {
+            qs.media_id = media_id;
+        }
+        Ok(())
+    }
+
+    fn delete_question(&self, question_id: i32) -> QueryResult<()> {
+        self.state().remove_question(question_id);
+        Ok(())
+    }
+
+    fn get_answers(&self, question_ids: &[i32]) -> QueryResult<Vec<Answer>> {
+        let state = self.state();
+        let answers = state
+            .answers
+            .values()
+            .filter(|ans| question_ids.contains(&ans.q_id))
+            .collect();
+        Ok(sorted_by_key(answers, |ans| (ans.position, ans.id)))
+    }
+
+    // All or nothing, like a multi row INSERT
+    fn insert_answers(&self, new_answers: Vec<NewAnswer>) -> QueryResult<()> {
+        let mut state = self.state();
+        if new_answers
+            .iter()
+            .any(|ans| !state.questions.contains_key(&ans.q_id))
+        {
+            return Err(foreign_key_violation("answer", "q_id"));
+        }
+        for ans in &new_answers {
+            state.check_media("answer", ans.media_id)?;
+        }
+        for ans in new_answers {
+            let id = state.next_id("answer");
+            state.answers.insert(
+                id,
+                Answer {
+                    id,
+                    description: ans.description,
+                    val: ans.val,
+                    q_id: ans.q_id,
+                    position: ans.position,
+                    is_correct: ans.is_correct,
+                    media_id: ans.media_id,
+                    weights: BTreeMap::new(),
+                    next_question: None,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn update_answer(
+        &self,
+        answer_id: i32,
+        description: &str,
+        val: i32,
+        is_correct: bool,
+        position: i32,
+    ) -> QueryResult<()> {
+        if let Some(ans) = self.state().answers.get_mut(&answer_id) {
+            ans.description = String::from(description);
+            ans.val = val;
+            ans.is_correct = is_correct;
+            ans.position = position;
+        }
+        Ok(())
+    }
+
+    fn move_answer(&self, answer_id: i32, position: i32) -> QueryResult<()> {
+        if let Some(ans) = self.state().answers.get_mut(&answer_id) {
+            ans.position = position;
+        }
+        Ok(())
+    }
+
+    fn set_answer_media(&self, answer_id: i32, media_id: Option<i32>) -> QueryResult<()> {
+        let mut state = self.state();
+        state.check_media("answer", media_id)?;
+        if let Some(ans) = state.answers.get_mut(&answer_id) {
+            ans.media_id = media_id;
+        }
+        Ok(())
+    }
+
+    fn delete_answer(&self, answer_id: i32) -> QueryResult<()> {
+        self.state().remove_answer(answer_id);
+        Ok(())
+    }
+
+    fn get_answer_weights(&self, answer_ids: &[i32]) -> QueryResult<Vec<AnswerWeight>> {
+        let state = self.state();
+        let weights = state
+            .answer_weights
+            .values()
+            .filter(|weight| answer_ids.contains(&weight.a_id))
+            .collect();
+        Ok(sorted_by_key(weights, |weight| weight.id))
+    }
+
+    fn insert_answer_weights(&self, new_weights: Vec<NewAnswerWeight>) -> QueryResult<()> {
+        let mut state = self.state();
+        for (i, weight) in new_weights.iter().enumerate() {
+            if !state.answers.contains_key(&weight.a_id) {
+                return Err(foreign_key_violation("answer_weight", "a_id"));
+            }
+            if !state.results.contains_key(&weight.r_id) {
+                return Err(foreign_key_violation("answer_weight", "r_id"));
+            }
+            let pair = (weight.a_id, weight.r_id);
+            let taken = state
+                .answer_weights
+                .values()
+                .map(|stored| (stored.a_id, stored.r_id))
+                .chain(new_weights[..i].iter().map(|new| (new.a_id, new.r_id)))
+                .any(|other| other == pair);
+            if taken {
+                return Err(Error::DatabaseError(
+                    DatabaseErrorKind::UniqueViolation,
+                    Box::new(String::from(
+                        "answer_weight.a_id and r_id have to be unique",
+                    )),
+                ));
+            }
+        }
+        for weight in new_weights {
+            let id = state.next_id("answer_weight");
+            state.answer_weights.insert(
+                id,
+                AnswerWeight {
+                    id,
+                    a_id: weight.a_id,
+                    r_id: weight.r_id,
+                    weight: weight.weight,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn delete_answer_weights(&self, answer_ids: &[i32]) -> QueryResult<()> {
+        self.state()
+            .answer_weights
+            .retain(|_, weight| !answer_ids.contains(&weight.a_id));
+        Ok(())
+    }
+
+    fn get_answer_rules(&self, answer_ids: &[i32]) -> QueryResult<Vec<AnswerRule>> {
+        let state = self.state();
+        let rules = state
+            .answer_rules
+            .values()
+            .filter(|rule| answer_ids.contains(&rule.a_id))
+            .collect();
+        Ok(sorted_by_key(rules, |rule| rule.id))
+    }
+
+    fn insert_answer_rules(&self, new_rules: Vec<NewAnswerRule>) -> QueryResult<()> {
+        let mut state = self.state();
+        for (i, rule) in new_rules.iter().enumerate() {
+            if !state.answers.contains_key(&rule.a_id) {
+                return Err(foreign_key_violation("answer_rule", "a_id"));
+            }
+            if let Some(next_q_id) = rule.next_q_id {
+                if !state.questions.contains_key(&next_q_id) {
+                    return Err(foreign_key_violation("answer_rule", "next_q_id"));
+                }
+            }
+            let taken = state
+                .answer_rules
+                .values()
+                .map(|stored| stored.a_id)
+                .chain(new_rules[..i].iter().map(|new| new.a_id))
+                .any(|a_id| a_id == rule.a_id);
+            if taken {
+                return Err(Error::DatabaseError(
+                    DatabaseErrorKind::UniqueViolation,
+                    Box::new(String::from("answer_rule.a_id has to be unique")),
+                ));
+            }
+        }
+        for rule in new_rules {
+            let id = state.next_id("answer_rule");
+            state.answer_rules.insert(
+                id,
+                AnswerRule {
+                    id,
+                    a_id: rule.a_id,
+                    next_q_id: rule.next_q_id,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn delete_answer_rules(&self, answer_ids: &[i32]) -> QueryResult<()> {
+        self.state()
+            .answer_rules
+            .retain(|_, rule| !answer_ids.contains(&rule.a_id));
+        Ok(())
+    }
+
+    // In 'num' order
+    fn get_results(&self, quiz_id: i32) -> QueryResult<Vec<QuizResult>> {
+        let state = self.state();
+        let mut results: Vec<QuizResult> = state
+            .results
+            .values()
+            .filter(|res| res.qz_id == quiz_id)
+            .cloned()
+            .collect();
+        results.sort_by_key(|res| (res.num, res.id));
+        Ok(results)
+    }
+
+    fn get_results_by_ids(&self, result_ids: &[i32]) -> QueryResult<Vec<QuizResult>> {
+        let state = self.state();
+        let results = state
+            .results
+            .values()
+            .filter(|res| result_ids.contains(&res.id))
+            .collect();
+        Ok(sorted_by_key(results, |res| res.id))
+    }
+
+    fn insert_results(&self, new_results: Vec<NewQuizResult>) -> QueryResult<()> {
+        let mut state = self.state();
+        if new_results
+            .iter()
+            .any(|res| !state.quizzes.contains_key(&res.qz_id))
+        {
+            return Err(foreign_key_violation("result", "qz_id"));
+        }
+        for res in &new_results {
+            state.check_media("result", res.media_id)?;
+        }
+        for res in new_results {
+            let id = state.next_id("result");
+            state.results.insert(
+                id,
+                QuizResult {
+                    id,
+                    num: res.num,
+                    header: res.header,
+                    description: res.description,
+                    qz_id: res.qz_id,
+                    min_percent: res.min_percent,
+                    max_percent: res.max_percent,
+                    media_id: res.media_id,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn update_result(
+        &self,
+        result_id: i32,
+        num: i32,
+        header: &str,
+        description: &str,
+        min_percent: Option<i32>,
+        max_percent: Option<i32>,
+    ) -> QueryResult<()> {
+        if let Some(res) = self.state().results.get_mut(&result_id) {
+            res.num = num;
+            res.header = String::from(header);
+            res.description = String::from(description);
+            res.min_percent = min_percent;
+            res.max_percent = max_percent;
+        }
+        Ok(())
+    }
+
+    fn set_result_media(&self, result_id: i32, media_id: Option<i32>) -> QueryResult<()> {
+        let mut state = self.state();
+        state.check_media("result", media_id)?;
+        if let Some(res) = state.results.get_mut(&result_id) {
+            res.media_id = media_id;
+        }
+        Ok(())
+    }
+
+    fn delete_result(&self, result_id: i32) -> QueryResult<()> {
+        self.state().remove_result(result_id);
+        Ok(())
+    }
+
+    /* -------------------------------- Tags -------------------------------- */
+    fn get_tags_by_names(&self, names: &[String]) -> QueryResult<Vec<Tag>> {
+        let state = self.state();
+        let tags = state
+            .tags
+            .values()
+            .filter(|tag| names.contains(&tag.name))
+            .collect();
+        Ok(sorted_by_key(tags, |tag| tag.name.clone()))
+    }
+
+    fn insert_tags(&self, new_tags: Vec<NewTag>) -> QueryResult<()> {
+        let mut state = self.state();
+        for (i, new_tag) in new_tags.iter().enumerate() {
+            let taken = state
+                .tags
+                .values()
+                .map(|stored| &stored.name)
+                .chain(new_tags[..i].iter().map(|new| &new.name))
+                .any(|name| *name == new_tag.name);
+            if taken {
+                return Err(Error::DatabaseError(
+                    DatabaseErrorKind::UniqueViolation,
+                    Box::new(String::from("tag.name has to be unique")),
+                ));
+            }
+        }
+        for new_tag in new_tags {
+            let id = state.next_id("tag");
+            state.tags.insert(
+                id,
+                Tag {
+                    id,
+                    name: new_tag.name,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn get_quiz_tags(&self, quiz_ids: &[i32]) -> QueryResult<Vec<QuizTag>> {
+        let state = self.state();
+        let mut quiz_ids: Vec<i32> = quiz_ids.to_vec();
+        quiz_ids.sort_unstable();
+        quiz_ids.dedup();
+        Ok(quiz_ids
+            .into_iter()
+            .flat_map(|qz_id| {
+                state
+                    .tag_names(qz_id)
+                    .into_iter()
+                    .map(move |name| QuizTag { qz_id, name })
+            })
+            .collect())
+    }
+
+    fn insert_quiz_tags(&self, new_quiz_tags: Vec<NewQuizTag>) -> QueryResult<()> {
+        let mut state = self.state();
+        for (i, new) in new_quiz_tags.iter().enumerate() {
+            if !state.quizzes.contains_key(&new.qz_id) {
+                return Err(foreign_key_violation("quiz_tag", "qz_id"));
+            }
+            if !state.tags.contains_key(&new.tag_id) {
+                return Err(foreign_key_violation("quiz_tag", "tag_id"));
+            }
+            let taken = state.quiz_tags.contains(&(new.qz_id, new.tag_id))
+                || new_quiz_tags[..i]
+                    .iter()
+                    .any(|other| (other.qz_id, other.tag_id) == (new.qz_id, new.tag_id));
+            if taken {
+                return Err(Error::DatabaseError(
+                    DatabaseErrorKind::UniqueViolation,
+                    Box::new(String::from("quiz_tag has to be unique")),
+                ));
+            }
+        }
+        for new in new_quiz_tags {
+            state.quiz_tags.insert((new.qz_id, new.tag_id));
+        }
+        Ok(())
+    }
+
+    fn delete_quiz_tags(&self, quiz_id: i32) -> QueryResult<()> {
+        self.state()
+            .quiz_tags
+            .retain(|(qz_id, _)| *qz_id != quiz_id);
+        Ok(())
+    }
+
+    fn count_tags(&self) -> QueryResult<Vec<TagCount>> {
+        let state = self.state();
+        let published = state.matching_quizzes(&QuizFilter::Published, &QuizFacets::default());
+        Ok(
+            counted(published.iter().flat_map(|qz| state.tag_names(qz.id)))
+                .into_iter()
+                .map(|facet| TagCount {
+                    name: facet.value,
+                    count: facet.count,
+                })
+                .collect(),
+        )
+    }
+
+    /* -------------------------------- Media ------------------------------- */
+    fn insert_media(&self, new_media: NewMedia) -> QueryResult<i32> {
+        let mut state = self.state();
+        if !state.users.contains_key(&new_media.u_id) {
+            return Err(foreign_key_violation("media", "u_id"));
+        }
+        let id = state.next_id("media");
+        state.media.insert(
+            id,
+            Media {
+                id,
+                u_id: new_media.u_id,
+                content_type: new_media.content_type,
+                byte_size: new_media.byte_size,
+                width: new_media.width,
+                height: new_media.height,
+                created_at: Utc::now().naive_utc().with_nanosecond(0).unwrap(),
+            },
+        );
+        Ok(id)
+    }
+
+    fn get_media(&self, media_id: i32) -> QueryResult<Media> {
+        self.state()
+            .media
+            .get(&media_id)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn get_media_by_ids(&self, media_ids: &[i32]) -> QueryResult<Vec<Media>> {
+        let state = self.state();
+        let media = state
+            .media
+            .values()
+            .filter(|media| media_ids.contains(&media.id))
+            .collect();
+        Ok(sorted_by_key(media, |media| media.id))
+    }
+
+    /* ------------------------------ Versions ------------------------------ */
+    fn insert_version(&self, new_version: NewQuizVersion) -> QueryResult<i32> {
+        let mut state = self.state();
+        if !state.quizzes.contains_key(&new_version.qz_id) {
+            return Err(foreign_key_violation("quiz_version", "qz_id"));
+        }
+        if let Some(u_id) = new_version.u_id {
+            if !state.users.contains_key(&u_id) {
+                return Err(foreign_key_violation("quiz_version", "u_id"));
+            }
+        }
+        if state
+            .versions
+            .values()
+            .any(|version| version.qz_id == new_version.qz_id && version.num == new_version.num)
+        {
+            return Err(Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new(String::from("quiz_version.num already taken for this quiz")),
+            ));
+        }
+        let id = state.next_id("quiz_version");
+        state.versions.insert(
+            id,
+            QuizVersion {
+                id,
+                qz_id: new_version.qz_id,
+                num: new_version.num,
+                u_id: new_version.u_id,
+                created_at: Utc::now().naive_utc().with_nanosecond(0).unwrap(),
+                summary: new_version.summary,
+                snapshot: new_version.snapshot,
+            },
+        );
+        Ok(id)
+    }
+
+    fn get_versions(&self, quiz_id: i32) -> QueryResult<Vec<QuizVersionInfo>> {
+        let state = self.state();
+        let mut versions: Vec<QuizVersionInfo> = state
+            .versions
+            .values()
+            .filter(|version| version.qz_id == quiz_id)
+            .cloned()
+            .map(QuizVersionInfo::from)
+            .collect();
+        versions.sort_by_key(|version| Reverse(version.num));
+        Ok(versions)
+    }
+
+    fn get_version(&self, quiz_id: i32, num: i32) -> QueryResult<QuizVersion> {
+        self.state()
+            .versions
+            .values()
+            .find(|version| version.qz_id == quiz_id && version.num == num)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    /* ------------------------------ Attempts ------------------------------ */
+    fn insert_attempt(&self, new_attempt: NewAttempt) -> QueryResult<i32> {
+        let mut state = self.state();
+        if !state.quizzes.contains_key(&new_attempt.qz_id) {
+            return Err(foreign_key_violation("attempt", "qz_id"));
+        }
+        if let Some(u_id) = new_attempt.u_id {
+            if !state.users.contains_key(&u_id) {
+                return Err(foreign_key_violation("attempt", "u_id"));
+            }
+        }
+        if let Some(r_id) = new_attempt.r_id {
+            if !state.results.contains_key(&r_id) {
+                return Err(foreign_key_violation("attempt", "r_id"));
+            }
+        }
+        let id = state.next_id("attempt");
+        state.attempts.insert(
+            id,
+            Attempt {
+                id,
+                qz_id: new_attempt.qz_id,
+                u_id: new_attempt.u_id,
+                r_id: new_attempt.r_id,
+                result_header: new_attempt.result_header,
+                // Whole seconds, like a CURRENT_TIMESTAMP default
+                created_at: Utc::now().naive_utc().with_nanosecond(0).unwrap(),
+            },
+        );
+        Ok(id)
+    }
+
+    fn insert_attempt_answers(&self, new_answers: Vec<NewAttemptAnswer>) -> QueryResult<()> {
+        let mut state = self.state();
+        for picked in &new_answers {
+            if !state.attempts.contains_key(&picked.at_id) {
+                return Err(foreign_key_violation("attempt_answer", "at_id"));
+            }
+            if !state.answers.contains_key(&picked.a_id) {
+                return Err(foreign_key_violation("attempt_answer", "a_id"));
+            }
+        }
+        for picked in new_answers {
+            let id = state.next_id("attempt_answer");
+            state.attempt_answers.insert(
+                id,
+                AttemptAnswer {
+                    id,
+                    at_id: picked.at_id,
+                    a_id: Some(picked.a_id),
+                    description: picked.description,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn get_attempt(&self, attempt_id: i32) -> QueryResult<Attempt> {
+        self.state()
+            .attempts
+            .get(&attempt_id)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn get_attempts_by_user_id(&self, user_id: i32) -> QueryResult<Vec<Attempt>> {
+        let state = self.state();
+        let mut attempts: Vec<Attempt> = state
+            .attempts
+            .values()
+            .filter(|at| at.u_id == Some(user_id))
+            .cloned()
+            .collect();
+        attempts.sort_by_key(|at| Reverse((at.created_at, at.id)));
+        Ok(attempts)
+    }
+
+    fn get_attempt_answers(&self, attempt_ids: &[i32]) -> QueryResult<Vec<AttemptAnswer>> {
+        let state = self.state();
+        let picked = state
+            .attempt_answers
+            .values()
+            .filter(|picked| attempt_ids.contains(&picked.at_id))
+            .collect();
+        Ok(sorted_by_key(picked, |picked| picked.id))
+    }
+
+    fn insert_attempt_responses(&self, new_responses: Vec<NewAttemptResponse>) -> QueryResult<()> {
+        let mut state = self.state();
+        for response in &new_responses {
+            if !state.attempts.contains_key(&response.at_id) {
+                return Err(foreign_key_violation("attempt_response", "at_id"));
+            }
+            if !state.questions.contains_key(&response.q_id) {
+                return Err(foreign_key_violation("attempt_response", "q_id"));
+            }
+        }
+        for response in new_responses {
+            let id = state.next_id("attempt_response");
+            state.attempt_responses.insert(
+                id,
+                AttemptResponse {
+                    id,
+                    at_id: response.at_id,
+                    q_id: response.q_id,
+                    scale_value: response.scale_value,
+                    free_text: response.free_text,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn get_attempt_responses(&self, attempt_ids: &[i32]) -> QueryResult<Vec<AttemptResponse>> {
+        let state = self.state();
+        let responses = state
+            .attempt_responses
+            .values()
+            .filter(|response| attempt_ids.contains(&response.at_id))
+            .collect();
+        Ok(sorted_by_key(responses, |response| response.id))
+    }
+
+    /* --------------------------- Users and auth --------------------------- */
+    fn insert_user(&self, new_user: NewUser) -> QueryResult<i32> {
+        let mut state = self.state();
+        let id = state.next_id("user");
+        state.users.insert(
+            id,
+            User {
+                id,
+                name: new_user.name,
+                email: new_user.email,
+            },
+        );
+        Ok(id)
+    }
+
+    fn fetch_user_by_id(&self, user_id: i32) -> QueryResult<Option<User>> {
+        Ok(self.state().users.get(&user_id).cloned())
+    }
+
+    // The first one if several share the email, as with a LIMIT 1 query
+    fn fetch_user_by_email(&self, email: &str) -> QueryResult<Option<User>> {
+        let state = self.state();
+        let users = state
+            .users
+            .values()
+            .filter(|user| user.email == email)
+            .collect();
+        Ok(sorted_by_key(users, |user| user.id).into_iter().next())
+    }
+
+    // auth_info.uid has no foreign key, so nothing is checked here
+    fn insert_auth_info(&self, new_auth_info: NewAuthInfo) -> QueryResult<()> {
+        let mut state = self.state();
+        let id = state.next_id("auth_info");
+        state.auth_infos.insert(
+            id,
+            AuthInfo {
+                id,
+                uid: new_auth_info.uid,
+                password_hash: new_auth_info.password_hash,
+            },
+        );
+        Ok(())
+    }
+
+    fn fetch_auth_info_by_user_id(&self, user_id: i32) -> QueryResult<Option<AuthInfo>> {
+        let state = self.state();
+        let auth_infos = state
+            .auth_infos
+            .values()
+            .filter(|info| info.uid == user_id)
+            .collect();
+        Ok(sorted_by_key(auth_infos, |info| info.id).into_iter().next())
+    }
+
+    fn update_password_hash(&self, user_id: i32, password_hash: &str) -> QueryResult<()> {
+        for info in self.state().auth_infos.values_mut() {
+            if info.uid == user_id {
+                info.password_hash = String::from(password_hash);
+            }
+        }
+        Ok(())
+    }
+
+    /* ------------------------------ Sessions ------------------------------ */
+    fn insert_session(&self, new_session: NewSession) -> QueryResult<()> {
+        let mut state = self.state();
+        if state.sessions.contains_key(&new_session.id) {
+            return Err(Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new(String::from("session.id already exists")),
+            ));
+        }
+        if !state.users.contains_key(&new_session.u_id) {
+            return Err(foreign_key_violation("session", "u_id"));
+        }
+        state.sessions.insert(
+            new_session.id.clone(),
+            Session {
+                id: new_session.id,
+                u_id: new_session.u_id,
+                created_at: new_session.created_at,
+                last_seen: new_session.last_seen,
+                expires_at: new_session.expires_at,
+                user_agent: new_session.user_agent,
+            },
+        );
+        Ok(())
+    }
+
+    fn fetch_session(&self, session_id: &str) -> QueryResult<Option<Session>> {
+        Ok(self.state().sessions.get(session_id).cloned())
+    }
+
+    fn touch_session(&self, session_id: &str, last_seen: NaiveDateTime) -> QueryResult<()> {
+        if let Some(session) = self.state().sessions.get_mut(session_id) {
+            session.last_seen = last_seen;
+        }
+        Ok(())
+    }
+
+    fn fetch_sessions_by_user_id(
+        &self,
+        user_id: i32,
+        now: NaiveDateTime,
+    ) -> QueryResult<Vec<Session>> {
+        let state = self.state();
+        let mut sessions: Vec<Session> = state
+            .sessions
+            .values()
+            .filter(|session| session.u_id == user_id && session.expires_at > now)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| Reverse(session.last_seen));
+        Ok(sessions)
+    }
+
+    fn delete_session(&self, session_id: &str) -> QueryResult<()> {
+        self.state().sessions.remove(session_id);
+        Ok(())
+    }
+
+    fn delete_sessions_by_user_id(&self, user_id: i32) -> QueryResult<()> {
+        self.state()
+            .sessions
+            .retain(|_, session| session.u_id != user_id);
+        Ok(())
+    }
+}
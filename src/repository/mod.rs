@@ -0,0 +1,250 @@
+use crate::models::attempt_models::*;
+use crate::models::auth_models::*;
+use crate::models::media_models::*;
+use crate::models::quiz_models::*;
+use crate::models::tag_models::*;
+use crate::models::version_models::*;
+use crate::routing::pagination::{Cursor, PageRequest};
+use crate::DbConn;
+use chrono::NaiveDateTime;
+use diesel::result::QueryResult;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::State;
+use std::ops::Deref;
+use std::sync::Arc;
+
+#[macro_use]
+mod diesel_repository;
+pub mod memory;
+#[cfg(feature = "mysql")]
+pub mod mysql;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+// Which quizzes a listing covers. Only an author's own listing shows anything unpublished.
+#[derive(Debug)]
+pub enum QuizFilter {
+    Published,
+    // Every quiz by the user, whatever its status
+    ByUser(i32),
+    // Every quiz there is, for upkeep like rebuilding the search index
+    All,
+    // The published ones out of these, e.g. the hits of a search. Any order is up to the listing.
+    Among(Vec<i32>),
+}
+
+// Narrows a listing down further, to quizzes carrying a tag and in a category. Both are matched
+// exactly, the way they're stored.
+#[derive(Debug, Default)]
+pub struct QuizFacets {
+    pub tag: Option<String>,
+    pub category: Option<String>,
+}
+
+// Everything the routes need from storage. Each method is a single query (or a single batch),
+// anything that spans several of them lives in the *_functions modules and goes through
+// 'transaction'. Errors are diesel's so every backend reports missing rows and broken
+// constraints the same way.
+pub trait Repository {
+    // Runs 'f' so that either all of its writes happen or none do.
+    fn transaction(&self, f: &mut dyn FnMut() -> QueryResult<()>) -> QueryResult<()>;
+
+    /* ------------------------------ Quizzes ------------------------------- */
+    fn get_quiz(&self, quiz_id: i32) -> QueryResult<Quiz>;
+    // One page of the quizzes matching 'filter' and 'facets', along with how many match in total
+    // and where the next page starts, None if there's nothing after this one
+    fn list_quizzes(
+        &self,
+        filter: &QuizFilter,
+        facets: &QuizFacets,
+        page: &PageRequest,
+    ) -> QueryResult<(Vec<Quiz>, i64, Option<Cursor>)>;
+    // The tags and categories of every quiz list_quizzes would match, most common first
+    fn count_facets(&self, filter: &QuizFilter, facets: &QuizFacets) -> QueryResult<Facets>;
+    fn insert_quiz(&self, new_quiz: NewQuiz) -> QueryResult<i32>;
+    fn update_quiz(
+        &self,
+        quiz_id: i32,
+        name: &str,
+        description: &str,
+        mode: QuizMode,
+        category: Option<&str>,
+    ) -> QueryResult<()>;
+    fn update_quiz_status(
+        &self,
+        quiz_id: i32,
+        status: QuizStatus,
+        published_at: Option<NaiveDateTime>,
+    ) -> QueryResult<()>;
+    fn update_quiz_forking(&self, quiz_id: i32, allow_forks: bool) -> QueryResult<()>;
+    fn set_quiz_media(&self, quiz_id: i32, media_id: Option<i32>) -> QueryResult<()>;
+    // How many quizzes name this one as the quiz they were forked from
+    fn count_forks(&self, quiz_id: i32) -> QueryResult<i64>;
+    // Takes the quiz's questions, answers, results and attempts with it, forks of it stay
+    fn delete_quiz(&self, quiz_id: i32) -> QueryResult<()>;
+
+    // In position order
+    fn get_questions(&self, quiz_id: i32) -> QueryResult<Vec<Question>>;
+    fn insert_question(&self, new_question: NewQuestion) -> QueryResult<i32>;
+    fn update_question(
+        &self,
+        question_id: i32,
+        description: &str,
+        kind: QuestionKind,
+        config: &QuestionConfig,
+        position: i32,
+    ) -> QueryResult<()>;
+    fn move_question(&self, question_id: i32, position: i32) -> QueryResult<()>;
+    fn set_question_media(&self, question_id: i32, media_id: Option<i32>) -> QueryResult<()>;
+    // Takes the question's answers with it
+    fn delete_question(&self, question_id: i32) -> QueryResult<()>;
+
+    // The answers to all of the given questions, in position order
+    fn get_answers(&self, question_ids: &[i32]) -> QueryResult<Vec<Answer>>;
+    fn insert_answers(&self, new_answers: Vec<NewAnswer>) -> QueryResult<()>;
+    fn update_answer(
+        &self,
+        answer_id: i32,
+        description: &str,
+        val: i32,
+        is_correct: bool,
+        position: i32,
+    ) -> QueryResult<()>;
+    fn move_answer(&self, answer_id: i32, position: i32) -> QueryResult<()>;
+    fn set_answer_media(&self, answer_id: i32, media_id: Option<i32>) -> QueryResult<()>;
+    fn delete_answer(&self, answer_id: i32) -> QueryResult<()>;
+    // The weights of all of the given answers, in id order
+    fn get_answer_weights(&self, answer_ids: &[i32]) -> QueryResult<Vec<AnswerWeight>>;
+    fn insert_answer_weights(&self, new_weights: Vec<NewAnswerWeight>) -> QueryResult<()>;
+    fn delete_answer_weights(&self, answer_ids: &[i32]) -> QueryResult<()>;
+    // The rules of all of the given answers, in id order
+    fn get_answer_rules(&self, answer_ids: &[i32]) -> QueryResult<Vec<AnswerRule>>;
+    fn insert_answer_rules(&self, new_rules: Vec<NewAnswerRule>) -> QueryResult<()>;
+    fn delete_answer_rules(&self, answer_ids: &[i32]) -> QueryResult<()>;
+
+    fn get_results(&self, quiz_id: i32) -> QueryResult<Vec<QuizResult>>;
+    fn get_results_by_ids(&self, result_ids: &[i32]) -> QueryResult<Vec<QuizResult>>;
+    fn insert_results(&self, new_results: Vec<NewQuizResult>) -> QueryResult<()>;
+    fn update_result(
+        &self,
+        result_id: i32,
+        num: i32,
+        header: &str,
+        description: &str,
+        min_percent: Option<i32>,
+        max_percent: Option<i32>,
+    ) -> QueryResult<()>;
+    fn set_result_media(&self, result_id: i32, media_id: Option<i32>) -> QueryResult<()>;
+    fn delete_result(&self, result_id: i32) -> QueryResult<()>;
+
+    /* -------------------------------- Tags -------------------------------- */
+    // Whichever of the given tags exist, in name order
+    fn get_tags_by_names(&self, names: &[String]) -> QueryResult<Vec<Tag>>;
+    fn insert_tags(&self, new_tags: Vec<NewTag>) -> QueryResult<()>;
+    // The tags of all of the given quizzes, by quiz and then by name
+    fn get_quiz_tags(&self, quiz_ids: &[i32]) -> QueryResult<Vec<QuizTag>>;
+    fn insert_quiz_tags(&self, new_quiz_tags: Vec<NewQuizTag>) -> QueryResult<()>;
+    fn delete_quiz_tags(&self, quiz_id: i32) -> QueryResult<()>;
+    // Every tag on a published quiz with how many of them carry it, most used first
+    fn count_tags(&self) -> QueryResult<Vec<TagCount>>;
+
+    /* -------------------------------- Media ------------------------------- */
+    fn insert_media(&self, new_media: NewMedia) -> QueryResult<i32>;
+    fn get_media(&self, media_id: i32) -> QueryResult<Media>;
+    // Whichever of the given media exist, in id order
+    fn get_media_by_ids(&self, media_ids: &[i32]) -> QueryResult<Vec<Media>>;
+
+    /* ------------------------------ Versions ------------------------------ */
+    fn insert_version(&self, new_version: NewQuizVersion) -> QueryResult<i32>;
+    // Newest first, without the snapshots
+    fn get_versions(&self, quiz_id: i32) -> QueryResult<Vec<QuizVersionInfo>>;
+    fn get_version(&self, quiz_id: i32, num: i32) -> QueryResult<QuizVersion>;
+
+    /* ------------------------------ Attempts ------------------------------ */
+    fn insert_attempt(&self, new_attempt: NewAttempt) -> QueryResult<i32>;
+    fn insert_attempt_answers(&self, new_answers: Vec<NewAttemptAnswer>) -> QueryResult<()>;
+    fn get_attempt(&self, attempt_id: i32) -> QueryResult<Attempt>;
+    // Newest first
+    fn get_attempts_by_user_id(&self, user_id: i32) -> QueryResult<Vec<Attempt>>;
+    fn get_attempt_answers(&self, attempt_ids: &[i32]) -> QueryResult<Vec<AttemptAnswer>>;
+    fn insert_attempt_responses(&self, new_responses: Vec<NewAttemptResponse>) -> QueryResult<()>;
+    // The responses to all of the given attempts, in id order
+    fn get_attempt_responses(&self, attempt_ids: &[i32]) -> QueryResult<Vec<AttemptResponse>>;
+
+    /* --------------------------- Users and auth --------------------------- */
+    fn insert_user(&self, new_user: NewUser) -> QueryResult<i32>;
+    fn fetch_user_by_id(&self, user_id: i32) -> QueryResult<Option<User>>;
+    fn fetch_user_by_email(&self, email: &str) -> QueryResult<Option<User>>;
+    fn insert_auth_info(&self, new_auth_info: NewAuthInfo) -> QueryResult<()>;
+    fn fetch_auth_info_by_user_id(&self, user_id: i32) -> QueryResult<Option<AuthInfo>>;
+    fn update_password_hash(&self, user_id: i32, password_hash: &str) -> QueryResult<()>;
+
+    /* ------------------------------ Sessions ------------------------------ */
+    fn insert_session(&self, new_session: NewSession) -> QueryResult<()>;
+    fn fetch_session(&self, session_id: &str) -> QueryResult<Option<Session>>;
+    fn touch_session(&self, session_id: &str, last_seen: NaiveDateTime) -> QueryResult<()>;
+    // Sessions that haven't expired by 'now', most recently used first
+    fn fetch_sessions_by_user_id(
+        &self,
+        user_id: i32,
+        now: NaiveDateTime,
+    ) -> QueryResult<Vec<Session>>;
+    fn delete_session(&self, session_id: &str) -> QueryResult<()>;
+    fn delete_sessions_by_user_id(&self, user_id: i32) -> QueryResult<()>;
+}
+
+// Runs 'f' under Repository::transaction, handing back whatever it returns.
+pub fn transaction<T, F>(repo: &dyn Repository, f: F) -> QueryResult<T>
+where
+    F: FnOnce() -> QueryResult<T>,
+{
+    let mut f = Some(f);
+    let mut output = None;
+    repo.transaction(&mut || {
+        let f = f.take().expect("transaction body runs once");
+        output = Some(f()?);
+        Ok(())
+    })?;
+    Ok(output.expect("transaction body ran"))
+}
+
+// Managed state telling the Repo guard where to find storage. The server runs off the pooled
+// database; tests can hand every request one shared repository instead, e.g. a MemoryRepository.
+pub enum Storage {
+    Database,
+    Shared(Arc<dyn Repository + Send + Sync>),
+}
+
+// Request guard handing routes the repository to work with.
+pub enum Repo {
+    Pooled(DbConn),
+    Shared(Arc<dyn Repository + Send + Sync>),
+}
+
+impl Deref for Repo {
+    type Target = dyn Repository;
+    fn deref(&self) -> &(dyn Repository + 'static) {
+        match self {
+            Repo::Pooled(conn) => &**conn,
+            Repo::Shared(repo) => &**repo,
+        }
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Repo {
+    type Error = ();
+    fn from_request(request: &'a Request<'r>) -> Outcome<Repo, ()> {
+        if let Storage::Shared(repo) = request.guard::<State<Storage>>()?.inner() {
+            return Outcome::Success(Repo::Shared(Arc::clone(repo)));
+        }
+        let conn = request.guard::<DbConn>()?;
+        // SQLite leaves foreign keys off unless asked, per connection
+        #[cfg(not(feature = "mysql"))]
+        {
+            if sqlite::enable_foreign_keys(&conn).is_err() {
+                return Outcome::Failure((rocket::http::Status::InternalServerError, ()));
+            }
+        }
+        Outcome::Success(Repo::Pooled(conn))
+    }
+}
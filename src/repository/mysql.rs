@@ -0,0 +1,20 @@
+use crate::models::attempt_models::*;
+use crate::models::auth_models::*;
+use crate::models::media_models::*;
+use crate::models::quiz_models::*;
+use crate::models::tag_models::*;
+use crate::models::version_models::*;
+use crate::utils::sql_utils::last_insert_id;
+use diesel::mysql::Mysql;
+use diesel::prelude::*;
+
+fn last_inserted(conn: &MysqlConnection) -> QueryResult<i32> {
+    let last_id: u64 = diesel::select(last_insert_id).first(conn)?;
+    Ok(last_id as i32)
+}
+
+diesel_repository!(
+    MysqlConnection,
+    Mysql,
+    last_insert_id: last_inserted
+);
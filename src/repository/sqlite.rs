@@ -0,0 +1,54 @@
+use crate::models::attempt_models::*;
+use crate::models::auth_models::*;
+use crate::models::media_models::*;
+use crate::models::quiz_models::*;
+use crate::models::tag_models::*;
+use crate::models::version_models::*;
+use crate::utils::sql_utils::last_insert_rowid;
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+
+// The SQLite flavour of the schema, kept in step with 'migrations'
+embed_migrations!("migrations_sqlite");
+
+// Brings a SQLite database up to date, creating it from scratch if it's empty.
+pub fn run_migrations(
+    conn: &SqliteConnection,
+) -> Result<(), diesel_migrations::RunMigrationsError> {
+    embedded_migrations::run(conn)
+}
+
+// Needed on every new connection, otherwise deletes don't cascade.
+pub fn enable_foreign_keys(conn: &SqliteConnection) -> QueryResult<()> {
+    conn.batch_execute("PRAGMA foreign_keys = ON")
+}
+
+// Runs the migrations when the server starts, so a fresh SQLite file just works.
+#[cfg(not(feature = "mysql"))]
+pub fn migrations_fairing() -> rocket::fairing::AdHoc {
+    rocket::fairing::AdHoc::on_attach("SQLite migrations", |rocket| {
+        let outcome = match crate::DbConn::get_one(&rocket) {
+            Some(conn) => run_migrations(&conn).map_err(|err| err.to_string()),
+            None => Err(String::from("no database connection")),
+        };
+        match outcome {
+            Ok(()) => Ok(rocket),
+            Err(err) => {
+                log::error!("Failed to run the SQLite migrations: {}", err);
+                Err(rocket)
+            }
+        }
+    })
+}
+
+fn last_inserted(conn: &SqliteConnection) -> QueryResult<i32> {
+    let last_id: i64 = diesel::select(last_insert_rowid).first(conn)?;
+    Ok(last_id as i32)
+}
+
+diesel_repository!(
+    SqliteConnection,
+    Sqlite,
+    last_insert_id: last_inserted
+);
@@ -0,0 +1,146 @@
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use rocket::http::{ContentType, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use serde_json::Value;
+use std::io::Cursor;
+
+// The one error type every route returns. Serializes as '{"code", "message", "details"}' where
+// 'code' is a stable machine readable string clients can match on, and 'message' is meant for
+// humans. Database internals never make it into either.
+#[derive(Serialize, Debug)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: Status,
+    pub code: &'static str,
+    pub message: String,
+    pub details: Value,
+}
+
+impl ApiError {
+    pub fn new(status: Status, code: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            code,
+            message: String::from(message),
+            details: Value::Null,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    pub fn bad_request(message: &str) -> Self {
+        Self::new(Status::BadRequest, "bad_request", message)
+    }
+
+    pub fn unauthorized(message: &str) -> Self {
+        Self::new(Status::Unauthorized, "unauthorized", message)
+    }
+
+    pub fn forbidden(message: &str) -> Self {
+        Self::new(Status::Forbidden, "forbidden", message)
+    }
+
+    pub fn not_found(message: &str) -> Self {
+        Self::new(Status::NotFound, "not_found", message)
+    }
+
+    pub fn conflict(message: &str) -> Self {
+        Self::new(Status::Conflict, "conflict", message)
+    }
+
+    pub fn unprocessable(code: &'static str, message: &str) -> Self {
+        Self::new(Status::UnprocessableEntity, code, message)
+    }
+
+    pub fn internal() -> Self {
+        Self::new(
+            Status::InternalServerError,
+            "internal_error",
+            "Something went wrong on our end",
+        )
+    }
+}
+
+impl From<DieselError> for ApiError {
+    fn from(err: DieselError) -> Self {
+        match err {
+            DieselError::NotFound => ApiError::not_found("The requested record does not exist"),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ApiError::conflict("A record with these values already exists")
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                ApiError::unprocessable(
+                    "invalid_reference",
+                    "The request refers to a record that does not exist",
+                )
+            }
+            other => {
+                // Logged for us, but kept out of the response
+                log::error!("Database error: {}", other);
+                ApiError::internal()
+            }
+        }
+    }
+}
+
+impl From<argon2::Error> for ApiError {
+    fn from(err: argon2::Error) -> Self {
+        log::error!("Password hashing error: {}", err);
+        ApiError::internal()
+    }
+}
+
+impl From<tantivy::TantivyError> for ApiError {
+    fn from(err: tantivy::TantivyError) -> Self {
+        log::error!("Search index error: {}", err);
+        ApiError::internal()
+    }
+}
+
+impl<'r> Responder<'r> for ApiError {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        let body = serde_json::to_string(&self).map_err(|_| Status::InternalServerError)?;
+        Response::build()
+            .status(self.status)
+            .header(ContentType::JSON)
+            .sized_body(Cursor::new(body))
+            .ok()
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*     Catchers, so errors Rocket raises itself come back in the same shape     */
+/* -------------------------------------------------------------------------- */
+
+#[catch(401)]
+pub fn unauthorized(_: &Request) -> ApiError {
+    ApiError::unauthorized("You need to be logged in to do that")
+}
+
+#[catch(404)]
+pub fn not_found(req: &Request) -> ApiError {
+    ApiError::not_found(&format!("Nothing lives at '{}'", req.uri().path()))
+}
+
+#[catch(406)]
+pub fn not_acceptable(_: &Request) -> ApiError {
+    ApiError::new(
+        Status::NotAcceptable,
+        "unsupported_version",
+        "The requested API version is not supported",
+    )
+}
+
+#[catch(422)]
+pub fn unprocessable_entity(_: &Request) -> ApiError {
+    ApiError::unprocessable("malformed_body", "The request body could not be understood")
+}
+
+#[catch(500)]
+pub fn internal_error(_: &Request) -> ApiError {
+    ApiError::internal()
+}
@@ -0,0 +1,42 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder, Response};
+
+// Which shape of a resource a client wants back. Everything defaults to v1 so the existing
+// frontend keeps working, v2 is asked for with a parameter on the Accept header, e.g.
+// 'Accept: application/json; version=2', or by going through the '/v2' routes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for ApiVersion {
+    type Error = ();
+    fn from_request(request: &'a Request<'r>) -> Outcome<ApiVersion, ()> {
+        let requested = request.accept().and_then(|accept| {
+            accept
+                .iter()
+                .flat_map(|media_type| media_type.media_type().params())
+                .find(|&(name, _)| name == "version")
+                .map(|(_, value)| value)
+        });
+        match requested {
+            None | Some("1") => Outcome::Success(ApiVersion::V1),
+            Some("2") => Outcome::Success(ApiVersion::V2),
+            Some(_) => Outcome::Failure((Status::NotAcceptable, ())),
+        }
+    }
+}
+
+// A response whose shape was picked from the Accept header. Says so with 'Vary: Accept', so
+// shared caches keep each shape apart instead of handing v1 to v2 clients or the other way round.
+pub struct VaryOnAccept<R>(pub R);
+
+impl<'r, R: Responder<'r>> Responder<'r> for VaryOnAccept<R> {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        Response::build_from(self.0.respond_to(request)?)
+            .raw_header("Vary", "Accept")
+            .ok()
+    }
+}
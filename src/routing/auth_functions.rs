@@ -1,71 +1,134 @@
 // password hashing
 use crate::models::auth_models::*;
+use crate::repository::Repository;
+use argon2::{Config, Variant, Version};
+use chrono::{Duration, Utc};
 use crypto::digest::Digest;
+use crypto::sha2::Sha256;
 use crypto::sha3::Sha3;
-use diesel::{self, prelude::*};
-use rocket::http::Cookies;
+use crypto::util::fixed_time_eq;
+use diesel::result::QueryResult;
+use rand::RngCore;
 
-pub fn logged_in(uid: i32, cookies: &mut Cookies) -> bool {
-    let logged_in_user = cookies.get_private("user_id");
-    match logged_in_user {
-        Some(c) => {
-            let logged_in_uid = c.value().parse::<i32>().unwrap();
-            if logged_in_uid == uid {
-                true
-            } else {
-                false
-            }
-        }
-        None => false,
+// Argon2id parameters for new hashes, memory cost is in KiB
+const ARGON2_MEM_COST: u32 = 19456;
+const ARGON2_TIME_COST: u32 = 2;
+const ARGON2_LANES: u32 = 1;
+const SALT_LEN: usize = 16;
+
+// Name of the private cookie holding the session token
+pub const SESSION_COOKIE: &str = "session_id";
+const SESSION_TOKEN_LEN: usize = 32;
+pub const SESSION_LIFETIME_DAYS: i64 = 30;
+// last_seen is only written back when it is at least this stale, to spare a write per request
+const LAST_SEEN_RESOLUTION_SECS: i64 = 60;
+
+// How a password compared against the hash stored for a user.
+#[derive(Debug, PartialEq)]
+pub enum PasswordCheck {
+    Valid,
+    // Correct, but the stored hash is a legacy SHA3 one or uses outdated Argon2 parameters
+    ValidNeedsRehash,
+    Invalid,
+}
+
+fn argon2_config<'a>() -> Config<'a> {
+    Config {
+        variant: Variant::Argon2id,
+        version: Version::Version13,
+        mem_cost: ARGON2_MEM_COST,
+        time_cost: ARGON2_TIME_COST,
+        lanes: ARGON2_LANES,
+        ..Config::default()
     }
 }
 
-pub fn fetch_auth_info_by_user_id(
-    conn: &diesel::MysqlConnection,
-    input_uid: i32,
-) -> Option<AuthInfo> {
-    use crate::schema::auth_info::dsl::*;
-    let mut auth_infos_by_uid: Vec<AuthInfo> = auth_info
-        .filter(uid.eq(input_uid))
-        .load::<AuthInfo>(conn)
-        .expect("Error");
-    if auth_infos_by_uid.len() == 0 {
-        None
-    } else {
-        let first = auth_infos_by_uid.remove(0);
-        Some(first)
+// Hashes with a fresh random salt. The result is a PHC string, e.g.
+// '$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>', so it carries everything needed to verify it.
+pub fn hash_password(password: &str) -> Result<String, argon2::Error> {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    argon2::hash_encoded(password.as_bytes(), &salt, &argon2_config())
+}
+
+pub fn verify_password(password: &str, stored_hash: &str) -> PasswordCheck {
+    // Accounts created before the switch to Argon2 hold a bare hex SHA3-256 digest
+    if !stored_hash.starts_with('$') {
+        return if fixed_time_eq(
+            legacy_hash_password(password).as_bytes(),
+            stored_hash.as_bytes(),
+        ) {
+            PasswordCheck::ValidNeedsRehash
+        } else {
+            PasswordCheck::Invalid
+        };
+    }
+
+    match argon2::verify_encoded(stored_hash, password.as_bytes()) {
+        Ok(true) if stored_hash.starts_with(&current_hash_prefix()) => PasswordCheck::Valid,
+        Ok(true) => PasswordCheck::ValidNeedsRehash,
+        _ => PasswordCheck::Invalid,
     }
 }
 
-pub fn hash_password(password: &String) -> String {
+// The part of a PHC string that identifies the algorithm and parameters currently in use
+fn current_hash_prefix() -> String {
+    format!(
+        "$argon2id$v=19$m={},t={},p={}$",
+        ARGON2_MEM_COST, ARGON2_TIME_COST, ARGON2_LANES
+    )
+}
+
+fn legacy_hash_password(password: &str) -> String {
     let mut hasher = Sha3::sha3_256();
     hasher.input_str(password);
     hasher.result_str()
 }
 
-pub fn fetch_user_by_email(conn: &diesel::MysqlConnection, input_email: &String) -> Option<User> {
-    use crate::schema::user::dsl::*;
-    let mut users_by_id: Vec<User> = user
-        .filter(email.eq(input_email))
-        .load::<User>(conn)
-        .expect("Error");
-    if users_by_id.len() == 0 {
-        None
-    } else {
-        let first = users_by_id.remove(0);
-        Some(first)
-    }
+fn hash_session_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input_str(token);
+    hasher.result_str()
 }
 
-pub fn fetch_user_by_id(conn: &diesel::MysqlConnection, input_id: i32) -> Option<User> {
-    use crate::schema::user::dsl::*;
-    let mut users_by_id: Vec<User> = user
-        .filter(id.eq(input_id))
-        .load::<User>(conn)
-        .expect("Error");
-    if users_by_id.len() == 0 {
-        None
-    } else {
-        Some(users_by_id.remove(0))
+// Starts a session for a user and returns the token to hand to the client. Only its hash is
+// stored, which doubles as the session's public id.
+pub fn create_session(
+    repo: &dyn Repository,
+    input_uid: i32,
+    input_user_agent: Option<String>,
+) -> QueryResult<String> {
+    let mut raw_token = [0u8; SESSION_TOKEN_LEN];
+    rand::thread_rng().fill_bytes(&mut raw_token);
+    let token: String = raw_token.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let now = Utc::now().naive_utc();
+    repo.insert_session(NewSession {
+        id: hash_session_token(&token),
+        u_id: input_uid,
+        created_at: now,
+        last_seen: now,
+        expires_at: now + Duration::days(SESSION_LIFETIME_DAYS),
+        user_agent: input_user_agent,
+    })?;
+    Ok(token)
+}
+
+// Looks up the live session for a token, bumping its last_seen. Expired sessions are deleted.
+pub fn fetch_session_by_token(repo: &dyn Repository, token: &str) -> QueryResult<Option<Session>> {
+    let mut found = match repo.fetch_session(&hash_session_token(token))? {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+
+    let now = Utc::now().naive_utc();
+    if found.expires_at <= now {
+        repo.delete_session(&found.id)?;
+        return Ok(None);
+    }
+    if now - found.last_seen >= Duration::seconds(LAST_SEEN_RESOLUTION_SECS) {
+        repo.touch_session(&found.id, now)?;
+        found.last_seen = now;
     }
+    Ok(Some(found))
 }
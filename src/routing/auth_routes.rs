@@ -1,83 +1,156 @@
-use diesel::{self, prelude::*};
+use chrono::Utc;
 
-use rocket::http::{Cookie, Cookies};
+use rocket::http::{Cookie, Cookies, Status};
 use rocket_contrib::json::Json; // Easy Json coercion
 
+use super::api_error::ApiError;
 use super::auth_functions::*;
 use super::auth_types::*;
+use super::quiz_types::LoggedInUserID;
 use crate::models::auth_models::*; // Models needed for pulling or pushing data
-use crate::utils::sql_utils::last_insert_id; //utility for getting around mysql being bad
-use crate::DbConn; // The state managed DB connection
+use crate::repository::{self, Repo}; // Storage, whichever database is behind it
 
 #[post("/users/create", format = "json", data = "<create_info>")]
-pub fn create(conn_ptr: DbConn, create_info: Json<CreateInfo>) -> Json<i32> {
-    use crate::schema::auth_info::dsl::auth_info as auth_info_table;
-    use crate::schema::user::dsl::user as user_table;
+pub fn create(repo: Repo, create_info: Json<CreateInfo>) -> Result<Json<i32>, ApiError> {
     let user = NewUser {
         name: create_info.name.clone(),
         email: create_info.email.clone(),
     };
-    let ref conn = *conn_ptr;
-    let _rows_changed = diesel::insert_into(user_table)
-        .values(user)
-        .execute(conn)
-        .expect("error");
+    let password_hash = hash_password(&create_info.password)?;
 
-    let password_hash = hash_password(&create_info.password);
-    let last_uid: u64 = diesel::select(last_insert_id).first(conn).expect("Error");
-    let auth_info = NewAuthInfo {
-        uid: last_uid as i32,
-        password_hash: password_hash,
-    };
-    let _rows_changed = diesel::insert_into(auth_info_table)
-        .values(auth_info)
-        .execute(conn)
-        .expect("Error");
-    Json(last_uid as i32)
+    // The user and their credentials go in together or not at all
+    let uid = repository::transaction(&*repo, || {
+        let uid = repo.insert_user(user)?;
+        repo.insert_auth_info(NewAuthInfo { uid, password_hash })?;
+        Ok(uid)
+    })?;
+    Ok(Json(uid))
 }
+
 #[post("/users/login", format = "json", data = "<login_info>")]
 pub fn login(
-    conn_ptr: DbConn,
+    repo: Repo,
     login_info: Json<LoginInfo>,
+    user_agent: UserAgent,
     mut cookies: Cookies,
-) -> Json<Option<User>> {
-    let ref conn = *conn_ptr;
-    let user_opt = fetch_user_by_email(conn, &login_info.username);
-    match user_opt {
-        Some(user) => {
-            let auth_opt = fetch_auth_info_by_user_id(conn, user.id);
-            match auth_opt {
-                Some(auth_info) => {
-                    let hash = hash_password(&login_info.password);
-                    if hash == auth_info.password_hash {
-                        cookies.add_private(Cookie::new("user_id", user.id.to_string()));
-                        Json(fetch_user_by_id(conn, user.id))
-                    } else {
-                        Json(None)
+) -> Result<Json<User>, ApiError> {
+    // Same error whether the email or the password is wrong, so emails can't be probed
+    let invalid_credentials = || {
+        ApiError::new(
+            Status::Unauthorized,
+            "invalid_credentials",
+            "Wrong email or password",
+        )
+    };
+
+    let user = repo
+        .fetch_user_by_email(&login_info.username)?
+        .ok_or_else(invalid_credentials)?;
+    let auth_info = repo
+        .fetch_auth_info_by_user_id(user.id)?
+        .ok_or_else(invalid_credentials)?;
+    match verify_password(&login_info.password, &auth_info.password_hash) {
+        PasswordCheck::Invalid => return Err(invalid_credentials()),
+        PasswordCheck::ValidNeedsRehash => {
+            // Migrates old hashes over as users log in. Failing to make or store the new hash
+            // shouldn't stop the login, the rehash is just tried again next time.
+            match hash_password(&login_info.password) {
+                Ok(new_hash) => {
+                    if let Err(err) = repo.update_password_hash(user.id, &new_hash) {
+                        log::error!("Failed to store a rehashed password: {}", err);
                     }
                 }
-                None => Json(None),
+                Err(err) => log::error!("Failed to rehash a password: {}", err),
             }
         }
-        None => Json(None),
+        PasswordCheck::Valid => {}
     }
+
+    let token = create_session(&*repo, user.id, user_agent.0)?;
+    cookies.add_private(session_cookie(token));
+    Ok(Json(user))
+}
+
+// The cookie outlives the browser session, the server side expiry is what actually ends it.
+fn session_cookie(token: String) -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE, token)
+        .path("/")
+        .http_only(true)
+        .max_age(time::Duration::days(SESSION_LIFETIME_DAYS))
+        .finish()
 }
 
 #[post("/users/logout", format = "json")]
-pub fn logout(mut cookies: Cookies) -> () {
-    cookies.remove_private(Cookie::named("user_id"));
+pub fn logout(session: Option<CurrentSession>, repo: Repo, mut cookies: Cookies) -> () {
+    if let Some(CurrentSession(session)) = session {
+        let _ = repo.delete_session(&session.id);
+    }
+    cookies.remove_private(Cookie::named(SESSION_COOKIE));
 }
 
 #[get("/users/cookies/<uid>")]
 pub fn fetch_info_by_user_id(
-    conn_ptr: DbConn,
+    repo: Repo,
     uid: i32,
+    user_id: LoggedInUserID,
+) -> Result<Json<User>, ApiError> {
+    if user_id.0 != uid {
+        return Err(ApiError::forbidden(
+            "Not allowed to view another user's info",
+        ));
+    }
+    repo.fetch_user_by_id(uid)?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("No such user"))
+}
+
+// Lists the devices the logged in user is signed in on.
+#[get("/users/sessions")]
+pub fn list_sessions(
+    current: CurrentSession,
+    repo: Repo,
+) -> Result<Json<Vec<SessionInfo>>, ApiError> {
+    let CurrentSession(current) = current;
+    let sessions = repo.fetch_sessions_by_user_id(current.u_id, Utc::now().naive_utc())?;
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionInfo::new(session, &current.id))
+            .collect(),
+    ))
+}
+
+// Signs one of the user's sessions out, e.g. a lost phone.
+#[delete("/users/sessions/<session_id>")]
+pub fn revoke_session(
+    session_id: String,
+    current: CurrentSession,
+    repo: Repo,
     mut cookies: Cookies,
-) -> Json<Option<User>> {
-    let ref conn = *conn_ptr;
-    if logged_in(uid, &mut cookies) {
-        Json(fetch_user_by_id(conn, uid))
-    } else {
-        Json(None)
+) -> Result<(), ApiError> {
+    let CurrentSession(current) = current;
+    let owned = repo
+        .fetch_sessions_by_user_id(current.u_id, Utc::now().naive_utc())?
+        .iter()
+        .any(|session| session.id == session_id);
+    if !owned {
+        return Err(ApiError::not_found("No such session"));
     }
+    repo.delete_session(&session_id)?;
+    if session_id == current.id {
+        cookies.remove_private(Cookie::named(SESSION_COOKIE));
+    }
+    Ok(())
+}
+
+// Logs out everywhere, including the session making the request.
+#[delete("/users/sessions")]
+pub fn revoke_all_sessions(
+    user_id: LoggedInUserID,
+    repo: Repo,
+    mut cookies: Cookies,
+) -> Result<(), ApiError> {
+    repo.delete_sessions_by_user_id(user_id.0)?;
+    cookies.remove_private(Cookie::named(SESSION_COOKIE));
+    Ok(())
 }
@@ -1,3 +1,10 @@
+use super::auth_functions::{fetch_session_by_token, SESSION_COOKIE};
+use crate::models::auth_models::Session;
+use crate::repository::Repo;
+use chrono::NaiveDateTime;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
 #[derive(Deserialize)]
 pub struct CreateInfo {
     pub name: String,
@@ -10,3 +17,61 @@ pub struct LoginInfo {
     pub username: String,
     pub password: String,
 }
+
+// The session behind the request's session cookie. Fails with 401 when the cookie is missing,
+// unknown, expired or revoked.
+pub struct CurrentSession(pub Session);
+
+impl<'a, 'r> FromRequest<'a, 'r> for CurrentSession {
+    type Error = ();
+    fn from_request(request: &'a Request<'r>) -> Outcome<CurrentSession, ()> {
+        let token = match request.cookies().get_private(SESSION_COOKIE) {
+            Some(cookie) => cookie.value().to_string(),
+            None => return Outcome::Failure((Status::Unauthorized, ())),
+        };
+        let repo = request.guard::<Repo>()?;
+        match fetch_session_by_token(&*repo, &token) {
+            Ok(Some(session)) => Outcome::Success(CurrentSession(session)),
+            Ok(None) => Outcome::Failure((Status::Unauthorized, ())),
+            Err(_) => Outcome::Failure((Status::InternalServerError, ())),
+        }
+    }
+}
+
+// The client's User-Agent header, recorded with new sessions so users can tell them apart.
+pub struct UserAgent(pub Option<String>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for UserAgent {
+    type Error = ();
+    fn from_request(request: &'a Request<'r>) -> Outcome<UserAgent, ()> {
+        let user_agent = request
+            .headers()
+            .get_one("User-Agent")
+            .map(|ua| ua.chars().take(240).collect());
+        Outcome::Success(UserAgent(user_agent))
+    }
+}
+
+// A session as listed to its owner, flagging the one the request came from.
+#[derive(Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub created_at: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub user_agent: Option<String>,
+    pub current: bool,
+}
+
+impl SessionInfo {
+    pub fn new(session: Session, current_id: &str) -> Self {
+        Self {
+            current: session.id == current_id,
+            id: session.id,
+            created_at: session.created_at,
+            last_seen: session.last_seen,
+            expires_at: session.expires_at,
+            user_agent: session.user_agent,
+        }
+    }
+}
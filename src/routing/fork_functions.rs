@@ -0,0 +1,74 @@
+use super::api_error::ApiError;
+use super::quiz_functions::{ensure_quiz_visible, insert_quiz_tree};
+use super::quiz_types::*;
+use crate::models::quiz_models::*;
+use crate::repository::Repository;
+use diesel::{self, prelude::*};
+use rocket::http::Status;
+
+// Anyone who can see a quiz can fork it, unless its author turned that off. The author can
+// always fork their own.
+pub fn ensure_forkable(quiz: &Quiz, user_id: &LoggedInUserID) -> Result<(), ApiError> {
+    ensure_quiz_visible(quiz, Some(user_id))?;
+    if quiz.allow_forks || quiz.u_id == user_id.0 {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            Status::Forbidden,
+            "forking_disabled",
+            "The author of this quiz doesn't allow forks",
+        ))
+    }
+}
+
+// Copies the whole tree of 'original' into a new draft owned by 'u_id', returning the new quiz's
+// id. Should be called inside a transaction. Someone forking another author's quiz doesn't get
+// its answer key, they have to mark the correct answers again themselves.
+pub fn fork_full_quiz(original: &FullQuiz, u_id: i32, repo: &dyn Repository) -> QueryResult<i32> {
+    let IncomingFullQuiz {
+        quiz,
+        questions,
+        mut answers,
+        results,
+    } = IncomingFullQuiz::from(original);
+    if u_id != original.quiz.u_id {
+        for ans in answers.iter_mut().flatten() {
+            ans.is_correct = false;
+        }
+    }
+    let new_quiz = NewQuiz {
+        forked_from_id: Some(original.quiz.id),
+        ..NewQuiz::new(quiz, u_id, QuizStatus::Draft)
+    };
+    insert_quiz_tree(
+        new_quiz,
+        &original.quiz.tags,
+        questions,
+        answers,
+        results,
+        repo,
+    )
+}
+
+// The quizzes 'quiz' descends from, nearest first. Forks only ever point at older quizzes, so
+// this always ends.
+pub fn get_lineage(
+    quiz: &Quiz,
+    viewer: Option<&LoggedInUserID>,
+    repo: &dyn Repository,
+) -> QueryResult<Vec<Quiz>> {
+    let mut lineage = Vec::new();
+    let mut next = quiz.forked_from_id;
+    while let Some(quiz_id) = next {
+        let parent = match repo.get_quiz(quiz_id).optional()? {
+            Some(parent) => parent,
+            None => break,
+        };
+        if ensure_quiz_visible(&parent, viewer).is_err() {
+            break;
+        }
+        next = parent.forked_from_id;
+        lineage.push(parent);
+    }
+    Ok(lineage)
+}
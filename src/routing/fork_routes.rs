@@ -0,0 +1,65 @@
+use rocket::State;
+use rocket_contrib::json::Json; // Easy Json coercion
+
+use crate::models::quiz_models::Quiz;
+use crate::repository::{self, Repo};
+
+use super::api_error::ApiError;
+use super::fork_functions::*;
+use super::fork_types::*;
+use super::quiz_functions::{ensure_quiz_owner, ensure_quiz_visible, get_full_quiz};
+use super::quiz_types::*;
+use super::search_functions::{index_quiz, SearchIndex};
+use super::version_functions::record_version;
+
+// Copies a quiz into the logged in user's account as a draft of their own, returning its id.
+// The copy starts its own version history.
+#[post("/quiz/<quiz_id>/fork")]
+pub fn fork_quiz(
+    quiz_id: i32,
+    user_id: LoggedInUserID,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<i32>, ApiError> {
+    let original = get_full_quiz(quiz_id, &*repo)?;
+    ensure_forkable(&original.quiz, &user_id)?;
+
+    let fork_id = repository::transaction(&*repo, || {
+        let fork_id = fork_full_quiz(&original, user_id.0, &*repo)?;
+        let summary = format!("Forked from quiz {}", quiz_id);
+        record_version(fork_id, user_id.0, Some(summary), &*repo)?;
+        Ok(fork_id)
+    })?;
+    index_quiz(fork_id, &index, &*repo);
+    Ok(Json(fork_id))
+}
+
+#[get("/quiz/<quiz_id>/forks")]
+pub fn get_forks(
+    quiz_id: i32,
+    viewer: Option<LoggedInUserID>,
+    repo: Repo,
+) -> Result<Json<ForkInfo>, ApiError> {
+    let quiz = repo.get_quiz(quiz_id)?;
+    ensure_quiz_visible(&quiz, viewer.as_ref())?;
+    Ok(Json(ForkInfo {
+        lineage: get_lineage(&quiz, viewer.as_ref(), &*repo)?,
+        fork_count: repo.count_forks(quiz_id)?,
+    }))
+}
+
+// Lets the author turn forking of their quiz off and on again. Forks made so far stay.
+#[put("/quiz/<quiz_id>/forking", format = "json", data = "<change>")]
+pub fn set_forking(
+    quiz_id: i32,
+    change: Json<ForkingChange>,
+    user_id: LoggedInUserID,
+    repo: Repo,
+) -> Result<Json<Quiz>, ApiError> {
+    let quiz = ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    repo.update_quiz_forking(quiz_id, change.allow_forks)?;
+    Ok(Json(Quiz {
+        allow_forks: change.allow_forks,
+        ..quiz
+    }))
+}
@@ -0,0 +1,14 @@
+use crate::models::quiz_models::Quiz;
+
+// Where a quiz came from and how often it's been copied since. 'lineage' runs from the quiz it
+// was forked from back towards the first one, stopping at the first the viewer can't see.
+#[derive(Serialize, Debug)]
+pub struct ForkInfo {
+    pub lineage: Vec<Quiz>,
+    pub fork_count: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ForkingChange {
+    pub allow_forks: bool,
+}
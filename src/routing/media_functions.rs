@@ -0,0 +1,217 @@
+use super::api_error::ApiError;
+use super::quiz_types::*;
+use crate::models::media_models::*;
+use crate::repository::{self, Repository};
+use image::{ImageFormat, ImageOutputFormat};
+use rocket::fairing::AdHoc;
+use rocket::http::Status;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::PathBuf;
+
+// Thumbnails fit in a square this many pixels across, keeping their aspect ratio
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+const DEFAULT_MEDIA_DIR: &str = "media";
+const DEFAULT_MAX_BYTES: u64 = 5 * 1024 * 1024;
+// Decoding takes a few bytes per pixel whatever the file size, a small file can claim to be huge
+const DEFAULT_MAX_PIXELS: u64 = 25_000_000;
+
+// Where uploads live on disk. Each one is stored as '<dir>/<media id>', next to a PNG thumbnail
+// called '<dir>/<media id>.thumb.png'.
+pub struct MediaStore {
+    pub dir: PathBuf,
+    pub max_bytes: u64,
+    // Width times height
+    pub max_pixels: u64,
+}
+
+impl MediaStore {
+    pub fn new(dir: PathBuf, max_bytes: u64, max_pixels: u64) -> Self {
+        Self {
+            dir,
+            max_bytes,
+            max_pixels,
+        }
+    }
+
+    // Reads 'media_dir', 'media_max_bytes' and 'media_max_pixels' from Rocket.toml, falling back
+    // to a 'media' directory next to the server, 5 MiB and 25 megapixels. The directory is
+    // created if it's missing.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Media store", |rocket| {
+            let dir = rocket
+                .config()
+                .get_str("media_dir")
+                .unwrap_or(DEFAULT_MEDIA_DIR)
+                .to_string();
+            let max_bytes = match rocket.config().get_int("media_max_bytes") {
+                Ok(max_bytes) if max_bytes > 0 => max_bytes as u64,
+                _ => DEFAULT_MAX_BYTES,
+            };
+            let max_pixels = match rocket.config().get_int("media_max_pixels") {
+                Ok(max_pixels) if max_pixels > 0 => max_pixels as u64,
+                _ => DEFAULT_MAX_PIXELS,
+            };
+            if let Err(err) = fs::create_dir_all(&dir) {
+                log::error!("Couldn't create the media directory '{}': {}", dir, err);
+                return Err(rocket);
+            }
+            Ok(rocket.manage(MediaStore::new(PathBuf::from(dir), max_bytes, max_pixels)))
+        })
+    }
+
+    pub fn path(&self, media_id: i32) -> PathBuf {
+        self.dir.join(media_id.to_string())
+    }
+
+    pub fn thumbnail_path(&self, media_id: i32) -> PathBuf {
+        self.dir.join(format!("{}.thumb.png", media_id))
+    }
+
+    fn write(&self, media_id: i32, bytes: &[u8], thumbnail: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(media_id), bytes)?;
+        fs::write(self.thumbnail_path(media_id), thumbnail)
+    }
+}
+
+// Works out what an upload is from its first few bytes. Whatever Content-Type the client sent
+// is ignored, only these formats are taken.
+pub fn sniff_format(bytes: &[u8]) -> Option<(ImageFormat, &'static str)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some((ImageFormat::Png, "image/png"))
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some((ImageFormat::Jpeg, "image/jpeg"))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some((ImageFormat::Gif, "image/gif"))
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some((ImageFormat::WebP, "image/webp"))
+    } else {
+        None
+    }
+}
+
+// Reads an upload, refusing anything over the store's limit before it's all in memory.
+pub fn read_upload(body: impl Read, store: &MediaStore) -> Result<Vec<u8>, ApiError> {
+    let mut bytes = Vec::new();
+    body.take(store.max_bytes + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|_| ApiError::bad_request("The upload couldn't be read"))?;
+    if bytes.len() as u64 > store.max_bytes {
+        return Err(ApiError::new(
+            Status::PayloadTooLarge,
+            "too_large",
+            "The upload is larger than allowed",
+        )
+        .with_details(json!({ "max_bytes": store.max_bytes })));
+    }
+    Ok(bytes)
+}
+
+// Checks an upload really is a picture, then stores it with its thumbnail under 'user_id'.
+pub fn store_media(
+    bytes: &[u8],
+    user_id: i32,
+    store: &MediaStore,
+    repo: &dyn Repository,
+) -> Result<Media, ApiError> {
+    let (format, content_type) = sniff_format(bytes).ok_or_else(|| {
+        ApiError::new(
+            Status::UnsupportedMediaType,
+            "unsupported_media",
+            "Only PNG, JPEG, GIF and WebP pictures can be uploaded",
+        )
+    })?;
+    let invalid_image =
+        |_| ApiError::unprocessable("invalid_image", "The picture couldn't be decoded");
+    // Only the header is read at first, so a picture that's too big is turned away before
+    // anything is allocated for it
+    let (width, height) = image::io::Reader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(invalid_image)?;
+    if u64::from(width) * u64::from(height) > store.max_pixels {
+        return Err(ApiError::unprocessable(
+            "too_many_pixels",
+            "The picture has more pixels than allowed",
+        )
+        .with_details(json!({ "max_pixels": store.max_pixels })));
+    }
+    let picture = image::load_from_memory_with_format(bytes, format).map_err(invalid_image)?;
+    let mut thumbnail = Vec::new();
+    picture
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut thumbnail, ImageOutputFormat::Png)
+        .map_err(|_| ApiError::internal())?;
+
+    // The row goes away again if the files can't be written, so nothing points at a missing file
+    let media_id = repository::transaction(repo, || {
+        let media_id = repo.insert_media(NewMedia {
+            u_id: user_id,
+            content_type: String::from(content_type),
+            byte_size: bytes.len() as i32,
+            width: width as i32,
+            height: height as i32,
+        })?;
+        store.write(media_id, bytes, &thumbnail).map_err(|err| {
+            log::error!("Couldn't write media {}: {}", media_id, err);
+            diesel::result::Error::RollbackTransaction
+        })?;
+        Ok(media_id)
+    })?;
+    Ok(repo.get_media(media_id)?)
+}
+
+// Every picture an incoming quiz refers to, each once
+pub fn referenced_media(f_quiz: &IncomingFullQuiz) -> BTreeSet<i32> {
+    let quiz_media = f_quiz.quiz.media_id;
+    let question_media = f_quiz.questions.iter().map(|qs| qs.media_id);
+    let answer_media = f_quiz.answers.iter().flatten().map(|ans| ans.media_id);
+    let result_media = f_quiz.results.iter().map(|res| res.media_id);
+    std::iter::once(quiz_media)
+        .chain(question_media)
+        .chain(answer_media)
+        .chain(result_media)
+        .flatten()
+        .collect()
+}
+
+// A quiz can show the user's own uploads, and keep whatever it already shows. That way an author
+// can't put someone else's pictures in their quiz, while an edit of a fork leaves the pictures
+// it came with alone.
+pub fn check_media(
+    f_quiz: &IncomingFullQuiz,
+    user_id: &LoggedInUserID,
+    current: Option<&FullQuiz>,
+    repo: &dyn Repository,
+) -> Result<(), ApiError> {
+    let kept = current.map(|full_quiz| referenced_media(&IncomingFullQuiz::from(full_quiz)));
+    let wanted: Vec<i32> = referenced_media(f_quiz)
+        .into_iter()
+        .filter(|media_id| !kept.iter().any(|kept| kept.contains(media_id)))
+        .collect();
+    if wanted.is_empty() {
+        return Ok(());
+    }
+    let owned: Vec<i32> = repo
+        .get_media_by_ids(&wanted)?
+        .into_iter()
+        .filter(|media| media.u_id == user_id.0)
+        .map(|media| media.id)
+        .collect();
+    let unknown: Vec<i32> = wanted
+        .into_iter()
+        .filter(|media_id| !owned.contains(media_id))
+        .collect();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::unprocessable(
+            "unknown_media",
+            "The quiz refers to pictures that don't exist or belong to someone else",
+        )
+        .with_details(json!({ "media_ids": unknown })))
+    }
+}
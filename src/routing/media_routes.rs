@@ -0,0 +1,75 @@
+use rocket::data::Data;
+use rocket::http::ContentType;
+use rocket::State;
+use rocket_contrib::json::Json; // Easy Json coercion
+use std::fs::File;
+use std::path::PathBuf;
+
+use crate::models::media_models::Media;
+use crate::repository::Repo;
+
+use super::api_error::ApiError;
+use super::media_functions::*;
+use super::media_types::*;
+use super::quiz_types::LoggedInUserID;
+
+// Takes a picture as the raw request body and hands back its record, whose id can then be put
+// in the 'media_id' of a quiz, question, answer or result. The body's Content-Type doesn't
+// matter, the bytes are checked for what they really are.
+#[post("/media", data = "<data>")]
+pub fn upload_media(
+    data: Data,
+    user_id: LoggedInUserID,
+    store: State<MediaStore>,
+    repo: Repo,
+) -> Result<Json<Media>, ApiError> {
+    let bytes = read_upload(data.open(), &store)?;
+    Ok(Json(store_media(&bytes, user_id.0, &store, &*repo)?))
+}
+
+#[get("/media/<media_id>")]
+pub fn get_media(
+    media_id: i32,
+    store: State<MediaStore>,
+    repo: Repo,
+) -> Result<MediaFile, ApiError> {
+    let media = repo.get_media(media_id)?;
+    let content_type =
+        ContentType::parse_flexible(&media.content_type).unwrap_or(ContentType::Binary);
+    open_media(
+        store.path(media_id),
+        content_type,
+        format!("\"{}\"", media_id),
+    )
+}
+
+// The picture shrunk down to fit THUMBNAIL_SIZE, always a PNG.
+#[get("/media/<media_id>/thumbnail")]
+pub fn get_thumbnail(
+    media_id: i32,
+    store: State<MediaStore>,
+    repo: Repo,
+) -> Result<MediaFile, ApiError> {
+    repo.get_media(media_id)?;
+    open_media(
+        store.thumbnail_path(media_id),
+        ContentType::PNG,
+        format!("\"{}-thumb\"", media_id),
+    )
+}
+
+fn open_media(
+    path: PathBuf,
+    content_type: ContentType,
+    etag: String,
+) -> Result<MediaFile, ApiError> {
+    let file = File::open(&path).map_err(|err| {
+        log::error!("Couldn't open {}: {}", path.display(), err);
+        ApiError::not_found("The file for this upload is missing")
+    })?;
+    Ok(MediaFile {
+        file,
+        content_type,
+        etag,
+    })
+}
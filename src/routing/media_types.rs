@@ -0,0 +1,36 @@
+use rocket::http::{ContentType, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use std::fs::File;
+
+// Uploads never change once stored, so clients and proxies can hold on to them for good
+pub const MEDIA_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+// A stored file on its way out. Answers 304 without a body when the client already has it.
+pub struct MediaFile {
+    pub file: File,
+    pub content_type: ContentType,
+    pub etag: String,
+}
+
+impl<'r> Responder<'r> for MediaFile {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let mut response = Response::build();
+        response
+            .raw_header("Cache-Control", MEDIA_CACHE_CONTROL)
+            .raw_header("ETag", self.etag.clone());
+
+        let cached = request
+            .headers()
+            .get("If-None-Match")
+            .flat_map(|tags| tags.split(','))
+            .any(|tag| tag.trim() == self.etag || tag.trim() == "*");
+        if cached {
+            return response.status(Status::NotModified).ok();
+        }
+        response
+            .header(self.content_type)
+            .sized_body(self.file)
+            .ok()
+    }
+}
@@ -1,6 +1,81 @@
+pub mod api_error;
+pub mod api_version;
 pub mod auth_functions;
 pub mod auth_routes;
 pub mod auth_types;
+pub mod fork_functions;
+pub mod fork_routes;
+pub mod fork_types;
+pub mod media_functions;
+pub mod media_routes;
+pub mod media_types;
+pub mod pagination;
 pub mod quiz_functions;
 pub mod quiz_routes;
 pub mod quiz_types;
+pub mod quiz_validation;
+pub mod search_functions;
+pub mod search_types;
+pub mod submission_functions;
+pub mod submission_routes;
+pub mod submission_types;
+pub mod suggest_functions;
+pub mod version_functions;
+pub mod version_routes;
+pub mod version_types;
+
+use rocket::{Catcher, Route};
+
+// Everything the server mounts at "/", shared with the route tests
+pub fn routes() -> Vec<Route> {
+    routes![
+        quiz_routes::index,
+        quiz_routes::get_full_quiz_route,
+        quiz_routes::get_nested_quiz_route,
+        quiz_routes::insert_quiz,
+        quiz_routes::edit_quiz,
+        quiz_routes::validate_quiz,
+        quiz_routes::create_draft,
+        quiz_routes::save_draft,
+        quiz_routes::reorder,
+        quiz_routes::publish,
+        quiz_routes::set_status,
+        quiz_routes::browse,
+        quiz_routes::search,
+        quiz_routes::suggest,
+        quiz_routes::list_tags,
+        quiz_routes::get_quizzes_by_user_id,
+        quiz_routes::delete,
+        fork_routes::fork_quiz,
+        fork_routes::get_forks,
+        fork_routes::set_forking,
+        version_routes::list_versions,
+        version_routes::get_version,
+        version_routes::diff_versions,
+        version_routes::restore_version,
+        media_routes::upload_media,
+        media_routes::get_media,
+        media_routes::get_thumbnail,
+        submission_routes::submit,
+        submission_routes::create_attempt,
+        submission_routes::get_attempt,
+        submission_routes::get_attempts_by_user,
+        auth_routes::create,
+        auth_routes::login,
+        auth_routes::fetch_info_by_user_id,
+        auth_routes::logout,
+        auth_routes::list_sessions,
+        auth_routes::revoke_session,
+        auth_routes::revoke_all_sessions,
+    ]
+}
+
+pub fn catchers() -> Vec<Catcher> {
+    catchers![
+        api_error::unauthorized,
+        api_error::not_found,
+        api_error::not_acceptable,
+        api_error::unprocessable_entity,
+        api_error::internal_error,
+    ]
+}
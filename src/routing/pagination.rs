@@ -0,0 +1,156 @@
+use super::api_error::ApiError;
+use crate::models::quiz_models::Quiz;
+use rocket::http::Status;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(FromFormValue, Clone, Copy, Debug, PartialEq)]
+pub enum SortOrder {
+    Name,
+    Newest,
+    Popular,
+}
+
+// The query parameters every listing route takes, e.g. '?limit=20&sort=popular&cursor=...'.
+// All of them are optional, each route picks its own defaults.
+#[derive(FromForm, Debug, Default)]
+pub struct PageParams {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: Option<SortOrder>,
+}
+
+// PageParams with the defaults filled in and the cursor decoded. 'after' is None for the first
+// page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageRequest {
+    pub limit: i64,
+    pub sort: SortOrder,
+    pub after: Option<Cursor>,
+}
+
+// Where a page picks up: the sort key and id of the last item on the page before it. Pages
+// start at whatever comes after that rather than at a count of items, so quizzes added or
+// deleted in between never make a page skip or repeat one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Cursor {
+    Name(String, i32),
+    Newest(i32),
+    // How many attempts the quiz had
+    Popular(i64, i32),
+    // Search hits in order of how well they match, see search_quizzes
+    Relevance(f32, i32),
+}
+
+impl Cursor {
+    // The cursor for the page after 'quiz' in a listing sorted by 'sort'. 'attempts' is how many
+    // it has, only needed for SortOrder::Popular.
+    pub fn after(sort: SortOrder, quiz: &Quiz, attempts: i64) -> Self {
+        match sort {
+            SortOrder::Name => Cursor::Name(quiz.name.clone(), quiz.id),
+            SortOrder::Newest => Cursor::Newest(quiz.id),
+            SortOrder::Popular => Cursor::Popular(attempts, quiz.id),
+        }
+    }
+
+    // Whether an item at this position comes after 'last' in the listing, i.e. belongs on the
+    // page 'last' is the cursor for. Positions in different orders never compare.
+    pub fn is_past(&self, last: &Cursor) -> bool {
+        match (self, last) {
+            (Cursor::Name(name, id), Cursor::Name(last_name, last_id)) => {
+                (name, id) > (last_name, last_id)
+            }
+            (Cursor::Newest(id), Cursor::Newest(last_id)) => id < last_id,
+            (Cursor::Popular(attempts, id), Cursor::Popular(last_attempts, last_id)) => {
+                (attempts, id) < (last_attempts, last_id)
+            }
+            (Cursor::Relevance(score, id), Cursor::Relevance(last_score, last_id)) => {
+                score < last_score || (score == last_score && id > last_id)
+            }
+            _ => false,
+        }
+    }
+
+    // Whether the cursor came from a listing sorted by 'sort', or in order of relevance when
+    // 'by_relevance' is on. Only search lists by relevance.
+    fn fits(&self, sort: SortOrder, by_relevance: bool) -> bool {
+        match self {
+            Cursor::Relevance(..) => by_relevance,
+            _ if by_relevance => false,
+            Cursor::Name(..) => sort == SortOrder::Name,
+            Cursor::Newest(_) => sort == SortOrder::Newest,
+            Cursor::Popular(..) => sort == SortOrder::Popular,
+        }
+    }
+}
+
+// For a cursor that's malformed, or from a listing in another order
+pub fn invalid_cursor() -> ApiError {
+    ApiError::new(
+        Status::BadRequest,
+        "invalid_cursor",
+        "The cursor is not one this server handed out",
+    )
+}
+
+impl PageParams {
+    // 'by_relevance' is for search, which lists best match first and hands out relevance cursors
+    // instead of ones for 'sort'. Every other listing leaves it off.
+    pub fn resolve(
+        &self,
+        default_limit: i64,
+        default_sort: SortOrder,
+        by_relevance: bool,
+    ) -> Result<PageRequest, ApiError> {
+        let limit = self.limit.unwrap_or(default_limit);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(ApiError::new(
+                Status::BadRequest,
+                "invalid_limit",
+                &format!("limit has to be between 1 and {}", MAX_PAGE_SIZE),
+            ));
+        }
+        let sort = self.sort.unwrap_or(default_sort);
+        let after = match &self.cursor {
+            Some(cursor) => Some(
+                decode_cursor(cursor)
+                    .filter(|cursor| cursor.fits(sort, by_relevance))
+                    .ok_or_else(invalid_cursor)?,
+            ),
+            None => None,
+        };
+        Ok(PageRequest { limit, sort, after })
+    }
+}
+
+// One page of a listing. 'next_cursor' is passed back to get the following page and is None on
+// the last one, 'total' counts every item across all pages.
+#[derive(Serialize, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total: i64,
+}
+
+impl<T> Page<T> {
+    // 'next' is where the page after this one starts, None if this is the last
+    pub fn new(items: Vec<T>, next: Option<Cursor>, total: i64) -> Self {
+        Self {
+            items,
+            next_cursor: next.as_ref().map(encode_cursor),
+            total,
+        }
+    }
+}
+
+// Cursors are opaque to clients, so what they carry can change without breaking anyone.
+pub fn encode_cursor(cursor: &Cursor) -> String {
+    let json = serde_json::to_string(cursor).expect("a cursor always serializes");
+    base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+}
+
+pub fn decode_cursor(cursor: &str) -> Option<Cursor> {
+    let decoded = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+    serde_json::from_slice(&decoded).ok()
+}
@@ -1,67 +1,701 @@
+use super::api_error::ApiError;
+use super::pagination::{Page, PageRequest};
 use super::quiz_types::*;
+use super::quiz_validation::validate_full_quiz;
 use crate::models::quiz_models::*;
+use crate::models::tag_models::*;
+use crate::repository::{QuizFacets, QuizFilter, Repository};
+use chrono::Utc;
 use diesel::{self, prelude::*};
-use rocket::response::status::NotFound;
-use rocket_contrib::json::Json;
-pub fn get_full_quiz(
+use rocket::http::Status;
+use std::collections::BTreeMap;
+
+pub fn get_full_quiz(quiz_id: i32, repo: &dyn Repository) -> QueryResult<FullQuiz> {
+    // Cannot do these concurrently, because they are all using the same db connection.
+    // Seven queries no matter how big the quiz is, tests/mysql_query_count.rs holds it to that.
+    let mut quiz = repo.get_quiz(quiz_id)?;
+    attach_tags(std::slice::from_mut(&mut quiz), repo)?;
+    let questions = repo.get_questions(quiz_id)?;
+    let mut answers = get_answers(&questions, repo)?;
+    let results = repo.get_results(quiz_id)?;
+    attach_weights(&mut answers, &results, repo)?;
+    attach_rules(&mut answers, &questions, repo)?;
+    Ok(FullQuiz {
+        quiz,
+        questions,
+        answers,
+        results,
+    })
+}
+
+// One page of quizzes with their tags, along with how many match in total.
+pub fn list_quizzes(
+    filter: &QuizFilter,
+    facets: &QuizFacets,
+    page: &PageRequest,
+    repo: &dyn Repository,
+) -> QueryResult<Page<Quiz>> {
+    let (mut items, total, next) = repo.list_quizzes(filter, facets, page)?;
+    attach_tags(&mut items, repo)?;
+    Ok(Page::new(items, next, total))
+}
+
+// Tags and categories are compared the way they're stored, so 'Board  Games' finds 'board games'
+pub fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// The facets a listing was asked to narrow down to, blank ones left out
+pub fn quiz_facets(tag: Option<String>, category: Option<String>) -> QuizFacets {
+    let normalized = |label: Option<String>| {
+        label
+            .map(|label| normalize_label(&label))
+            .filter(|label| !label.is_empty())
+    };
+    QuizFacets {
+        tag: normalized(tag),
+        category: normalized(category),
+    }
+}
+
+// Brings an incoming quiz's category and tags into their stored form. Tags end up sorted and
+// without repeats, the way get_full_quiz hands them back.
+pub fn normalize_labels(quiz: &mut IncomingQuiz) {
+    quiz.category = quiz
+        .category
+        .as_deref()
+        .map(normalize_label)
+        .filter(|category| !category.is_empty());
+    let mut tags: Vec<String> = quiz
+        .tags
+        .iter()
+        .map(|tag| normalize_label(tag))
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    quiz.tags = tags;
+}
+
+// Guards every route that changes a quiz. Hands back the quiz if the logged in user is its
+// author, otherwise a 403 (or a 404 if there is no such quiz).
+pub fn ensure_quiz_owner(
     quiz_id: i32,
-    conn: &diesel::MysqlConnection,
-) -> Result<Json<FullQuiz>, NotFound<RouteError>> {
-    // Cannot do these concurrently, because they are all using the same db connection
-    let quiz = get_quiz(quiz_id, conn)?;
-    let questions = get_questions(quiz_id, conn)?;
-    let answers = get_answers(&questions, conn)?;
-    let results = get_results(quiz_id, conn)?;
-    Ok(Json(FullQuiz {
+    user_id: &LoggedInUserID,
+    repo: &dyn Repository,
+) -> Result<Quiz, ApiError> {
+    let quiz = repo.get_quiz(quiz_id)?;
+    if quiz.u_id == user_id.0 {
+        Ok(quiz)
+    } else {
+        Err(ApiError::forbidden(
+            "Only the author of a quiz can change it",
+        ))
+    }
+}
+
+// Drafts are their author's business, anyone else is told there's no such quiz.
+pub fn ensure_quiz_visible(quiz: &Quiz, viewer: Option<&LoggedInUserID>) -> Result<(), ApiError> {
+    let is_author = viewer.map(|user_id| user_id.0) == Some(quiz.u_id);
+    if quiz.status == QuizStatus::Draft && !is_author {
+        Err(ApiError::not_found("The requested record does not exist"))
+    } else {
+        Ok(())
+    }
+}
+
+// Clears which answers are correct for anyone but the author, they'd give a trivia quiz away.
+// A false is_correct is left out of the JSON, so nothing about it is left to see.
+pub fn hide_correct_answers(full_quiz: &mut FullQuiz, viewer: Option<&LoggedInUserID>) {
+    if viewer.map(|user_id| user_id.0) != Some(full_quiz.quiz.u_id) {
+        for ans in full_quiz.answers.iter_mut().flatten() {
+            ans.is_correct = false;
+        }
+    }
+}
+
+// Moves a quiz to 'status'. Anything but a draft can be seen by others, so the stored quiz has
+// to pass the full validation first. A quiz that's been out can't go back to being a draft, and
+// 'published_at' keeps the first time it went out.
+pub fn change_quiz_status(
+    quiz_id: i32,
+    status: QuizStatus,
+    repo: &dyn Repository,
+) -> Result<Quiz, ApiError> {
+    let full_quiz = get_full_quiz(quiz_id, repo)?;
+    if status == QuizStatus::Draft {
+        return match full_quiz.quiz.status {
+            QuizStatus::Draft => Ok(full_quiz.quiz),
+            _ => Err(ApiError::new(
+                Status::Conflict,
+                "already_published",
+                "A quiz can't go back to being a draft once it's been out",
+            )),
+        };
+    }
+    validate_full_quiz(&IncomingFullQuiz::from(&full_quiz))?;
+
+    let published_at = full_quiz
+        .quiz
+        .published_at
+        .or_else(|| Some(Utc::now().naive_utc()));
+    repo.update_quiz_status(quiz_id, status, published_at)?;
+    Ok(Quiz {
+        status,
+        published_at,
+        ..full_quiz.quiz
+    })
+}
+
+// A new order has to list every question (or every answer to the question) exactly once, so
+// nothing can be left without a place.
+pub fn check_order(current: &FullQuiz, change: &OrderChange) -> Result<(), ApiError> {
+    let (mut stored, given): (Vec<i32>, &[i32]) = match change {
+        OrderChange::Questions { questions } => (
+            current.questions.iter().map(|qs| qs.id).collect(),
+            questions,
+        ),
+        OrderChange::Answers {
+            question_id,
+            answers,
+        } => {
+            let position = current
+                .questions
+                .iter()
+                .position(|qs| qs.id == *question_id)
+                .ok_or_else(|| ApiError::not_found("The requested record does not exist"))?;
+            (
+                current.answers[position].iter().map(|ans| ans.id).collect(),
+                answers,
+            )
+        }
+    };
+    let mut given = given.to_vec();
+    stored.sort_unstable();
+    given.sort_unstable();
+    if stored != given {
+        return Err(ApiError::unprocessable(
+            "invalid_order",
+            "A new order has to list every one of them exactly once",
+        ));
+    }
+
+    // Rules keep leading to the same questions, but which question comes next without one
+    // changes, and a quiz that's out can't be moved into a loop
+    if let OrderChange::Questions { questions } = change {
+        if current.quiz.status != QuizStatus::Draft {
+            validate_full_quiz(&reordered(current, questions))?;
+        }
+    }
+    Ok(())
+}
+
+// The quiz with its questions in 'order', a list of their ids, as it would be stored after
+// apply_order.
+fn reordered(current: &FullQuiz, order: &[i32]) -> IncomingFullQuiz {
+    let new_position = |old: usize| {
+        current
+            .questions
+            .get(old)
+            .and_then(|qs| order.iter().position(|question_id| *question_id == qs.id))
+            .unwrap_or(old)
+    };
+    let IncomingFullQuiz {
+        quiz,
+        questions,
+        mut answers,
+        results,
+    } = IncomingFullQuiz::from(current);
+    for ans in answers.iter_mut().flatten() {
+        if let Some(NextQuestion::Question(to)) = ans.next_question {
+            let to = new_position(to as usize) as i32;
+            ans.next_question = Some(NextQuestion::Question(to));
+        }
+    }
+    let mut moved: Vec<(usize, (IncomingQuestion, Vec<IncomingAnswer>))> = questions
+        .into_iter()
+        .zip(answers)
+        .enumerate()
+        .map(|(old, moving)| (new_position(old), moving))
+        .collect();
+    moved.sort_by_key(|(position, _)| *position);
+    let (questions, answers) = moved.into_iter().map(|(_, moving)| moving).unzip();
+    IncomingFullQuiz {
         quiz,
         questions,
         answers,
         results,
-    }))
+    }
 }
 
-fn get_quiz(quiz_id: i32, conn: &diesel::MysqlConnection) -> Result<Quiz, NotFound<RouteError>> {
-    use crate::schema::quiz::dsl::quiz as quiz_table;
-    quiz_table
-        .find(quiz_id)
-        .first::<Quiz>(conn)
-        .map_err(|msg| NotFound(msg.into()))
+// Moves everything into the order given, once check_order is happy with it. Should be called
+// inside a transaction so nothing is left half moved.
+pub fn apply_order(change: &OrderChange, repo: &dyn Repository) -> QueryResult<()> {
+    match change {
+        OrderChange::Questions { questions } => {
+            for (i, question_id) in questions.iter().enumerate() {
+                repo.move_question(*question_id, i as i32)?;
+            }
+        }
+        OrderChange::Answers { answers, .. } => {
+            for (i, answer_id) in answers.iter().enumerate() {
+                repo.move_answer(*answer_id, i as i32)?;
+            }
+        }
+    }
+    Ok(())
 }
 
-fn get_questions(
+// One query for the answers to every question, grouped back up in memory. The groups line up
+// with 'questions', including empty ones for questions without answers.
+fn get_answers(questions: &[Question], repo: &dyn Repository) -> QueryResult<Vec<Vec<Answer>>> {
+    let question_ids: Vec<i32> = questions.iter().map(|qs| qs.id).collect();
+    Ok(repo.get_answers(&question_ids)?.grouped_by(questions))
+}
+
+// Fills in each answer's weights, keyed by the 'num' of the result they count towards.
+fn attach_weights(
+    answers: &mut [Vec<Answer>],
+    results: &[QuizResult],
+    repo: &dyn Repository,
+) -> QueryResult<()> {
+    let answer_ids: Vec<i32> = answers.iter().flatten().map(|ans| ans.id).collect();
+    let weights = repo.get_answer_weights(&answer_ids)?;
+    for ans in answers.iter_mut().flatten() {
+        for weight in weights.iter().filter(|weight| weight.a_id == ans.id) {
+            if let Some(res) = results.iter().find(|res| res.id == weight.r_id) {
+                ans.weights.insert(res.num, weight.weight);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Fills in the tags of each quiz, with one query for all of them.
+pub fn attach_tags(quizzes: &mut [Quiz], repo: &dyn Repository) -> QueryResult<()> {
+    let quiz_ids: Vec<i32> = quizzes.iter().map(|qz| qz.id).collect();
+    let quiz_tags = repo.get_quiz_tags(&quiz_ids)?;
+    for qz in quizzes.iter_mut() {
+        qz.tags = quiz_tags
+            .iter()
+            .filter(|quiz_tag| quiz_tag.qz_id == qz.id)
+            .map(|quiz_tag| quiz_tag.name.clone())
+            .collect();
+    }
+    Ok(())
+}
+
+// Replaces the tags of a quiz with 'names', which have to be normalized already. Tags nobody has
+// used before are created on the way.
+fn store_tags(quiz_id: i32, names: &[String], repo: &dyn Repository) -> QueryResult<()> {
+    repo.delete_quiz_tags(quiz_id)?;
+    if names.is_empty() {
+        return Ok(());
+    }
+    let mut tags = repo.get_tags_by_names(names)?;
+    let missing: Vec<NewTag> = names
+        .iter()
+        .filter(|name| !tags.iter().any(|tag| tag.name == **name))
+        .map(|name| NewTag { name: name.clone() })
+        .collect();
+    if !missing.is_empty() {
+        repo.insert_tags(missing)?;
+        tags = repo.get_tags_by_names(names)?;
+    }
+    repo.insert_quiz_tags(
+        tags.iter()
+            .map(|tag| NewQuizTag {
+                qz_id: quiz_id,
+                tag_id: tag.id,
+            })
+            .collect(),
+    )
+}
+
+// Fills in where each answer leads, with the question by its position in 'questions'.
+fn attach_rules(
+    answers: &mut [Vec<Answer>],
+    questions: &[Question],
+    repo: &dyn Repository,
+) -> QueryResult<()> {
+    let answer_ids: Vec<i32> = answers.iter().flatten().map(|ans| ans.id).collect();
+    let rules = repo.get_answer_rules(&answer_ids)?;
+    for ans in answers.iter_mut().flatten() {
+        if let Some(rule) = rules.iter().find(|rule| rule.a_id == ans.id) {
+            ans.next_question = match rule.next_q_id {
+                None => Some(NextQuestion::End),
+                Some(next_q_id) => questions
+                    .iter()
+                    .position(|qs| qs.id == next_q_id)
+                    .map(|position| NextQuestion::Question(position as i32)),
+            };
+        }
+    }
+    Ok(())
+}
+
+// What an incoming answer says about the rest of its quiz, the results it's weighted towards
+// and the question it leads to. Both point at records by position, so they can only be stored
+// once everything else is.
+#[derive(Default)]
+struct AnswerLinks {
+    weights: BTreeMap<i32, i32>,
+    next_question: Option<NextQuestion>,
+}
+
+impl AnswerLinks {
+    fn is_empty(&self) -> bool {
+        self.weights.is_empty() && self.next_question.is_none()
+    }
+}
+
+// Replaces the weights and rules of every answer in a quiz. 'links' lines up with the quiz's
+// questions and answers as they're stored. Links to a result or question that doesn't exist are
+// dropped, only a draft can have those.
+fn store_links(
     quiz_id: i32,
-    conn: &diesel::MysqlConnection,
-) -> Result<Vec<Question>, NotFound<RouteError>> {
-    use crate::schema::question::dsl::{question as question_table, qz_id};
-    question_table
-        .filter(qz_id.eq(quiz_id))
-        .load::<Question>(conn)
-        .map_err(|msg| NotFound(msg.into()))
-}
-
-fn get_answers(
-    questions: &Vec<Question>,
-    conn: &diesel::MysqlConnection,
-) -> Result<Vec<Vec<Answer>>, NotFound<RouteError>> {
-    use crate::schema::answer::dsl::{answer as answer_table, q_id};
-    let mut answers: Vec<Vec<Answer>> = Vec::new();
-    for cur_question in questions {
-        let inner_answers = answer_table
-            .filter(q_id.eq(cur_question.id))
-            .load::<Answer>(conn)
-            .map_err(|msg| NotFound(msg.into()))?;
-        answers.push(inner_answers);
-    }
-    Ok(answers)
-}
-
-fn get_results(
+    links: Vec<Vec<AnswerLinks>>,
+    repo: &dyn Repository,
+) -> QueryResult<()> {
+    let questions = repo.get_questions(quiz_id)?;
+    let answers = get_answers(&questions, repo)?;
+    let results = repo.get_results(quiz_id)?;
+    let answer_ids: Vec<i32> = answers.iter().flatten().map(|ans| ans.id).collect();
+    repo.delete_answer_weights(&answer_ids)?;
+    repo.delete_answer_rules(&answer_ids)?;
+
+    let mut new_weights = Vec::new();
+    let mut new_rules = Vec::new();
+    for (question_answers, question_links) in answers.iter().zip(links) {
+        for (ans, answer_links) in question_answers.iter().zip(question_links) {
+            for (num, weight) in answer_links.weights {
+                if let Some(res) = results.iter().find(|res| res.num == num) {
+                    new_weights.push(NewAnswerWeight {
+                        a_id: ans.id,
+                        r_id: res.id,
+                        weight,
+                    });
+                }
+            }
+            let next_q_id = match answer_links.next_question {
+                None => continue,
+                Some(NextQuestion::End) => None,
+                Some(NextQuestion::Question(position)) => {
+                    match usize::try_from(position)
+                        .ok()
+                        .and_then(|position| questions.get(position))
+                    {
+                        Some(qs) => Some(qs.id),
+                        None => continue,
+                    }
+                }
+            };
+            new_rules.push(NewAnswerRule {
+                a_id: ans.id,
+                next_q_id,
+            });
+        }
+    }
+    if !new_weights.is_empty() {
+        repo.insert_answer_weights(new_weights)?;
+    }
+    if !new_rules.is_empty() {
+        repo.insert_answer_rules(new_rules)?;
+    }
+    Ok(())
+}
+
+// Takes the links out of incoming answers, for store_links once the answers are stored.
+fn take_links(answers: &mut [Vec<IncomingAnswer>]) -> Vec<Vec<AnswerLinks>> {
+    answers
+        .iter_mut()
+        .map(|question_answers| {
+            question_answers
+                .iter_mut()
+                .map(|ans| AnswerLinks {
+                    weights: std::mem::take(&mut ans.weights),
+                    next_question: ans.next_question.take(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn has_links(links: &[Vec<AnswerLinks>]) -> bool {
+    links
+        .iter()
+        .flatten()
+        .any(|answer_links| !answer_links.is_empty())
+}
+
+// Stores a brand new quiz tree, returning the new quiz's id. Should be called inside a
+// transaction.
+pub fn insert_full_quiz(
+    f_quiz: IncomingFullQuiz,
+    u_id: i32,
+    status: QuizStatus,
+    repo: &dyn Repository,
+) -> QueryResult<i32> {
+    let IncomingFullQuiz {
+        mut quiz,
+        questions,
+        answers,
+        results,
+    } = f_quiz;
+    normalize_labels(&mut quiz);
+    let tags = std::mem::take(&mut quiz.tags);
+    insert_quiz_tree(
+        NewQuiz::new(quiz, u_id, status),
+        &tags,
+        questions,
+        answers,
+        results,
+        repo,
+    )
+}
+
+// The rest of insert_full_quiz, for callers that need a say in how the quiz row is made.
+// Any ids on the incoming records are ignored, and the tags have to be normalized already.
+pub fn insert_quiz_tree(
+    new_quiz: NewQuiz,
+    tags: &[String],
+    questions: Vec<IncomingQuestion>,
+    answers: Vec<Vec<IncomingAnswer>>,
+    results: Vec<IncomingQuizResult>,
+    repo: &dyn Repository,
+) -> QueryResult<i32> {
+    let mut answers = answers;
+    let links = take_links(&mut answers);
+    let quiz_id = repo.insert_quiz(new_quiz)?;
+    if !tags.is_empty() {
+        store_tags(quiz_id, tags, repo)?;
+    }
+    for (i, (qs, question_answers)) in questions.into_iter().zip(answers).enumerate() {
+        let question_id = repo.insert_question(NewQuestion {
+            description: qs.description,
+            qz_id: quiz_id,
+            position: i as i32,
+            kind: qs.kind,
+            config: qs.config,
+            media_id: qs.media_id,
+        })?;
+        repo.insert_answers(
+            question_answers
+                .into_iter()
+                .enumerate()
+                .map(|(j, ans)| NewAnswer {
+                    description: ans.description,
+                    val: ans.val,
+                    q_id: question_id,
+                    position: j as i32,
+                    is_correct: ans.is_correct,
+                    media_id: ans.media_id,
+                })
+                .collect(),
+        )?;
+    }
+
+    repo.insert_results(
+        results
+            .into_iter()
+            .enumerate()
+            .map(|(i, res)| NewQuizResult {
+                num: i as i32,
+                header: res.header,
+                description: res.description,
+                qz_id: quiz_id,
+                min_percent: res.min_percent,
+                max_percent: res.max_percent,
+                media_id: res.media_id,
+            })
+            .collect(),
+    )?;
+    if has_links(&links) {
+        store_links(quiz_id, links, repo)?;
+    }
+    Ok(quiz_id)
+}
+
+// Applies an edited quiz tree on top of the stored one. Records carrying an id are updated in
+// place, records without one are inserted, and stored records missing from the edit are deleted.
+// The ids are expected to have been through validate_edit, ones that don't belong to the quiz
+// being edited still fail with NotFound rather than touching anything else. Should be called
+// inside a transaction so a failure halfway through leaves the quiz untouched.
+pub fn edit_full_quiz(
+    current: &FullQuiz,
+    edited: IncomingFullQuiz,
+    repo: &dyn Repository,
+) -> QueryResult<()> {
+    let quiz_id = current.quiz.id;
+
+    let IncomingFullQuiz {
+        quiz: mut edited_quiz,
+        questions,
+        mut answers,
+        results,
+    } = edited;
+    let links = take_links(&mut answers);
+
+    normalize_labels(&mut edited_quiz);
+    repo.update_quiz(
+        quiz_id,
+        &edited_quiz.name,
+        &edited_quiz.description,
+        edited_quiz.mode,
+        edited_quiz.category.as_deref(),
+    )?;
+    if edited_quiz.tags != current.quiz.tags {
+        store_tags(quiz_id, &edited_quiz.tags, repo)?;
+    }
+    if edited_quiz.media_id != current.quiz.media_id {
+        repo.set_quiz_media(quiz_id, edited_quiz.media_id)?;
+    }
+
+    // Deleting a question takes its answers with it through the foreign key cascade
+    let kept_question_ids: Vec<i32> = questions.iter().filter_map(|qs| qs.id).collect();
+    for stored in &current.questions {
+        if !kept_question_ids.contains(&stored.id) {
+            repo.delete_question(stored.id)?;
+        }
+    }
+
+    // Everything ends up where the edit lists it
+    for (i, (qs, question_answers)) in questions.into_iter().zip(answers).enumerate() {
+        let (question_id, stored_answers) = match qs.id {
+            Some(question_id) => {
+                let stored = current
+                    .questions
+                    .iter()
+                    .position(|stored| stored.id == question_id)
+                    .ok_or(diesel::result::Error::NotFound)?;
+                repo.update_question(question_id, &qs.description, qs.kind, &qs.config, i as i32)?;
+                if qs.media_id != current.questions[stored].media_id {
+                    repo.set_question_media(question_id, qs.media_id)?;
+                }
+                (question_id, &current.answers[stored][..])
+            }
+            None => {
+                let question_id = repo.insert_question(NewQuestion {
+                    description: qs.description,
+                    qz_id: quiz_id,
+                    position: i as i32,
+                    kind: qs.kind,
+                    config: qs.config,
+                    media_id: qs.media_id,
+                })?;
+                (question_id, &[][..])
+            }
+        };
+        edit_answers(question_id, stored_answers, question_answers, repo)?;
+    }
+
+    edit_results(quiz_id, &current.results, results, repo)?;
+    let had_links = current
+        .answers
+        .iter()
+        .flatten()
+        .any(|ans| !ans.weights.is_empty() || ans.next_question.is_some());
+    if had_links || has_links(&links) {
+        store_links(quiz_id, links, repo)?;
+    }
+    Ok(())
+}
+
+fn edit_answers(
+    question_id: i32,
+    stored: &[Answer],
+    edited: Vec<IncomingAnswer>,
+    repo: &dyn Repository,
+) -> QueryResult<()> {
+    let kept_answer_ids: Vec<i32> = edited.iter().filter_map(|ans| ans.id).collect();
+    for stored_answer in stored {
+        if !kept_answer_ids.contains(&stored_answer.id) {
+            repo.delete_answer(stored_answer.id)?;
+        }
+    }
+
+    for (i, ans) in edited.into_iter().enumerate() {
+        match ans.id {
+            Some(answer_id) => {
+                let stored_answer = stored
+                    .iter()
+                    .find(|stored_answer| stored_answer.id == answer_id)
+                    .ok_or(diesel::result::Error::NotFound)?;
+                repo.update_answer(
+                    answer_id,
+                    &ans.description,
+                    ans.val,
+                    ans.is_correct,
+                    i as i32,
+                )?;
+                if ans.media_id != stored_answer.media_id {
+                    repo.set_answer_media(answer_id, ans.media_id)?;
+                }
+            }
+            None => {
+                repo.insert_answers(vec![NewAnswer {
+                    description: ans.description,
+                    val: ans.val,
+                    q_id: question_id,
+                    position: i as i32,
+                    is_correct: ans.is_correct,
+                    media_id: ans.media_id,
+                }])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Results are renumbered by their position in the edit, the same way insert_quiz numbers them.
+fn edit_results(
     quiz_id: i32,
-    conn: &diesel::MysqlConnection,
-) -> Result<Vec<QuizResult>, NotFound<RouteError>> {
-    use crate::schema::result::dsl::{qz_id, result as result_table};
-    result_table
-        .filter(qz_id.eq(quiz_id))
-        .load::<QuizResult>(conn)
-        .map_err(|msg| NotFound(msg.into()))
+    stored: &[QuizResult],
+    edited: Vec<IncomingQuizResult>,
+    repo: &dyn Repository,
+) -> QueryResult<()> {
+    let kept_result_ids: Vec<i32> = edited.iter().filter_map(|res| res.id).collect();
+    for stored_result in stored {
+        if !kept_result_ids.contains(&stored_result.id) {
+            repo.delete_result(stored_result.id)?;
+        }
+    }
+
+    for (i, res) in edited.into_iter().enumerate() {
+        match res.id {
+            Some(result_id) => {
+                let stored_result = stored
+                    .iter()
+                    .find(|stored_result| stored_result.id == result_id)
+                    .ok_or(diesel::result::Error::NotFound)?;
+                repo.update_result(
+                    result_id,
+                    i as i32,
+                    &res.header,
+                    &res.description,
+                    res.min_percent,
+                    res.max_percent,
+                )?;
+                if res.media_id != stored_result.media_id {
+                    repo.set_result_media(result_id, res.media_id)?;
+                }
+            }
+            None => {
+                repo.insert_results(vec![NewQuizResult {
+                    num: i as i32,
+                    header: res.header,
+                    description: res.description,
+                    qz_id: quiz_id,
+                    min_percent: res.min_percent,
+                    max_percent: res.max_percent,
+                    media_id: res.media_id,
+                }])?;
+            }
+        }
+    }
+    Ok(())
 }
@@ -1,158 +1,316 @@
-use diesel::{self, prelude::*}; //common diesel things
-
-use rocket::request::Outcome;
-use rocket::response::status::{Conflict, NotFound}; // Response types
+use rocket::http::Status;
+use rocket::request::LenientForm;
+use rocket::State;
 use rocket_contrib::json::Json; // Easy Json coercion
 
 use crate::models::quiz_models::*; // Models needed for pulling or pushing data
-use crate::utils::sql_utils::last_insert_id; //utility for getting around mysql being bad
-use crate::DbConn; // The state managed DB connection
+use crate::models::tag_models::TagCount;
+use crate::repository::{self, QuizFacets, QuizFilter, Repo}; // Storage, whichever database is behind it
 
+use super::api_error::ApiError;
+use super::api_version::{ApiVersion, VaryOnAccept};
+use super::media_functions::check_media;
+use super::pagination::*;
 use super::quiz_functions::*;
 use super::quiz_types::*;
+use super::quiz_validation::{validate_draft, validate_edit, validate_full_quiz};
+use super::search_functions::*;
+use super::search_types::{SearchResults, Suggestions};
+use super::version_functions::record_version;
+
+// The front page only has room for a few quizzes
+const INDEX_PAGE_SIZE: i64 = 6;
+
+// Front page listing, the newest few quizzes unless asked otherwise.
+#[get("/?<page..>")]
+pub fn index(page: LenientForm<PageParams>, repo: Repo) -> Result<Json<Page<Quiz>>, ApiError> {
+    let page = page.resolve(INDEX_PAGE_SIZE, SortOrder::Newest, false)?;
+    Ok(Json(list_quizzes(
+        &QuizFilter::Published,
+        &QuizFacets::default(),
+        &page,
+        &*repo,
+    )?))
+}
 
-//TODO: Think about error handling improvements
+// Every published quiz, optionally only those with a tag and/or in a category.
+#[get("/browse?<tag>&<category>&<page..>")]
+pub fn browse(
+    tag: Option<String>,
+    category: Option<String>,
+    page: LenientForm<PageParams>,
+    repo: Repo,
+) -> Result<Json<Page<Quiz>>, ApiError> {
+    let page = page.resolve(DEFAULT_PAGE_SIZE, SortOrder::Name, false)?;
+    Ok(Json(list_quizzes(
+        &QuizFilter::Published,
+        &quiz_facets(tag, category),
+        &page,
+        &*repo,
+    )?))
+}
 
-//TODO: Create an Edit route
-// Test route.
-#[get("/")]
-pub fn index(conn_ptr: DbConn) -> Result<Json<Vec<Quiz>>, RouteError> {
-    use crate::schema::quiz::dsl::quiz as quiz_table; //convenience re-exports from 'table!' macro codegen
-    let ref conn = *conn_ptr; //Pull a connection out of the connection pool
-    Ok(Json(quiz_table.limit(6).load::<Quiz>(conn)?))
+// Searches the names, descriptions, questions and results of published quizzes, forgiving typos
+// and unfinished words. Hits come best match first unless a 'sort' is given, each with its score
+// and the bits that matched highlighted. Takes the same filters as browse. The facet counts cover
+// every match rather than just the page, so a client can offer them for narrowing the search down.
+#[get("/search?<query>&<tag>&<category>&<page..>")]
+pub fn search(
+    query: String,
+    tag: Option<String>,
+    category: Option<String>,
+    page: LenientForm<PageParams>,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<SearchResults>, ApiError> {
+    let by_relevance = page.sort.is_none();
+    let page = page.resolve(DEFAULT_PAGE_SIZE, SortOrder::Name, by_relevance)?;
+    Ok(Json(search_quizzes(
+        &query,
+        &quiz_facets(tag, category),
+        by_relevance,
+        &page,
+        &index,
+        &*repo,
+    )?))
 }
 
-#[get("/browse")]
-pub fn browse(conn_ptr: DbConn) -> Result<Json<Vec<Quiz>>, RouteError> {
-    use crate::schema::quiz::dsl::{name, quiz as quiz_table};
-    let ref conn = *conn_ptr;
-    Ok(Json(quiz_table.order(name.asc()).load::<Quiz>(conn)?))
+// Suggestions for a search box as the user types, the best few quiz names, tags and authors with
+// a word starting with 'prefix'. Served from memory, so it's quick enough to call on every key.
+#[get("/search/suggest?<prefix>")]
+pub fn suggest(
+    prefix: String,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<Suggestions>, ApiError> {
+    Ok(Json(index.suggest(&prefix, &*repo)?))
 }
 
-#[get("/search?<query>")]
-pub fn search(query: String, conn_ptr: DbConn) -> Result<Json<Vec<Quiz>>, NotFound<RouteError>> {
-    let ref conn = *conn_ptr;
-    let sql_query_string = query.replace(" ", "*");
-    let sql_query_string = String::from("*") + &sql_query_string + "*";
-    diesel::sql_query(
-        "SELECT * from quiz where match(name, description) against (? in boolean mode)",
-    )
-    .bind::<diesel::sql_types::Text, _>(sql_query_string)
-    .load(conn)
-    .map_err(|e| NotFound(e.into()))
-    .map(|val| Json(val))
+// Every tag in use on a published quiz, most used first.
+#[get("/tags")]
+pub fn list_tags(repo: Repo) -> Result<Json<Vec<TagCount>>, ApiError> {
+    Ok(Json(repo.count_tags()?))
 }
 
-#[get("/quizzes?<user_id>")]
+// Everything the logged in user has written, drafts included.
+#[get("/quizzes?<page..>")]
 pub fn get_quizzes_by_user_id(
     user_id: LoggedInUserID,
-    conn_ptr: DbConn,
-) -> Result<Json<Vec<Quiz>>, NotFound<RouteError>> {
-    let ref conn = *conn_ptr;
-    use crate::schema::quiz::dsl::{quiz as quiz_table, u_id};
-    let quizzes: Vec<Quiz> = quiz_table
-        .filter(u_id.eq(user_id.0))
-        .load::<Quiz>(conn)
-        .map_err(|e| NotFound(e.into()))?;
-
-    Ok(Json(quizzes))
+    page: LenientForm<PageParams>,
+    repo: Repo,
+) -> Result<Json<Page<Quiz>>, ApiError> {
+    let page = page.resolve(DEFAULT_PAGE_SIZE, SortOrder::Newest, false)?;
+    Ok(Json(list_quizzes(
+        &QuizFilter::ByUser(user_id.0),
+        &QuizFacets::default(),
+        &page,
+        &*repo,
+    )?))
 }
 
 // This route handles retrieval of all of the constituent parts of a quiz from their
 // tables and assembles them into a large struct and sends it as JSON.
+// Clients asking for version 2 in their Accept header get the nested shape instead.
 #[get("/quiz/<quiz_id>")]
 pub fn get_full_quiz_route(
     quiz_id: i32,
-    conn_ptr: DbConn,
-) -> Result<Json<FullQuiz>, NotFound<RouteError>> {
-    get_full_quiz(quiz_id, &*conn_ptr)
+    version: ApiVersion,
+    viewer: Option<LoggedInUserID>,
+    repo: Repo,
+) -> Result<VaryOnAccept<Json<VersionedFullQuiz>>, ApiError> {
+    let mut full_quiz = get_full_quiz(quiz_id, &*repo)?;
+    ensure_quiz_visible(&full_quiz.quiz, viewer.as_ref())?;
+    hide_correct_answers(&mut full_quiz, viewer.as_ref());
+    Ok(VaryOnAccept(Json(match version {
+        ApiVersion::V1 => VersionedFullQuiz::V1(full_quiz),
+        ApiVersion::V2 => VersionedFullQuiz::V2(NestedFullQuiz::from(full_quiz)),
+    })))
+}
+
+#[get("/v2/quiz/<quiz_id>")]
+pub fn get_nested_quiz_route(
+    quiz_id: i32,
+    viewer: Option<LoggedInUserID>,
+    repo: Repo,
+) -> Result<Json<NestedFullQuiz>, ApiError> {
+    let mut full_quiz = get_full_quiz(quiz_id, &*repo)?;
+    ensure_quiz_visible(&full_quiz.quiz, viewer.as_ref())?;
+    hide_correct_answers(&mut full_quiz, viewer.as_ref());
+    Ok(Json(NestedFullQuiz::from(full_quiz)))
 }
 // This route handles adding new quizzes to the db. Takes a large amount of data in the body
 // and destructures it into its fields for insertion into their respective tables.
+// The author is whoever is logged in, not whatever the body claims. The quiz goes out straight
+// away, use create_draft to store one that isn't finished.
 #[post("/quiz", format = "json", data = "<f_quiz>")]
 pub fn insert_quiz(
     f_quiz: Json<IncomingFullQuiz>,
-    conn_ptr: DbConn,
-) -> Result<Json<i32>, Conflict<RouteError>> {
-    use crate::schema::answer::dsl::answer as answer_table;
-    use crate::schema::question::dsl::question as question_table;
-    use crate::schema::quiz::dsl::quiz as quiz_table;
-    use crate::schema::result::dsl::result as result_table;
-    let ref conn = *conn_ptr;
-
-    let IncomingFullQuiz {
-        quiz,
-        questions,
-        answers,
-        results,
-    } = f_quiz.into_inner();
-
+    user_id: LoggedInUserID,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<i32>, ApiError> {
+    validate_full_quiz(&f_quiz)?;
+    check_media(&f_quiz, &user_id, None, &*repo)?;
     // Attempts to insert and associate all the new records under a transaction, rolling back under failure
-    conn.transaction::<Json<i32>, diesel::result::Error, _>(|| {
-        diesel::insert_into(quiz_table)
-            .values(NewQuiz::from(quiz))
-            .execute(conn)?;
-        let last_qz_id: u64 = diesel::select(last_insert_id).first(conn)?;
-        let mut cur_question = 0;
-        for qs in questions {
-            let question_to_add = NewQuestion {
-                description: qs.description.clone(),
-                qz_id: last_qz_id as i32,
-            };
-            let _row_changed = diesel::insert_into(question_table)
-                .values(question_to_add)
-                .execute(conn)?;
-            let last_q_id: u64 = diesel::select(last_insert_id).first(conn)?;
-            for ans in &answers[cur_question] {
-                let answer_to_add = NewAnswer {
-                    description: ans.description.clone(),
-                    val: ans.val,
-                    q_id: last_q_id as i32,
-                };
-                let _rows_changed = diesel::insert_into(answer_table)
-                    .values(answer_to_add)
-                    .execute(conn)?;
-            }
-            cur_question += 1;
-        }
-
-        let new_results: Vec<NewQuizResult> = results
-            .iter()
-            .enumerate()
-            .map(|(i, q)| NewQuizResult {
-                num: i as i32,
-                header: q.header.clone(),
-                description: q.description.clone(),
-                qz_id: last_qz_id as i32,
-            })
-            .collect();
-        diesel::insert_into(result_table)
-            .values(new_results)
-            .execute(conn)?;
-
-        Ok(Json(last_qz_id as i32))
-    })
-    .map_err(|msg| {
-        Conflict(Some(RouteError {
-            error: msg.to_string(),
-        }))
-    })
-}
-#[allow(unused_variables)]
-#[delete("/quiz?<quiz_id>&<user_id>")]
-pub fn delete(
+    let quiz_id = repository::transaction(&*repo, || {
+        let quiz_id = insert_full_quiz(
+            f_quiz.into_inner(),
+            user_id.0,
+            QuizStatus::Published,
+            &*repo,
+        )?;
+        record_version(quiz_id, user_id.0, None, &*repo)?;
+        Ok(quiz_id)
+    })?;
+    index_quiz(quiz_id, &index, &*repo);
+    Ok(Json(quiz_id))
+}
+// This route handles editing an existing quiz in place, so its id (and every link to it) survives.
+// Takes the same shape as insert_quiz, where questions, answers and results that already exist
+// carry their ids. Anything stored but left out of the body is deleted.
+#[put("/quiz/<quiz_id>", format = "json", data = "<f_quiz>")]
+pub fn edit_quiz(
+    quiz_id: i32,
+    f_quiz: Json<IncomingFullQuiz>,
+    user_id: LoggedInUserID,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<i32>, ApiError> {
+    ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    validate_full_quiz(&f_quiz)?;
+    let current = get_full_quiz(quiz_id, &*repo)?;
+    validate_edit(&f_quiz, &current)?;
+    check_media(&f_quiz, &user_id, Some(&current), &*repo)?;
+    let edited = f_quiz.into_inner();
+
+    // Diffs and applies the edit under a transaction, rolling back under failure. Every save that
+    // changes something becomes the quiz's next version.
+    repository::transaction(&*repo, || {
+        edit_full_quiz(&current, edited, &*repo)?;
+        record_version(quiz_id, user_id.0, None, &*repo)
+    })?;
+    index_quiz(quiz_id, &index, &*repo);
+    Ok(Json(quiz_id))
+}
+
+// Stores an unfinished quiz that only its author can see. Only checked for what the db can't
+// hold, e.g. text that's too long, so a half written quiz can be saved as often as the client likes.
+#[post("/quiz/draft", format = "json", data = "<f_quiz>")]
+pub fn create_draft(
+    f_quiz: Json<IncomingFullQuiz>,
+    user_id: LoggedInUserID,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<i32>, ApiError> {
+    validate_draft(&f_quiz)?;
+    check_media(&f_quiz, &user_id, None, &*repo)?;
+    let quiz_id = repository::transaction(&*repo, || {
+        let quiz_id = insert_full_quiz(f_quiz.into_inner(), user_id.0, QuizStatus::Draft, &*repo)?;
+        record_version(quiz_id, user_id.0, None, &*repo)?;
+        Ok(quiz_id)
+    })?;
+    index_quiz(quiz_id, &index, &*repo);
+    Ok(Json(quiz_id))
+}
+
+// Autosave for a draft, taking the same body as edit_quiz. Refuses quizzes that are already out,
+// those have to go through edit_quiz and its full validation.
+#[put("/quiz/<quiz_id>/draft", format = "json", data = "<f_quiz>")]
+pub fn save_draft(
     quiz_id: i32,
+    f_quiz: Json<IncomingFullQuiz>,
     user_id: LoggedInUserID,
-    conn_ptr: DbConn,
-) -> Result<(), Outcome<(), String>> {
-    use crate::schema::quiz::dsl::{id, quiz as quiz_table};
-    let ref conn = *conn_ptr;
-    let res = diesel::delete(quiz_table.filter(id.eq(quiz_id))).execute(conn);
-    match res {
-        Ok(_) => Ok(()),
-        Err(msg) => Err(Outcome::Failure((
-            rocket::http::Status::InternalServerError,
-            msg.to_string(),
-        ))),
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<i32>, ApiError> {
+    let quiz = ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    if quiz.status != QuizStatus::Draft {
+        return Err(ApiError::new(
+            Status::Conflict,
+            "not_a_draft",
+            "Only drafts can be autosaved, edit the quiz instead",
+        ));
     }
+    validate_draft(&f_quiz)?;
+    let current = get_full_quiz(quiz_id, &*repo)?;
+    validate_edit(&f_quiz, &current)?;
+    check_media(&f_quiz, &user_id, Some(&current), &*repo)?;
+    let edited = f_quiz.into_inner();
+    repository::transaction(&*repo, || {
+        edit_full_quiz(&current, edited, &*repo)?;
+        record_version(quiz_id, user_id.0, None, &*repo)
+    })?;
+    index_quiz(quiz_id, &index, &*repo);
+    Ok(Json(quiz_id))
+}
+
+// Moves the questions of a quiz around, or the answers to one of its questions, all at once.
+// Hands back the quiz in its new order.
+#[patch("/quiz/<quiz_id>/order", format = "json", data = "<change>")]
+pub fn reorder(
+    quiz_id: i32,
+    change: Json<OrderChange>,
+    user_id: LoggedInUserID,
+    repo: Repo,
+) -> Result<Json<FullQuiz>, ApiError> {
+    ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    let current = get_full_quiz(quiz_id, &*repo)?;
+    check_order(&current, &change)?;
+
+    repository::transaction(&*repo, || {
+        apply_order(&change, &*repo)?;
+        record_version(quiz_id, user_id.0, None, &*repo)
+    })?;
+    Ok(Json(get_full_quiz(quiz_id, &*repo)?))
+}
+
+// Puts a draft (or an unlisted or archived quiz) out in the listings, once it passes the same
+// validation insert_quiz makes.
+#[post("/quiz/<quiz_id>/publish")]
+pub fn publish(
+    quiz_id: i32,
+    user_id: LoggedInUserID,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<Quiz>, ApiError> {
+    ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    let quiz = change_quiz_status(quiz_id, QuizStatus::Published, &*repo)?;
+    index_quiz(quiz_id, &index, &*repo);
+    Ok(Json(quiz))
+}
+
+// Unlists, archives or republishes a quiz. Takes '{"status": "unlisted"}' and the like.
+#[put("/quiz/<quiz_id>/status", format = "json", data = "<change>")]
+pub fn set_status(
+    quiz_id: i32,
+    change: Json<StatusChange>,
+    user_id: LoggedInUserID,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<Quiz>, ApiError> {
+    ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    let quiz = change_quiz_status(quiz_id, change.status, &*repo)?;
+    index_quiz(quiz_id, &index, &*repo);
+    Ok(Json(quiz))
+}
+
+// Dry run of the checks insert_quiz and edit_quiz make, so a client can flag problems before
+// submitting. Answers 204 for a valid quiz, otherwise the same 422 those routes would.
+#[post("/quiz/validate", format = "json", data = "<f_quiz>")]
+pub fn validate_quiz(f_quiz: Json<IncomingFullQuiz>) -> Result<Status, ApiError> {
+    validate_full_quiz(&f_quiz)?;
+    Ok(Status::NoContent)
+}
+
+#[delete("/quiz?<quiz_id>")]
+pub fn delete(
+    quiz_id: i32,
+    user_id: LoggedInUserID,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<(), ApiError> {
+    ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    repo.delete_quiz(quiz_id)?;
+    unindex_quiz(quiz_id, &index);
+    Ok(())
 }
@@ -1,9 +1,11 @@
-use super::auth_functions::logged_in;
+use super::auth_types::CurrentSession;
 use crate::models::quiz_models::*;
-use rocket::http::RawStr;
-use rocket::request::{FromFormValue, FromRequest, Outcome, Request};
-// Aggregate struct to represent an entire quiz coming out of the db.
-#[derive(Serialize, Debug)]
+use rocket::request::{FromRequest, Outcome, Request};
+use std::collections::BTreeMap;
+
+// Aggregate struct to represent an entire quiz coming out of the db. Also how versions are
+// snapshotted.
+#[derive(Serialize, Deserialize, Debug)]
 pub struct FullQuiz {
     pub quiz: Quiz,
     pub questions: Vec<Question>,
@@ -11,6 +13,64 @@ pub struct FullQuiz {
     pub results: Vec<QuizResult>,
 }
 
+// The v2 shape of a quiz. Each question carries its own answers instead of relying on two lists
+// lining up by position, and results are keyed by the 'num' that answers' 'val' points at.
+#[derive(Serialize, Debug)]
+pub struct NestedFullQuiz {
+    pub quiz: Quiz,
+    pub questions: Vec<NestedQuestion>,
+    pub results: BTreeMap<i32, QuizResult>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NestedQuestion {
+    pub id: i32,
+    pub description: String,
+    pub qz_id: i32,
+    pub position: i32,
+    pub kind: QuestionKind,
+    pub config: QuestionConfig,
+    pub media_id: Option<i32>,
+    pub answers: Vec<Answer>,
+}
+
+impl From<FullQuiz> for NestedFullQuiz {
+    fn from(full_quiz: FullQuiz) -> Self {
+        let FullQuiz {
+            quiz,
+            questions,
+            answers,
+            results,
+        } = full_quiz;
+        Self {
+            quiz,
+            questions: questions
+                .into_iter()
+                .zip(answers)
+                .map(|(qs, answers)| NestedQuestion {
+                    id: qs.id,
+                    description: qs.description,
+                    qz_id: qs.qz_id,
+                    position: qs.position,
+                    kind: qs.kind,
+                    config: qs.config,
+                    media_id: qs.media_id,
+                    answers,
+                })
+                .collect(),
+            results: results.into_iter().map(|res| (res.num, res)).collect(),
+        }
+    }
+}
+
+// Either shape of a quiz, serialized as just the shape itself.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum VersionedFullQuiz {
+    V1(FullQuiz),
+    V2(NestedFullQuiz),
+}
+
 // Aggregate struct to represent an entire incoming quiz to be processed before going into the db.
 #[derive(Deserialize, Debug)]
 pub struct IncomingFullQuiz {
@@ -20,63 +80,86 @@ pub struct IncomingFullQuiz {
     pub results: Vec<IncomingQuizResult>,
 }
 
-#[derive(Debug)]
-pub struct RouteError {
-    pub error: String,
-}
-
-impl RouteError {
-    pub fn new(err: &str) -> Self {
-        Self {
-            error: String::from(err),
+// A stored quiz in the shape it's posted in, ids included, so it can go through the same
+// validation as an incoming one.
+impl From<&FullQuiz> for IncomingFullQuiz {
+    fn from(full_quiz: &FullQuiz) -> Self {
+        IncomingFullQuiz {
+            quiz: IncomingQuiz {
+                name: full_quiz.quiz.name.clone(),
+                description: full_quiz.quiz.description.clone(),
+                mode: full_quiz.quiz.mode,
+                media_id: full_quiz.quiz.media_id,
+                category: full_quiz.quiz.category.clone(),
+                tags: full_quiz.quiz.tags.clone(),
+            },
+            questions: full_quiz
+                .questions
+                .iter()
+                .map(|qs| IncomingQuestion {
+                    id: Some(qs.id),
+                    description: qs.description.clone(),
+                    kind: qs.kind,
+                    config: qs.config.clone(),
+                    media_id: qs.media_id,
+                })
+                .collect(),
+            answers: full_quiz
+                .answers
+                .iter()
+                .map(|question_answers| {
+                    question_answers
+                        .iter()
+                        .map(|ans| IncomingAnswer {
+                            id: Some(ans.id),
+                            description: ans.description.clone(),
+                            val: ans.val,
+                            is_correct: ans.is_correct,
+                            weights: ans.weights.clone(),
+                            next_question: ans.next_question,
+                            media_id: ans.media_id,
+                        })
+                        .collect()
+                })
+                .collect(),
+            results: full_quiz
+                .results
+                .iter()
+                .map(|res| IncomingQuizResult {
+                    id: Some(res.id),
+                    header: res.header.clone(),
+                    description: res.description.clone(),
+                    min_percent: res.min_percent,
+                    max_percent: res.max_percent,
+                    media_id: res.media_id,
+                })
+                .collect(),
         }
     }
 }
 
-impl From<diesel::result::Error> for RouteError {
-    fn from(err: diesel::result::Error) -> Self {
-        RouteError {
-            error: err.to_string(),
-        }
-    }
+#[derive(Deserialize, Debug)]
+pub struct StatusChange {
+    pub status: QuizStatus,
 }
 
-impl<'r> rocket::response::Responder<'r> for RouteError {
-    fn respond_to(self, _: &rocket::request::Request) -> rocket::response::Result<'r> {
-        rocket::Response::build()
-            .header(rocket::http::ContentType::Binary)
-            .sized_body(std::io::Cursor::new(self.error))
-            .ok()
-    }
+// A new order for the questions of a quiz, or for the answers to one of its questions, as a
+// list of their ids.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum OrderChange {
+    Answers { question_id: i32, answers: Vec<i32> },
+    Questions { questions: Vec<i32> },
 }
 
+// The id of the user the request's session belongs to.
 pub struct LoggedInUserID(pub i32);
 
 impl<'a, 'r> FromRequest<'a, 'r> for LoggedInUserID {
     type Error = ();
     fn from_request(request: &'a Request<'r>) -> Outcome<LoggedInUserID, ()> {
-        let id_opt = request.headers().get_one("x-api-key");
-        match id_opt {
-            Some(id_str) => {
-                let uid = id_str.parse().unwrap();
-                if logged_in(uid, &mut request.cookies()) {
-                    Outcome::Success(LoggedInUserID(uid))
-                } else {
-                    Outcome::Failure((rocket::http::Status::Unauthorized, ()))
-                }
-            }
-            None => Outcome::Failure((rocket::http::Status::NotFound, ())),
-        }
-    }
-}
-
-impl<'v> FromFormValue<'v> for LoggedInUserID {
-    type Error = ();
-
-    fn from_form_value(form_value: &'v RawStr) -> Result<LoggedInUserID, ()> {
-        match form_value.parse::<i32>() {
-            Ok(id) => Ok(LoggedInUserID(id)),
-            _ => Err(()),
-        }
+        request
+            .guard::<CurrentSession>()
+            .map(|CurrentSession(session)| LoggedInUserID(session.u_id))
     }
 }
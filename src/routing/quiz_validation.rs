@@ -0,0 +1,636 @@
+use super::api_error::ApiError;
+use super::quiz_functions::normalize_label;
+use super::quiz_types::{FullQuiz, IncomingFullQuiz};
+use crate::models::quiz_models::{
+    IncomingAnswer, IncomingQuestion, NextQuestion, QuestionKind, QuizMode,
+};
+use std::fmt;
+
+// Column sizes from the migrations, MySQL counts VARCHAR lengths in characters
+const MAX_TEXT_LEN: usize = 240;
+const MAX_HEADER_LEN: usize = 64;
+const MAX_LABEL_LEN: usize = 32; // tags and categories
+const MAX_TAGS: usize = 10;
+pub const MAX_FREE_TEXT_LEN: usize = 1000;
+const MIN_QUESTIONS: usize = 1;
+const MIN_ANSWERS: usize = 2;
+const MIN_RESULTS: usize = 2;
+const MAX_PERCENT: i32 = 100;
+const MAX_WEIGHT: i32 = 100;
+
+// A single problem with an incoming quiz. 'field' is a path into the body, e.g.
+// 'questions[2].description' or 'answers[0][1].val'.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Violation {
+    pub field: String,
+    pub message: String,
+}
+
+impl Violation {
+    fn new(field: String, message: &str) -> Self {
+        Self {
+            field,
+            message: String::from(message),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+// Everything wrong with an incoming quiz, so a client can fix it all in one go.
+#[derive(Debug)]
+pub struct ValidationErrors(pub Vec<Violation>);
+
+impl From<ValidationErrors> for ApiError {
+    fn from(ValidationErrors(violations): ValidationErrors) -> Self {
+        let message = violations
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect::<Vec<String>>()
+            .join("; ");
+        ApiError::unprocessable("invalid_quiz", &message).with_details(serde_json::json!({
+            "violations": violations,
+        }))
+    }
+}
+
+// Checks a quiz before any of it touches the db. Everything is checked rather than stopping at
+// the first problem, and nothing is indexed unchecked, so any body that deserializes is safe here.
+pub fn validate_full_quiz(f_quiz: &IncomingFullQuiz) -> Result<(), ValidationErrors> {
+    check_quiz(f_quiz, true)
+}
+
+// The checks for an autosaved draft, which only has to fit in the db. Whether it's complete is
+// left for validate_full_quiz to decide when it's published.
+pub fn validate_draft(f_quiz: &IncomingFullQuiz) -> Result<(), ValidationErrors> {
+    check_quiz(f_quiz, false)
+}
+
+// The ids in an edit of 'current', on top of the checks for its shape. Each one has to be one of
+// the quiz's own and can only be listed once, an answer's under the question it's stored under.
+// The answers under a question whose id is wrong aren't checked, they'd all be wrong with it.
+pub fn validate_edit(
+    f_quiz: &IncomingFullQuiz,
+    current: &FullQuiz,
+) -> Result<(), ValidationErrors> {
+    let mut violations = Vec::new();
+
+    let stored_questions: Vec<i32> = current.questions.iter().map(|qs| qs.id).collect();
+    let mut seen_questions = Vec::new();
+    for (i, qs) in f_quiz.questions.iter().enumerate() {
+        if !check_id(
+            &mut violations,
+            format!("questions[{}].id", i),
+            qs.id,
+            &stored_questions,
+            &mut seen_questions,
+            "quiz",
+        ) {
+            continue;
+        }
+        let stored_answers: Vec<i32> = stored_questions
+            .iter()
+            .position(|id| Some(*id) == qs.id)
+            .map_or(Vec::new(), |stored| {
+                current.answers[stored].iter().map(|ans| ans.id).collect()
+            });
+        let mut seen_answers = Vec::new();
+        for (j, ans) in f_quiz.answers.get(i).into_iter().flatten().enumerate() {
+            check_id(
+                &mut violations,
+                format!("answers[{}][{}].id", i, j),
+                ans.id,
+                &stored_answers,
+                &mut seen_answers,
+                "question",
+            );
+        }
+    }
+
+    let stored_results: Vec<i32> = current.results.iter().map(|res| res.id).collect();
+    let mut seen_results = Vec::new();
+    for (i, res) in f_quiz.results.iter().enumerate() {
+        check_id(
+            &mut violations,
+            format!("results[{}].id", i),
+            res.id,
+            &stored_results,
+            &mut seen_results,
+            "quiz",
+        );
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors(violations))
+    }
+}
+
+fn check_quiz(f_quiz: &IncomingFullQuiz, complete: bool) -> Result<(), ValidationErrors> {
+    let mut violations = Vec::new();
+    let personality = f_quiz.quiz.mode == QuizMode::Personality;
+
+    check_text(
+        &mut violations,
+        "quiz.name",
+        &f_quiz.quiz.name,
+        MAX_TEXT_LEN,
+        complete,
+    );
+    check_text(
+        &mut violations,
+        "quiz.description",
+        &f_quiz.quiz.description,
+        MAX_TEXT_LEN,
+        false,
+    );
+    // Checked the way they'll be stored, repeats and all
+    if let Some(category) = &f_quiz.quiz.category {
+        check_text(
+            &mut violations,
+            "quiz.category",
+            &normalize_label(category),
+            MAX_LABEL_LEN,
+            false,
+        );
+    }
+    let mut tags: Vec<String> = f_quiz
+        .quiz
+        .tags
+        .iter()
+        .map(|tag| normalize_label(tag))
+        .collect();
+    for (i, tag) in tags.iter().enumerate() {
+        check_text(
+            &mut violations,
+            &format!("quiz.tags[{}]", i),
+            tag,
+            MAX_LABEL_LEN,
+            false,
+        );
+    }
+    tags.sort();
+    tags.dedup();
+    if tags.len() > MAX_TAGS {
+        violations.push(Violation::new(
+            String::from("quiz.tags"),
+            &format!("too many, at most {}", MAX_TAGS),
+        ));
+    }
+
+    if complete && f_quiz.questions.len() < MIN_QUESTIONS {
+        violations.push(Violation::new(
+            String::from("questions"),
+            &format!("needs at least {} question", MIN_QUESTIONS),
+        ));
+    }
+    for (i, qs) in f_quiz.questions.iter().enumerate() {
+        check_text(
+            &mut violations,
+            &format!("questions[{}].description", i),
+            &qs.description,
+            MAX_TEXT_LEN,
+            complete,
+        );
+        let answers = f_quiz.answers.get(i).map_or(0, Vec::len);
+        check_kind(&mut violations, i, qs, answers, complete);
+    }
+
+    if f_quiz.answers.len() != f_quiz.questions.len() {
+        violations.push(Violation::new(
+            String::from("answers"),
+            &format!(
+                "expected {} lists of answers, one per question, got {}",
+                f_quiz.questions.len(),
+                f_quiz.answers.len()
+            ),
+        ));
+    }
+    for (i, question_answers) in f_quiz.answers.iter().enumerate() {
+        let kind = f_quiz.questions.get(i).map(|qs| qs.kind);
+        let takes_answers = kind != Some(QuestionKind::FreeText);
+        if complete && takes_answers && question_answers.len() < MIN_ANSWERS {
+            violations.push(Violation::new(
+                format!("answers[{}]", i),
+                &format!("needs at least {} answers", MIN_ANSWERS),
+            ));
+        }
+        let mut seen: Vec<String> = Vec::new();
+        for (j, ans) in question_answers.iter().enumerate() {
+            let path = format!("answers[{}][{}]", i, j);
+            check_text(
+                &mut violations,
+                &format!("{}.description", path),
+                &ans.description,
+                MAX_TEXT_LEN,
+                complete,
+            );
+            // Same wording twice under one question can't be told apart by a taker
+            let normalized = ans.description.trim().to_lowercase();
+            if complete && !normalized.is_empty() && seen.contains(&normalized) {
+                violations.push(Violation::new(
+                    format!("{}.description", path),
+                    "duplicate answer",
+                ));
+            }
+            seen.push(normalized);
+            // Results are numbered by position, and an answer's val is the result it counts towards
+            let val_matches = ans.val >= 0 && (ans.val as usize) < f_quiz.results.len();
+            if complete && personality && !val_matches {
+                violations.push(Violation::new(
+                    format!("{}.val", path),
+                    "does not match any result",
+                ));
+            }
+            if complete && personality && ans.is_correct {
+                violations.push(Violation::new(
+                    format!("{}.is_correct", path),
+                    "only used in trivia quizzes",
+                ));
+            }
+            if complete && !personality && !ans.weights.is_empty() {
+                violations.push(Violation::new(
+                    format!("{}.weights", path),
+                    "only used in personality quizzes",
+                ));
+            }
+            // Weights are keyed by result position, the same as val
+            if complete && personality {
+                check_weights(&mut violations, &path, ans, f_quiz.results.len());
+            }
+            if complete {
+                check_next_question(&mut violations, &path, ans, kind, f_quiz.questions.len());
+            }
+        }
+    }
+    if complete && !f_quiz.questions.is_empty() {
+        let mut done = vec![false; f_quiz.questions.len()];
+        check_flow(&mut violations, f_quiz, 0, &mut done, &mut Vec::new());
+    }
+
+    if complete && f_quiz.results.len() < MIN_RESULTS {
+        violations.push(Violation::new(
+            String::from("results"),
+            &format!("needs at least {} results", MIN_RESULTS),
+        ));
+    }
+    for (i, res) in f_quiz.results.iter().enumerate() {
+        check_text(
+            &mut violations,
+            &format!("results[{}].header", i),
+            &res.header,
+            MAX_HEADER_LEN,
+            complete,
+        );
+        check_text(
+            &mut violations,
+            &format!("results[{}].description", i),
+            &res.description,
+            MAX_TEXT_LEN,
+            false,
+        );
+        for (percent, name) in &[
+            (res.min_percent, "min_percent"),
+            (res.max_percent, "max_percent"),
+        ] {
+            if complete && personality && percent.is_some() {
+                violations.push(Violation::new(
+                    format!("results[{}].{}", i, name),
+                    "only used in trivia quizzes",
+                ));
+            }
+        }
+    }
+    if complete && !personality {
+        check_trivia(&mut violations, f_quiz);
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors(violations))
+    }
+}
+
+// What each kind of question asks of its config and answers. Labels have to fit even in a draft,
+// the rest only matters once the quiz goes out.
+fn check_kind(
+    violations: &mut Vec<Violation>,
+    i: usize,
+    qs: &IncomingQuestion,
+    answers: usize,
+    complete: bool,
+) {
+    let field = |name: &str| format!("questions[{}].config.{}", i, name);
+    let config = &qs.config;
+    for (label, name) in &[
+        (&config.min_label, "min_label"),
+        (&config.max_label, "max_label"),
+    ] {
+        if let Some(label) = label {
+            check_text(violations, &field(name), label, MAX_HEADER_LEN, false);
+        }
+    }
+    if !complete {
+        return;
+    }
+
+    for name in config.fields_set() {
+        if !qs.kind.config_fields().contains(&name) {
+            violations.push(Violation::new(
+                field(name),
+                &format!("not used by {} questions", qs.kind.as_str()),
+            ));
+        }
+    }
+    match qs.kind {
+        QuestionKind::MultiSelect => {
+            let (min, max) = config.pick_range(answers);
+            if min < 1 {
+                violations.push(Violation::new(field("min_picks"), "has to be at least 1"));
+            } else if max < min {
+                violations.push(Violation::new(
+                    field("max_picks"),
+                    "can't be less than min_picks",
+                ));
+            } else if max > answers {
+                violations.push(Violation::new(
+                    field("max_picks"),
+                    "more than there are answers",
+                ));
+            }
+        }
+        QuestionKind::Scale => match (config.min, config.max) {
+            (Some(min), Some(max)) => {
+                // Every answer is a band of the scale, and a band needs at least one number
+                let width = i64::from(max) - i64::from(min) + 1;
+                if min >= max {
+                    violations.push(Violation::new(field("max"), "has to be more than min"));
+                } else if width < answers as i64 {
+                    violations.push(Violation::new(
+                        field("max"),
+                        "too narrow, needs at least one number per answer",
+                    ));
+                }
+            }
+            (min, max) => {
+                for (value, name) in &[(min, "min"), (max, "max")] {
+                    if value.is_none() {
+                        violations.push(Violation::new(field(name), "required"));
+                    }
+                }
+            }
+        },
+        QuestionKind::FreeText => {
+            if answers > 0 {
+                violations.push(Violation::new(
+                    format!("answers[{}]", i),
+                    "free_text questions don't take answers",
+                ));
+            }
+            if let Some(max_length) = config.max_length {
+                if max_length < 1 || max_length as usize > MAX_FREE_TEXT_LEN {
+                    violations.push(Violation::new(
+                        field("max_length"),
+                        &format!("has to be between 1 and {}", MAX_FREE_TEXT_LEN),
+                    ));
+                }
+            }
+        }
+        QuestionKind::SingleChoice | QuestionKind::Ranking => {}
+    }
+}
+
+// A trivia quiz is marked rather than added up. Every question needs its correct answers, and
+// the results' bands of percentages have to cover every possible score exactly once.
+fn check_trivia(violations: &mut Vec<Violation>, f_quiz: &IncomingFullQuiz) {
+    for (i, (qs, question_answers)) in f_quiz.questions.iter().zip(&f_quiz.answers).enumerate() {
+        let correct = question_answers.iter().filter(|ans| ans.is_correct).count();
+        let path = format!("answers[{}]", i);
+        match qs.kind {
+            QuestionKind::SingleChoice if correct != 1 => {
+                violations.push(Violation::new(path, "needs exactly one correct answer"));
+            }
+            QuestionKind::MultiSelect => {
+                let (min, max) = qs.config.pick_range(question_answers.len());
+                if correct == 0 {
+                    violations.push(Violation::new(path, "needs at least one correct answer"));
+                } else if correct < min || correct > max {
+                    violations.push(Violation::new(
+                        path,
+                        "the correct answers have to be a number that can be picked",
+                    ));
+                }
+            }
+            QuestionKind::Scale | QuestionKind::Ranking | QuestionKind::FreeText => {
+                violations.push(Violation::new(
+                    format!("questions[{}].kind", i),
+                    "not available in trivia quizzes",
+                ));
+            }
+            QuestionKind::SingleChoice => {}
+        }
+    }
+
+    let mut bands = Vec::new();
+    for (i, res) in f_quiz.results.iter().enumerate() {
+        let field = |name: &str| format!("results[{}].{}", i, name);
+        match (res.min_percent, res.max_percent) {
+            (Some(min), Some(max)) => {
+                if !(0..=MAX_PERCENT).contains(&min) {
+                    violations.push(Violation::new(
+                        field("min_percent"),
+                        &format!("has to be between 0 and {}", MAX_PERCENT),
+                    ));
+                } else if max < min || max > MAX_PERCENT {
+                    violations.push(Violation::new(
+                        field("max_percent"),
+                        &format!("has to be between min_percent and {}", MAX_PERCENT),
+                    ));
+                } else {
+                    bands.push((min, max));
+                }
+            }
+            (min, max) => {
+                for (percent, name) in &[(min, "min_percent"), (max, "max_percent")] {
+                    if percent.is_none() {
+                        violations.push(Violation::new(field(name), "required"));
+                    }
+                }
+            }
+        }
+    }
+    // Only worth looking at how they fit together once each of them is fine
+    if bands.len() == f_quiz.results.len() && !bands.is_empty() {
+        bands.sort();
+        // Each band has to start right where the one before it ended
+        let mut next = Some(0);
+        for (min, max) in bands {
+            next = match next {
+                Some(start) if start == min => Some(max + 1),
+                _ => None,
+            };
+        }
+        if next != Some(MAX_PERCENT + 1) {
+            violations.push(Violation::new(
+                String::from("results"),
+                &format!(
+                    "the bands have to cover 0 to {} percent without gaps or overlaps",
+                    MAX_PERCENT
+                ),
+            ));
+        }
+    }
+}
+
+fn check_weights(
+    violations: &mut Vec<Violation>,
+    path: &str,
+    ans: &IncomingAnswer,
+    results: usize,
+) {
+    for (num, weight) in &ans.weights {
+        let field = format!("{}.weights.{}", path, num);
+        if *num < 0 || *num as usize >= results {
+            violations.push(Violation::new(field, "does not match any result"));
+        } else if !(1..=MAX_WEIGHT).contains(weight) {
+            violations.push(Violation::new(
+                field,
+                &format!("has to be between 1 and {}", MAX_WEIGHT),
+            ));
+        }
+    }
+}
+
+// Questions are numbered by position for this too, and a question can't lead back to itself
+fn check_next_question(
+    violations: &mut Vec<Violation>,
+    path: &str,
+    ans: &IncomingAnswer,
+    kind: Option<QuestionKind>,
+    questions: usize,
+) {
+    let field = format!("{}.next_question", path);
+    match ans.next_question {
+        Some(_) if kind != Some(QuestionKind::SingleChoice) => {
+            violations.push(Violation::new(
+                field,
+                "only single_choice answers can lead elsewhere",
+            ));
+        }
+        Some(NextQuestion::Question(to)) if to < 0 || to as usize >= questions => {
+            violations.push(Violation::new(field, "does not match any question"));
+        }
+        _ => {}
+    }
+}
+
+// The ways on from question 'at', as the answer that leads there (None for every answer that
+// just goes on to the next question) along with its rule. Only single choice answers branch.
+fn exits(f_quiz: &IncomingFullQuiz, at: usize) -> Vec<(Option<usize>, Option<NextQuestion>)> {
+    let branches = f_quiz.questions[at].kind == QuestionKind::SingleChoice;
+    let answers = f_quiz.answers.get(at).map_or(&[][..], Vec::as_slice);
+    let mut exits: Vec<(Option<usize>, Option<NextQuestion>)> = answers
+        .iter()
+        .enumerate()
+        .filter(|(_, ans)| branches && ans.next_question.is_some())
+        .map(|(j, ans)| (Some(j), ans.next_question))
+        .collect();
+    if exits.is_empty() || exits.len() < answers.len() {
+        exits.push((None, None));
+    }
+    exits
+}
+
+// Follows every way through the quiz from question 'at', flagging the rules that lead back round
+// to a question already on the way. Every answer leads to a question that's there or the end, so
+// a quiz without loops always comes to an end. 'taken' is the way here from the first question,
+// as the question, answer and where it led for each step, 'done' the questions every way on from
+// has been followed.
+fn check_flow(
+    violations: &mut Vec<Violation>,
+    f_quiz: &IncomingFullQuiz,
+    at: usize,
+    done: &mut [bool],
+    taken: &mut Vec<(usize, Option<usize>, usize)>,
+) {
+    for (answer, rule) in exits(f_quiz, at) {
+        let next = match NextQuestion::follow(rule, at, f_quiz.questions.len()) {
+            Some(next) => next,
+            None => continue,
+        };
+        taken.push((at, answer, next));
+        if let Some(start) = taken.iter().position(|(from, _, _)| *from == next) {
+            // Going on to the next question only ever moves forward, so any loop has at least one
+            // rule jumping back in it
+            for (from, answer, to) in &taken[start..] {
+                let j = match answer {
+                    Some(j) if to <= from => j,
+                    _ => continue,
+                };
+                let violation = Violation::new(
+                    format!("answers[{}][{}].next_question", from, j),
+                    "leads back round to a question already asked, the quiz would never end",
+                );
+                if !violations.contains(&violation) {
+                    violations.push(violation);
+                }
+            }
+        } else if !done[next] {
+            check_flow(violations, f_quiz, next, done, taken);
+        }
+        taken.pop();
+    }
+    done[at] = true;
+}
+
+// Whether an id in an edit is fine, i.e. one of the 'stored' ids of its 'owner' that isn't in
+// 'seen' yet. Records without an id are new, so they always are.
+fn check_id(
+    violations: &mut Vec<Violation>,
+    field: String,
+    id: Option<i32>,
+    stored: &[i32],
+    seen: &mut Vec<i32>,
+    owner: &str,
+) -> bool {
+    let id = match id {
+        Some(id) => id,
+        None => return true,
+    };
+    if !stored.contains(&id) {
+        violations.push(Violation::new(
+            field,
+            &format!("not part of this {}", owner),
+        ));
+        false
+    } else if seen.contains(&id) {
+        violations.push(Violation::new(field, "listed twice"));
+        false
+    } else {
+        seen.push(id);
+        true
+    }
+}
+
+fn check_text(
+    violations: &mut Vec<Violation>,
+    field: &str,
+    value: &str,
+    max_len: usize,
+    required: bool,
+) {
+    if required && value.trim().is_empty() {
+        violations.push(Violation::new(String::from(field), "required"));
+    } else if value.chars().count() > max_len {
+        violations.push(Violation::new(
+            String::from(field),
+            &format!("too long, at most {} characters", max_len),
+        ));
+    }
+}
@@ -0,0 +1,503 @@
+use super::api_error::ApiError;
+use super::pagination::*;
+use super::quiz_functions::{attach_tags, get_full_quiz};
+use super::quiz_types::FullQuiz;
+use super::search_types::*;
+use super::suggest_functions::*;
+use crate::models::quiz_models::Quiz;
+use crate::repository::{QuizFacets, QuizFilter, Repository};
+use diesel::QueryResult;
+use rocket::fairing::AdHoc;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, RwLock};
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{BooleanQuery, BoostQuery, FuzzyTermQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value, INDEXED, STORED, TEXT};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+// A search never looks past this many hits, the filters and paging work through those
+pub const MAX_HITS: usize = 500;
+// Only the first few words of a query count
+const MAX_QUERY_WORDS: usize = 8;
+const SNIPPET_LENGTH: usize = 120;
+const DEFAULT_INDEX_DIR: &str = "search_index";
+// What each tantivy writer thread gets to buffer documents in, the least tantivy allows
+const WRITER_MEMORY: usize = 15_000_000;
+
+// How much a match in each field counts for. A word in the name says more about a quiz than one
+// in a question does.
+const NAME_WEIGHT: f32 = 3.0;
+const DESCRIPTION_WEIGHT: f32 = 1.5;
+const QUESTIONS_WEIGHT: f32 = 1.0;
+const RESULTS_WEIGHT: f32 = 1.0;
+// A misspelled or unfinished word counts for this much of one spelled out in full
+const CLOSE_MATCH_WEIGHT: f32 = 0.2;
+
+// The full text index behind search, covering each quiz's name, description, questions and
+// results. It holds every quiz whatever its status, the repository decides which hits can be
+// shown. The database has the final say, the index is kept in step with it as quizzes are
+// stored and can always be rebuilt from it, see rebuild_index.
+// The suggestions for a search box live alongside it, in memory only. They're loaded from the
+// database when first asked for and kept up to date the same way.
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    suggester: RwLock<Option<Suggester>>,
+    quiz_id: Field,
+    name: Field,
+    description: Field,
+    questions: Field,
+    results: Field,
+}
+
+impl SearchIndex {
+    // Opens the index in 'dir', creating it if there isn't one yet. Only one process can write to
+    // it at a time.
+    pub fn open(dir: &Path) -> tantivy::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let directory = MmapDirectory::open(dir)?;
+        Self::from_index(Index::open_or_create(directory, Self::schema())?)
+    }
+
+    // An index that only lives as long as this value does, for tests
+    pub fn in_memory() -> Self {
+        Self::from_index(Index::create_in_ram(Self::schema()))
+            .expect("an index in RAM always opens")
+    }
+
+    // Reads 'search_index_dir' from Rocket.toml, falling back to a 'search_index' directory next
+    // to the server.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Search index", |rocket| {
+            let dir = rocket
+                .config()
+                .get_str("search_index_dir")
+                .unwrap_or(DEFAULT_INDEX_DIR)
+                .to_string();
+            match SearchIndex::open(Path::new(&dir)) {
+                Ok(index) => Ok(rocket.manage(index)),
+                Err(err) => {
+                    log::error!("Couldn't open the search index in '{}': {}", dir, err);
+                    Err(rocket)
+                }
+            }
+        })
+    }
+
+    fn schema() -> Schema {
+        let mut builder = Schema::builder();
+        builder.add_i64_field("quiz_id", INDEXED | STORED);
+        builder.add_text_field("name", TEXT | STORED);
+        builder.add_text_field("description", TEXT | STORED);
+        builder.add_text_field("questions", TEXT | STORED);
+        builder.add_text_field("results", TEXT | STORED);
+        builder.build()
+    }
+
+    fn from_index(index: Index) -> tantivy::Result<Self> {
+        let schema = index.schema();
+        let field = |name: &str| schema.get_field(name);
+        // Reloaded by hand after every commit, so a search straight after a save finds it
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        Ok(Self {
+            quiz_id: field("quiz_id")?,
+            name: field("name")?,
+            description: field("description")?,
+            questions: field("questions")?,
+            results: field("results")?,
+            index,
+            reader,
+            writer: Mutex::new(writer),
+            suggester: RwLock::new(None),
+        })
+    }
+
+    fn weighted_fields(&self) -> [(Field, &'static str, f32); 4] {
+        [
+            (self.name, "name", NAME_WEIGHT),
+            (self.description, "description", DESCRIPTION_WEIGHT),
+            (self.questions, "questions", QUESTIONS_WEIGHT),
+            (self.results, "results", RESULTS_WEIGHT),
+        ]
+    }
+
+    fn document(&self, full_quiz: &FullQuiz) -> TantivyDocument {
+        let mut document = TantivyDocument::new();
+        document.add_i64(self.quiz_id, full_quiz.quiz.id as i64);
+        document.add_text(self.name, &full_quiz.quiz.name);
+        document.add_text(self.description, &full_quiz.quiz.description);
+        for qs in &full_quiz.questions {
+            document.add_text(self.questions, &qs.description);
+        }
+        for res in &full_quiz.results {
+            document.add_text(self.results, &res.header);
+            document.add_text(self.results, &res.description);
+        }
+        document
+    }
+
+    fn quiz_term(&self, quiz_id: i32) -> Term {
+        Term::from_field_i64(self.quiz_id, quiz_id as i64)
+    }
+
+    // Runs 'f' against the writer and makes whatever it did visible to searches
+    fn write<F: FnOnce(&mut IndexWriter) -> tantivy::Result<()>>(
+        &self,
+        f: F,
+    ) -> tantivy::Result<()> {
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut writer)?;
+        writer.commit()?;
+        self.reader.reload()
+    }
+
+    // Adds the quiz, or replaces what the index had for it
+    pub fn put(&self, full_quiz: &FullQuiz) -> tantivy::Result<()> {
+        self.write(|writer| {
+            writer.delete_term(self.quiz_term(full_quiz.quiz.id));
+            writer.add_document(self.document(full_quiz))?;
+            Ok(())
+        })
+    }
+
+    pub fn remove(&self, quiz_id: i32) -> tantivy::Result<()> {
+        self.write(|writer| {
+            writer.delete_term(self.quiz_term(quiz_id));
+            Ok(())
+        })
+    }
+
+    // Swaps everything in the index for 'quizzes' in one commit, so searches never see it half
+    // built.
+    pub fn replace_all(&self, quizzes: &[FullQuiz]) -> tantivy::Result<()> {
+        self.write(|writer| {
+            writer.delete_all_documents()?;
+            for full_quiz in quizzes {
+                writer.add_document(self.document(full_quiz))?;
+            }
+            Ok(())
+        })
+    }
+
+    // Suggestions for what's been typed into a search box so far
+    pub fn suggest(&self, prefix: &str, repo: &dyn Repository) -> QueryResult<Suggestions> {
+        if let Some(suggester) = self
+            .suggester
+            .read()
+            .unwrap_or_else(|p| p.into_inner())
+            .as_ref()
+        {
+            return Ok(suggester.suggest(prefix));
+        }
+        let mut suggester = self.suggester.write().unwrap_or_else(|p| p.into_inner());
+        if suggester.is_none() {
+            *suggester = Some(load_suggester(repo)?);
+        }
+        Ok(suggester
+            .as_ref()
+            .map(|s| s.suggest(prefix))
+            .unwrap_or_default())
+    }
+
+    // Applies a change to the suggestions. Until they're loaded there's nothing to change, the
+    // load finds it in the database anyway.
+    pub fn update_suggestions<F: FnOnce(&mut Suggester)>(&self, f: F) {
+        if let Some(suggester) = self
+            .suggester
+            .write()
+            .unwrap_or_else(|p| p.into_inner())
+            .as_mut()
+        {
+            f(suggester);
+        }
+    }
+
+    // The words of a query as the index stores them, each once
+    fn query_words(&self, query: &str) -> tantivy::Result<Vec<String>> {
+        let mut analyzer = self.index.tokenizer_for_field(self.name)?;
+        let mut stream = analyzer.token_stream(query);
+        let mut words: Vec<String> = Vec::new();
+        while stream.advance() {
+            let word = &stream.token().text;
+            if !words.contains(word) {
+                words.push(word.clone());
+            }
+        }
+        words.truncate(MAX_QUERY_WORDS);
+        Ok(words)
+    }
+
+    // Every word has to turn up somewhere in the quiz, spelled out or close to it. Words spelled
+    // out score by how rare they are (BM25), close ones only make up the numbers.
+    fn query(&self, words: &[String]) -> BooleanQuery {
+        let clauses = words
+            .iter()
+            .map(|word| {
+                let per_field = self
+                    .weighted_fields()
+                    .iter()
+                    .flat_map(|&(field, _, weight)| {
+                        let term = Term::from_field_text(field, word);
+                        let exact = TermQuery::new(term.clone(), IndexRecordOption::WithFreqs);
+                        let close = FuzzyTermQuery::new_prefix(
+                            term,
+                            typo_allowance(word.chars().count()),
+                            true,
+                        );
+                        vec![
+                            boosted(Box::new(exact), weight),
+                            boosted(Box::new(close), weight * CLOSE_MATCH_WEIGHT),
+                        ]
+                    })
+                    .map(|query| (Occur::Should, query))
+                    .collect();
+                (
+                    Occur::Must,
+                    Box::new(BooleanQuery::new(per_field)) as Box<dyn Query>,
+                )
+            })
+            .collect();
+        BooleanQuery::new(clauses)
+    }
+
+    // The best 'limit' quizzes for a query, best first
+    pub fn search(&self, query: &str, limit: usize) -> tantivy::Result<Vec<IndexHit>> {
+        let words = self.query_words(query)?;
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        let searcher = self.reader.searcher();
+        let top_docs = searcher.search(&self.query(&words), &TopDocs::with_limit(limit))?;
+        let mut hits = Vec::with_capacity(top_docs.len());
+        for (score, address) in top_docs {
+            let document: TantivyDocument = searcher.doc(address)?;
+            let quiz_id = match document.get_first(self.quiz_id).and_then(|id| id.as_i64()) {
+                Some(quiz_id) => quiz_id as i32,
+                None => continue,
+            };
+            let mut highlights = BTreeMap::new();
+            for (field, field_name, _) in self.weighted_fields().iter() {
+                if let Some(snippet) = self.highlight(&document, *field, &words)? {
+                    highlights.insert(String::from(*field_name), snippet);
+                }
+            }
+            hits.push(IndexHit {
+                quiz_id,
+                score,
+                highlights,
+            });
+        }
+        Ok(hits)
+    }
+
+    // The part of a field that matched best, as HTML with the matching words in <b>. Only looks at
+    // the one value of a field with the most matches, e.g. a single question.
+    fn highlight(
+        &self,
+        document: &TantivyDocument,
+        field: Field,
+        words: &[String],
+    ) -> tantivy::Result<Option<String>> {
+        let mut analyzer = self.index.tokenizer_for_field(field)?;
+        let mut best: Option<(usize, String)> = None;
+        for text in document.get_all(field).filter_map(|value| value.as_str()) {
+            // The words found in this text that the query matched, whichever way it matched them
+            let mut matched = BTreeMap::new();
+            let mut stream = analyzer.token_stream(text);
+            while stream.advance() {
+                let token = &stream.token().text;
+                if words.iter().any(|word| close_to(word, token)) {
+                    matched.insert(token.clone(), 1.0);
+                }
+            }
+            drop(stream);
+            if matched.is_empty() {
+                continue;
+            }
+            let generator = SnippetGenerator::new(matched, analyzer.clone(), field, SNIPPET_LENGTH);
+            let snippet = generator.snippet(text);
+            let count = snippet.highlighted().len();
+            let better = match &best {
+                Some((most, _)) => count > *most,
+                None => true,
+            };
+            if better {
+                best = Some((count, snippet.to_html()));
+            }
+        }
+        Ok(best.map(|(_, html)| html))
+    }
+}
+
+fn boosted(query: Box<dyn Query>, weight: f32) -> Box<dyn Query> {
+    Box::new(BoostQuery::new(query, weight))
+}
+
+// How many typos a word can have and still match. Short words have to be right, or 'cat' would
+// find 'hat' and 'car' too.
+fn typo_allowance(length: usize) -> u8 {
+    match length {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+// Whether 'token' starts with 'word' give or take its typo allowance, the same test the fuzzy
+// queries make, with a swap of two letters counting as one typo.
+fn close_to(word: &str, token: &str) -> bool {
+    let word: Vec<char> = word.chars().collect();
+    let token: Vec<char> = token.chars().collect();
+    let allowance = typo_allowance(word.len()) as usize;
+    // Edit distances from each start of 'word' to the start of 'token' read so far
+    let mut previous: Vec<usize> = Vec::new();
+    let mut row: Vec<usize> = (0..=word.len()).collect();
+    if row[word.len()] <= allowance {
+        return true;
+    }
+    for (i, &c) in token.iter().enumerate() {
+        let mut next = vec![i + 1];
+        for (j, &w) in word.iter().enumerate() {
+            let mut distance = (row[j] + (w != c) as usize)
+                .min(row[j + 1] + 1)
+                .min(next[j] + 1);
+            if i > 0 && j > 0 && w == token[i - 1] && word[j - 1] == c {
+                distance = distance.min(previous[j - 1] + 1);
+            }
+            next.push(distance);
+        }
+        if next[word.len()] <= allowance {
+            return true;
+        }
+        previous = std::mem::replace(&mut row, next);
+    }
+    false
+}
+
+// Brings the index and the suggestions in step with a quiz that was just stored, or published or
+// taken down. The save has gone through by then, so a failure is only logged, rebuild_index
+// catches the index up later.
+pub fn index_quiz(quiz_id: i32, index: &SearchIndex, repo: &dyn Repository) {
+    let outcome = get_full_quiz(quiz_id, repo)
+        .map_err(|err| err.to_string())
+        .and_then(|full_quiz| {
+            index.put(&full_quiz).map_err(|err| err.to_string())?;
+            let author = author_name(full_quiz.quiz.u_id, repo).map_err(|err| err.to_string())?;
+            index.update_suggestions(|suggester| suggester.put(&full_quiz.quiz, &author));
+            Ok(())
+        });
+    if let Err(err) = outcome {
+        log::error!("Couldn't index quiz {}: {}", quiz_id, err);
+    }
+}
+
+pub fn unindex_quiz(quiz_id: i32, index: &SearchIndex) {
+    index.update_suggestions(|suggester| suggester.remove(quiz_id));
+    if let Err(err) = index.remove(quiz_id) {
+        log::error!("Couldn't take quiz {} out of the index: {}", quiz_id, err);
+    }
+}
+
+// Fills the index again from every stored quiz, returning how many went in.
+pub fn rebuild_index(index: &SearchIndex, repo: &dyn Repository) -> Result<usize, ApiError> {
+    let mut full_quizzes = Vec::new();
+    let mut page = PageRequest {
+        limit: MAX_PAGE_SIZE,
+        sort: SortOrder::Newest,
+        after: None,
+    };
+    loop {
+        let (quizzes, _, next) =
+            repo.list_quizzes(&QuizFilter::All, &QuizFacets::default(), &page)?;
+        for qz in &quizzes {
+            full_quizzes.push(get_full_quiz(qz.id, repo)?);
+        }
+        page.after = next;
+        if page.after.is_none() {
+            break;
+        }
+    }
+    index.replace_all(&full_quizzes)?;
+    Ok(full_quizzes.len())
+}
+
+// A page of the published quizzes matching 'query' and 'facets'. They come best match first
+// unless 'by_relevance' is off, then they're in the page's own order. 'page' should be resolved
+// with the same 'by_relevance', so its cursor is one for that order.
+pub fn search_quizzes(
+    query: &str,
+    facets: &QuizFacets,
+    by_relevance: bool,
+    page: &PageRequest,
+    index: &SearchIndex,
+    repo: &dyn Repository,
+) -> Result<SearchResults, ApiError> {
+    let hits = index.search(query, MAX_HITS)?;
+    let scores: HashMap<i32, f32> = hits.iter().map(|hit| (hit.quiz_id, hit.score)).collect();
+    let filter = QuizFilter::Among(hits.iter().map(|hit| hit.quiz_id).collect());
+
+    let (mut quizzes, total, next) = if by_relevance {
+        // There are never more than MAX_HITS, so the filtered hits are fetched all at once and
+        // paged here, after they're ranked
+        let everything = PageRequest {
+            limit: MAX_HITS as i64,
+            sort: page.sort,
+            after: None,
+        };
+        let (quizzes, total, _) = repo.list_quizzes(&filter, facets, &everything)?;
+        // Best match first, ties broken by id so there's always an order to carry on from
+        let mut ranked: Vec<(Cursor, Quiz)> = quizzes
+            .into_iter()
+            .map(|qz| (Cursor::Relevance(scores[&qz.id], qz.id), qz))
+            .filter(|(position, _)| match &page.after {
+                Some(last) => position.is_past(last),
+                None => true,
+            })
+            .collect();
+        ranked.sort_by(|(_, a), (_, b)| {
+            scores[&b.id]
+                .total_cmp(&scores[&a.id])
+                .then(a.id.cmp(&b.id))
+        });
+        let next = if ranked.len() as i64 > page.limit {
+            ranked.truncate(page.limit as usize);
+            ranked.last().map(|(position, _)| position.clone())
+        } else {
+            None
+        };
+        let quizzes = ranked.into_iter().map(|(_, qz)| qz).collect();
+        (quizzes, total, next)
+    } else {
+        repo.list_quizzes(&filter, facets, page)?
+    };
+    attach_tags(&mut quizzes, repo)?;
+
+    let mut hits: HashMap<i32, IndexHit> = hits.into_iter().map(|hit| (hit.quiz_id, hit)).collect();
+    let items = quizzes
+        .into_iter()
+        .filter_map(|quiz| {
+            let hit = hits.remove(&quiz.id)?;
+            Some(SearchHit {
+                quiz,
+                score: hit.score,
+                highlights: hit.highlights,
+            })
+        })
+        .collect();
+    Ok(SearchResults {
+        page: Page::new(items, next, total),
+        facets: repo.count_facets(&filter, facets)?,
+    })
+}
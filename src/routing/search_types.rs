@@ -0,0 +1,52 @@
+use super::pagination::Page;
+use crate::models::quiz_models::Quiz;
+use crate::models::tag_models::{Facets, TagCount};
+use std::collections::BTreeMap;
+
+// What the index knows about a quiz that matched. 'highlights' has the best bit of each field
+// that matched, keyed 'name', 'description', 'questions' or 'results'. It's HTML, escaped, with
+// the matching words in <b> tags.
+#[derive(Debug)]
+pub struct IndexHit {
+    pub quiz_id: i32,
+    pub score: f32,
+    pub highlights: BTreeMap<String, String>,
+}
+
+// A quiz a search found, along with how well and where it matched
+#[derive(Serialize, Debug)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub quiz: Quiz,
+    pub score: f32,
+    pub highlights: BTreeMap<String, String>,
+}
+
+// A page of search results along with the facet counts of the whole search
+#[derive(Serialize, Debug)]
+pub struct SearchResults {
+    #[serde(flatten)]
+    pub page: Page<SearchHit>,
+    pub facets: Facets,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct QuizSuggestion {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AuthorSuggestion {
+    pub id: i32,
+    pub name: String,
+    pub quiz_count: i64, // published ones
+}
+
+// What a search box can offer for the few letters typed into it
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Suggestions {
+    pub quizzes: Vec<QuizSuggestion>,
+    pub tags: Vec<TagCount>,
+    pub authors: Vec<AuthorSuggestion>,
+}
@@ -0,0 +1,477 @@
+use super::quiz_types::*;
+use super::quiz_validation::MAX_FREE_TEXT_LEN;
+use super::submission_types::*;
+use crate::models::attempt_models::*;
+use crate::models::quiz_models::*;
+use crate::repository::Repository;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use crypto::util::fixed_time_eq;
+use diesel::result::QueryResult;
+use rand::RngCore;
+use rocket::fairing::AdHoc;
+use std::collections::BTreeMap;
+
+// Works out which result a submission lands on. In a personality quiz each question hands out
+// points to the result whose 'num' matches the 'val' of an answer, or if the answer has weights,
+// to every result it has a weight for, that many times over:
+// - single_choice: one point for the picked answer
+// - multi_select: one point for each picked answer
+// - ranking: the last ranked answer gets nothing, every place above it one more point
+// - scale: one point for the answer matching the band the value falls in, the scale is split
+//   into one equal band per answer, lowest first
+// - free_text: nothing, it only has to be there
+// The result with the most points wins. Ties go to the result with the lowest 'num', i.e. the
+// one the author listed first. How the points spread over every result comes back too.
+// A trivia quiz is marked instead, see trivia_score, and lands on the result whose band the
+// percentage falls in. Either way every question the taker's answers led them through has to be
+// answered the way its kind asks, and the ones they skipped can't be.
+pub fn score_submission(
+    full_quiz: FullQuiz,
+    submission: &Submission,
+) -> Result<SubmissionOutcome, SubmissionError> {
+    let FullQuiz {
+        quiz,
+        questions,
+        answers,
+        results,
+    } = full_quiz;
+
+    // Sort the picked answers under their questions, keeping the order they came in
+    let mut picked: Vec<Vec<&Answer>> = vec![Vec::new(); questions.len()];
+    for answer_id in &submission.answers {
+        let (question_index, ans) = answers
+            .iter()
+            .enumerate()
+            .find_map(|(i, question_answers)| {
+                question_answers
+                    .iter()
+                    .find(|ans| ans.id == *answer_id)
+                    .map(|ans| (i, ans))
+            })
+            .ok_or(SubmissionError::UnknownAnswer(*answer_id))?;
+        if !questions[question_index].kind.takes_picks() {
+            return Err(SubmissionError::WrongKind(questions[question_index].id));
+        }
+        if picked[question_index]
+            .iter()
+            .any(|ans| ans.id == *answer_id)
+        {
+            return Err(SubmissionError::DuplicateAnswer(*answer_id));
+        }
+        picked[question_index].push(ans);
+    }
+    let given_for = |question_ids: Vec<&i32>, kind: QuestionKind| {
+        for q_id in question_ids {
+            let qs = questions
+                .iter()
+                .find(|qs| qs.id == *q_id)
+                .ok_or(SubmissionError::UnknownQuestion(*q_id))?;
+            if qs.kind != kind {
+                return Err(SubmissionError::WrongKind(*q_id));
+            }
+        }
+        Ok(())
+    };
+    given_for(submission.values.keys().collect(), QuestionKind::Scale)?;
+    given_for(submission.texts.keys().collect(), QuestionKind::FreeText)?;
+
+    // Follows the rule of the answer picked for each single choice question from the first one.
+    // A quiz that passed validation can't loop, stopping at a question seen before only keeps a
+    // broken one from hanging.
+    let mut asked = vec![false; questions.len()];
+    let mut at = if questions.is_empty() { None } else { Some(0) };
+    while let Some(i) = at {
+        if asked[i] {
+            break;
+        }
+        asked[i] = true;
+        let rule = match (questions[i].kind, picked[i].as_slice()) {
+            (QuestionKind::SingleChoice, [ans]) => ans.next_question,
+            _ => None,
+        };
+        at = NextQuestion::follow(rule, i, questions.len());
+    }
+
+    let mut points: Vec<(i32, usize)> = Vec::new();
+    let mut award = |ans: &Answer, times: usize| {
+        let mut add = |num: i32, won: usize| match points.iter_mut().find(|(n, _)| *n == num) {
+            Some((_, total)) => *total += won,
+            None => points.push((num, won)),
+        };
+        if ans.weights.is_empty() {
+            add(ans.val, times);
+        }
+        for (num, weight) in &ans.weights {
+            add(*num, times * (*weight).max(0) as usize);
+        }
+    };
+    for (((qs, question_answers), picks), was_asked) in
+        questions.iter().zip(&answers).zip(&picked).zip(&asked)
+    {
+        if !was_asked {
+            let given = !picks.is_empty()
+                || submission.values.contains_key(&qs.id)
+                || submission.texts.contains_key(&qs.id);
+            if given {
+                return Err(SubmissionError::SkippedQuestion(qs.id));
+            }
+            continue;
+        }
+        let unanswered = SubmissionError::UnansweredQuestion(qs.id);
+        match qs.kind {
+            QuestionKind::SingleChoice => match picks.as_slice() {
+                [] => return Err(unanswered),
+                [ans] => award(ans, 1),
+                _ => return Err(SubmissionError::DuplicateQuestion(qs.id)),
+            },
+            QuestionKind::MultiSelect => {
+                let (min, max) = qs.config.pick_range(question_answers.len());
+                if picks.is_empty() {
+                    return Err(unanswered);
+                }
+                if picks.len() < min || picks.len() > max {
+                    return Err(SubmissionError::PickCount {
+                        question: qs.id,
+                        min,
+                        max,
+                    });
+                }
+                for ans in picks {
+                    award(ans, 1);
+                }
+            }
+            QuestionKind::Ranking => {
+                if picks.is_empty() {
+                    return Err(unanswered);
+                }
+                if picks.len() != question_answers.len() {
+                    return Err(SubmissionError::IncompleteRanking(qs.id));
+                }
+                for (rank, ans) in picks.iter().enumerate() {
+                    award(ans, picks.len() - 1 - rank);
+                }
+            }
+            QuestionKind::Scale => {
+                let value = *submission.values.get(&qs.id).ok_or(unanswered)?;
+                let band = qs
+                    .config
+                    .scale_band(value, question_answers.len())
+                    .ok_or(SubmissionError::OffTheScale(qs.id))?;
+                award(&question_answers[band], 1);
+            }
+            QuestionKind::FreeText => {
+                let text = submission.texts.get(&qs.id).map_or("", |text| text.trim());
+                if text.is_empty() {
+                    return Err(unanswered);
+                }
+                let max = qs
+                    .config
+                    .max_length
+                    .map_or(MAX_FREE_TEXT_LEN, |len| len.max(0) as usize);
+                if text.chars().count() > max {
+                    return Err(SubmissionError::TextTooLong {
+                        question: qs.id,
+                        max,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut results = results;
+    results.sort_by_key(|res| res.num);
+    match quiz.mode {
+        QuizMode::Personality => {
+            let distribution = share_out(&points, &results);
+            Ok(SubmissionOutcome {
+                result: most_points(&points, results)?,
+                trivia: None,
+                distribution: Some(distribution),
+            })
+        }
+        QuizMode::Trivia => {
+            let trivia = trivia_score(&questions, &answers, &picked, &asked);
+            let result = results
+                .into_iter()
+                .find(|res| res.covers(trivia.percentage))
+                .ok_or(SubmissionError::NoResults)?;
+            Ok(SubmissionOutcome {
+                result,
+                trivia: Some(trivia),
+                distribution: None,
+            })
+        }
+    }
+}
+
+fn points_for(points: &[(i32, usize)], num: i32) -> usize {
+    points
+        .iter()
+        .find(|(n, _)| *n == num)
+        .map_or(0, |(_, total)| *total)
+}
+
+// Every result with its share of the points, in the order of 'results'. Points for a result
+// that doesn't exist aren't part of anyone's share.
+fn share_out(points: &[(i32, usize)], results: &[QuizResult]) -> Vec<ResultShare> {
+    let total: usize = results.iter().map(|res| points_for(points, res.num)).sum();
+    results
+        .iter()
+        .map(|res| {
+            let won = points_for(points, res.num);
+            ResultShare {
+                result_id: res.id,
+                num: res.num,
+                header: res.header.clone(),
+                points: won,
+                // To a tenth of a percent
+                percentage: if total == 0 {
+                    0.0
+                } else {
+                    (won as f64 * 1000.0 / total as f64).round() / 10.0
+                },
+            }
+        })
+        .collect()
+}
+
+// 'results' in ascending 'num'. Only replacing on a strictly higher score keeps the tie-break.
+fn most_points(
+    points: &[(i32, usize)],
+    results: Vec<QuizResult>,
+) -> Result<QuizResult, SubmissionError> {
+    let mut winner: Option<(usize, QuizResult)> = None;
+    for res in results {
+        let total = points_for(points, res.num);
+        match winner {
+            Some((best, _)) if total <= best => {}
+            _ => winner = Some((total, res)),
+        }
+    }
+    winner
+        .map(|(_, res)| res)
+        .ok_or(SubmissionError::NoResults)
+}
+
+// Marks each question of a trivia quiz the taker was asked. A question is right when exactly its
+// correct answers were picked. Kinds that have no correct answers never are, a trivia quiz can't
+// be published with them anyway.
+fn trivia_score(
+    questions: &[Question],
+    answers: &[Vec<Answer>],
+    picked: &[Vec<&Answer>],
+    asked: &[bool],
+) -> TriviaScore {
+    let marked: Vec<QuestionScore> = questions
+        .iter()
+        .zip(answers)
+        .zip(picked)
+        .zip(asked)
+        .filter(|(_, was_asked)| **was_asked)
+        .map(|(((qs, question_answers), picks), _)| {
+            let correct = match qs.kind {
+                QuestionKind::SingleChoice | QuestionKind::MultiSelect => {
+                    let wanted = question_answers.iter().filter(|ans| ans.is_correct).count();
+                    // Picks are never repeated, so this is the same set of answers
+                    wanted > 0 && picks.len() == wanted && picks.iter().all(|ans| ans.is_correct)
+                }
+                QuestionKind::Scale | QuestionKind::Ranking | QuestionKind::FreeText => false,
+            };
+            QuestionScore {
+                question_id: qs.id,
+                correct,
+            }
+        })
+        .collect();
+    let score = marked.iter().filter(|qs| qs.correct).count();
+    let total = marked.len();
+    TriviaScore {
+        score,
+        total,
+        percentage: (score * 100).checked_div(total).unwrap_or(0) as i32,
+        questions: marked,
+    }
+}
+
+// Stores a scored attempt and everything given for it, returning the new attempt's id. Picks
+// are stored in the order they came in, which is what keeps a ranking. 'answers' are the quiz's
+// answers the picks are among. Each pick keeps its answer's description and the attempt keeps
+// its result's header, so editing the quiz later doesn't rewrite what the taker saw.
+// Should be called inside a transaction.
+pub fn insert_attempt(
+    quiz_id: i32,
+    user_id: Option<i32>,
+    result: &QuizResult,
+    submission: &Submission,
+    answers: &[Vec<Answer>],
+    repo: &dyn Repository,
+) -> QueryResult<i32> {
+    let attempt_id = repo.insert_attempt(NewAttempt {
+        qz_id: quiz_id,
+        u_id: user_id,
+        r_id: Some(result.id),
+        result_header: Some(result.header.clone()),
+    })?;
+    if !submission.answers.is_empty() {
+        repo.insert_attempt_answers(
+            submission
+                .answers
+                .iter()
+                .map(|answer_id| NewAttemptAnswer {
+                    at_id: attempt_id,
+                    a_id: *answer_id,
+                    description: answers
+                        .iter()
+                        .flatten()
+                        .find(|ans| ans.id == *answer_id)
+                        .map(|ans| ans.description.clone())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        )?;
+    }
+    let values = submission
+        .values
+        .iter()
+        .map(|(q_id, value)| NewAttemptResponse {
+            at_id: attempt_id,
+            q_id: *q_id,
+            scale_value: Some(*value),
+            free_text: None,
+        });
+    let texts = submission
+        .texts
+        .iter()
+        .map(|(q_id, text)| NewAttemptResponse {
+            at_id: attempt_id,
+            q_id: *q_id,
+            scale_value: None,
+            free_text: Some(text.trim().to_string()),
+        });
+    let responses: Vec<NewAttemptResponse> = values.chain(texts).collect();
+    if !responses.is_empty() {
+        repo.insert_attempt_responses(responses)?;
+    }
+    Ok(attempt_id)
+}
+
+pub fn get_attempt_details(attempt_id: i32, repo: &dyn Repository) -> QueryResult<AttemptDetails> {
+    let found = repo.get_attempt(attempt_id)?;
+    Ok(attempt_details(vec![found], repo)?.remove(0))
+}
+
+// Newest first
+pub fn get_attempts_by_user_id(
+    user_id: i32,
+    repo: &dyn Repository,
+) -> QueryResult<Vec<AttemptDetails>> {
+    let attempts = repo.get_attempts_by_user_id(user_id)?;
+    attempt_details(attempts, repo)
+}
+
+// Pulls what was given for a batch of attempts and the results they landed on in three queries.
+fn attempt_details(
+    attempts: Vec<Attempt>,
+    repo: &dyn Repository,
+) -> QueryResult<Vec<AttemptDetails>> {
+    let attempt_ids: Vec<i32> = attempts.iter().map(|at| at.id).collect();
+    let result_ids: Vec<i32> = attempts.iter().filter_map(|at| at.r_id).collect();
+    let picked = repo.get_attempt_answers(&attempt_ids)?;
+    let responses = repo.get_attempt_responses(&attempt_ids)?;
+    let mut results = repo.get_results_by_ids(&result_ids)?;
+
+    Ok(attempts
+        .into_iter()
+        .map(|at| {
+            let answers = picked
+                .iter()
+                .filter(|picked_answer| picked_answer.at_id == at.id)
+                .map(|picked_answer| PickedAnswer {
+                    id: picked_answer.a_id,
+                    description: picked_answer.description.clone(),
+                })
+                .collect();
+            let mut values = BTreeMap::new();
+            let mut texts = BTreeMap::new();
+            for response in responses.iter().filter(|response| response.at_id == at.id) {
+                if let Some(value) = response.scale_value {
+                    values.insert(response.q_id, value);
+                }
+                if let Some(text) = &response.free_text {
+                    texts.insert(response.q_id, text.clone());
+                }
+            }
+            let result = at
+                .r_id
+                .and_then(|r_id| results.iter().position(|res| res.id == r_id))
+                .map(|i| results.swap_remove(i));
+            AttemptDetails {
+                attempt: at,
+                answers,
+                values,
+                texts,
+                result,
+            }
+        })
+        .collect())
+}
+
+// Signs attempt ids so anonymous takers can come back to their result without an account.
+// Tokens look like '<attempt id>.<hex HMAC-SHA256 of the id>'.
+pub struct AttemptSigner {
+    key: Vec<u8>,
+}
+
+impl AttemptSigner {
+    pub fn new(key: &[u8]) -> Self {
+        Self { key: key.to_vec() }
+    }
+
+    // Reads the key from 'attempt_token_secret' in Rocket.toml. Without one a random key is
+    // generated, which means tokens stop working whenever the server restarts.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Attempt token signer", |rocket| {
+            let signer = match rocket.config().get_str("attempt_token_secret") {
+                Ok(secret) => AttemptSigner::new(secret.as_bytes()),
+                Err(_) => {
+                    log::warn!(
+                        "No attempt_token_secret configured, using a random one. Anonymous \
+                         attempt tokens will stop working when the server restarts."
+                    );
+                    let mut key = [0u8; 32];
+                    rand::thread_rng().fill_bytes(&mut key);
+                    AttemptSigner::new(&key)
+                }
+            };
+            Ok(rocket.manage(signer))
+        })
+    }
+
+    pub fn sign(&self, attempt_id: i32) -> String {
+        format!("{}.{}", attempt_id, self.mac(attempt_id))
+    }
+
+    // Returns the attempt id the token was issued for, if the signature checks out.
+    pub fn verify(&self, token: &str) -> Option<i32> {
+        let mut parts = token.splitn(2, '.');
+        let attempt_id = parts.next()?.parse::<i32>().ok()?;
+        let signature = parts.next()?;
+        if fixed_time_eq(signature.as_bytes(), self.mac(attempt_id).as_bytes()) {
+            Some(attempt_id)
+        } else {
+            None
+        }
+    }
+
+    fn mac(&self, attempt_id: i32) -> String {
+        let mut hmac = Hmac::new(Sha256::new(), &self.key);
+        hmac.input(attempt_id.to_string().as_bytes());
+        hmac.result()
+            .code()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
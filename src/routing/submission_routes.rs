@@ -0,0 +1,111 @@
+use rocket::http::Status;
+use rocket::State;
+use rocket_contrib::json::Json; // Easy Json coercion
+
+use crate::models::quiz_models::*; // Models needed for pulling or pushing data
+use crate::repository::{self, Repo};
+
+use super::api_error::ApiError;
+use super::quiz_functions::{ensure_quiz_visible, get_full_quiz};
+use super::quiz_types::*;
+use super::submission_functions::*;
+use super::submission_types::*;
+
+// This route handles scoring a taker's answers on the server, so the outcome can't be faked
+// by the client. Takes the ids of the picked answers, plus values and texts for scale and free
+// text questions. Trivia quizzes also say how many questions were right.
+#[post("/quiz/<quiz_id>/submit", format = "json", data = "<submission>")]
+pub fn submit(
+    quiz_id: i32,
+    submission: Json<Submission>,
+    viewer: Option<LoggedInUserID>,
+    repo: Repo,
+) -> Result<Json<SubmissionOutcome>, ApiError> {
+    let full_quiz = get_full_quiz(quiz_id, &*repo)?;
+    ensure_quiz_visible(&full_quiz.quiz, viewer.as_ref())?;
+    score_submission(full_quiz, &submission)
+        .map(Json)
+        .map_err(ApiError::from)
+}
+
+// Like submit, but also stores the attempt so it shows up in the taker's history. Anonymous
+// takers get a signed token back to fetch their result with later.
+#[post("/quiz/<quiz_id>/attempts", format = "json", data = "<submission>")]
+pub fn create_attempt(
+    quiz_id: i32,
+    submission: Json<Submission>,
+    user_id: Option<LoggedInUserID>,
+    signer: State<AttemptSigner>,
+    repo: Repo,
+) -> Result<Json<AttemptReceipt>, ApiError> {
+    let full_quiz = get_full_quiz(quiz_id, &*repo)?;
+    ensure_quiz_visible(&full_quiz.quiz, user_id.as_ref())?;
+    // A draft can be tried out through submit, but only quizzes that are out keep a history
+    if let QuizStatus::Draft | QuizStatus::Archived = full_quiz.quiz.status {
+        return Err(ApiError::new(
+            Status::Conflict,
+            "not_accepting_attempts",
+            "This quiz isn't taking attempts",
+        ));
+    }
+    let answers = full_quiz.answers.clone();
+    let SubmissionOutcome {
+        result,
+        trivia,
+        distribution,
+    } = score_submission(full_quiz, &submission)?;
+
+    let user_id = user_id.map(|LoggedInUserID(uid)| uid);
+    let attempt_id = repository::transaction(&*repo, || {
+        insert_attempt(quiz_id, user_id, &result, &submission, &answers, &*repo)
+    })?;
+    let token = match user_id {
+        Some(_) => None,
+        None => Some(signer.sign(attempt_id)),
+    };
+    Ok(Json(AttemptReceipt {
+        id: attempt_id,
+        result,
+        token,
+        trivia,
+        distribution,
+    }))
+}
+
+// Fetches a single attempt, either for the logged in user who took it or for whoever holds the
+// token handed out when it was taken anonymously.
+#[get("/attempts/<attempt_id>?<token>")]
+pub fn get_attempt(
+    attempt_id: i32,
+    token: Option<String>,
+    user_id: Option<LoggedInUserID>,
+    signer: State<AttemptSigner>,
+    repo: Repo,
+) -> Result<Json<AttemptDetails>, ApiError> {
+    let details = get_attempt_details(attempt_id, &*repo)?;
+    let token_matches = token.and_then(|t| signer.verify(&t)) == Some(attempt_id);
+    let taken_by_user = match (user_id, details.attempt.u_id) {
+        (Some(LoggedInUserID(uid)), Some(taker)) => uid == taker,
+        _ => false,
+    };
+    if token_matches || taken_by_user {
+        Ok(Json(details))
+    } else {
+        Err(ApiError::forbidden("Not allowed to view this attempt"))
+    }
+}
+
+// Ranked after /users/cookies/<uid>, which matches the same paths as far as Rocket can tell
+#[get("/users/<uid>/attempts", rank = 2)]
+pub fn get_attempts_by_user(
+    uid: i32,
+    user_id: LoggedInUserID,
+    repo: Repo,
+) -> Result<Json<Vec<AttemptDetails>>, ApiError> {
+    if user_id.0 != uid {
+        return Err(ApiError::forbidden(
+            "Not allowed to view another user's attempts",
+        ));
+    }
+    Ok(Json(get_attempts_by_user_id(uid, &*repo)?))
+}
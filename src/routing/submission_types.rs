@@ -0,0 +1,163 @@
+use super::api_error::ApiError;
+use crate::models::attempt_models::*;
+use crate::models::quiz_models::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// What a taker gave. 'answers' are the ids of the answers they picked, a ranking question takes
+// all of its answers, best first. Scale and free text questions are answered by question id in
+// 'values' and 'texts'.
+#[derive(Deserialize, Debug, Default)]
+pub struct Submission {
+    pub answers: Vec<i32>,
+    #[serde(default)]
+    pub values: BTreeMap<i32, i32>,
+    #[serde(default)]
+    pub texts: BTreeMap<i32, String>,
+}
+
+// Where a submission landed. For a trivia quiz, also how many questions it got right, for a
+// personality quiz how the points spread over every result.
+#[derive(Serialize, Debug)]
+pub struct SubmissionOutcome {
+    #[serde(flatten)]
+    pub result: QuizResult,
+    #[serde(flatten)]
+    pub trivia: Option<TriviaScore>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distribution: Option<Vec<ResultShare>>,
+}
+
+// How much of a personality submission went towards one result, e.g. 60 percent Gryffindor.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ResultShare {
+    pub result_id: i32,
+    pub num: i32,
+    pub header: String,
+    pub points: usize,
+    pub percentage: f64,
+}
+
+// A marked trivia submission. 'percentage' is rounded down, so only a perfect score gets 100.
+// Questions the taker's answers skipped aren't part of 'total'.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TriviaScore {
+    pub score: usize,
+    pub total: usize,
+    pub percentage: i32,
+    pub questions: Vec<QuestionScore>,
+}
+
+// Whether one question was answered correctly. The correct answers themselves stay hidden.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct QuestionScore {
+    pub question_id: i32,
+    pub correct: bool,
+}
+
+// What a taker gets back after their attempt is stored. Anonymous takers also get a signed
+// token that lets them fetch the attempt again later.
+#[derive(Serialize, Debug)]
+pub struct AttemptReceipt {
+    pub id: i32,
+    pub result: QuizResult,
+    pub token: Option<String>,
+    #[serde(flatten)]
+    pub trivia: Option<TriviaScore>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distribution: Option<Vec<ResultShare>>,
+}
+
+// A stored attempt along with what was given for it and the result it landed on. 'result' is
+// empty once the author has deleted it, 'attempt.result_header' still says what it was called.
+#[derive(Serialize, Debug)]
+pub struct AttemptDetails {
+    pub attempt: Attempt,
+    pub answers: Vec<PickedAnswer>,
+    pub values: BTreeMap<i32, i32>,
+    pub texts: BTreeMap<i32, String>,
+    pub result: Option<QuizResult>,
+}
+
+// An answer picked in an attempt as it read at the time. 'id' is empty once the author has
+// deleted the answer.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PickedAnswer {
+    pub id: Option<i32>,
+    pub description: String,
+}
+
+// Reasons a submission can't be scored.
+#[derive(Debug, PartialEq)]
+pub enum SubmissionError {
+    UnknownAnswer(i32),
+    UnknownQuestion(i32),
+    DuplicateAnswer(i32),
+    DuplicateQuestion(i32),
+    UnansweredQuestion(i32),
+    SkippedQuestion(i32),
+    WrongKind(i32),
+    PickCount {
+        question: i32,
+        min: usize,
+        max: usize,
+    },
+    IncompleteRanking(i32),
+    OffTheScale(i32),
+    TextTooLong {
+        question: i32,
+        max: usize,
+    },
+    NoResults,
+}
+
+impl fmt::Display for SubmissionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SubmissionError::UnknownAnswer(id) => {
+                write!(f, "Answer {} is not part of this quiz", id)
+            }
+            SubmissionError::UnknownQuestion(id) => {
+                write!(f, "Question {} is not part of this quiz", id)
+            }
+            SubmissionError::DuplicateAnswer(id) => {
+                write!(f, "Answer {} was picked more than once", id)
+            }
+            SubmissionError::DuplicateQuestion(id) => {
+                write!(f, "Question {} was answered more than once", id)
+            }
+            SubmissionError::UnansweredQuestion(id) => {
+                write!(f, "Question {} was not answered", id)
+            }
+            SubmissionError::SkippedQuestion(id) => {
+                write!(f, "Question {} was skipped by an earlier answer", id)
+            }
+            SubmissionError::WrongKind(id) => {
+                write!(f, "Question {} isn't answered that way", id)
+            }
+            SubmissionError::PickCount { question, min, max } => write!(
+                f,
+                "Question {} takes between {} and {} answers",
+                question, min, max
+            ),
+            SubmissionError::IncompleteRanking(id) => {
+                write!(f, "Question {} needs all of its answers ranked", id)
+            }
+            SubmissionError::OffTheScale(id) => {
+                write!(f, "The value for question {} is off its scale", id)
+            }
+            SubmissionError::TextTooLong { question, max } => write!(
+                f,
+                "The text for question {} is too long, at most {} characters",
+                question, max
+            ),
+            SubmissionError::NoResults => write!(f, "This quiz has no results to land on"),
+        }
+    }
+}
+
+impl From<SubmissionError> for ApiError {
+    fn from(err: SubmissionError) -> Self {
+        ApiError::unprocessable("invalid_submission", &err.to_string())
+    }
+}
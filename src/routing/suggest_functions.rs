@@ -0,0 +1,315 @@
+use super::pagination::*;
+use super::quiz_functions::attach_tags;
+use super::search_types::*;
+use crate::models::quiz_models::{Quiz, QuizStatus};
+use crate::models::tag_models::TagCount;
+use crate::repository::{QuizFacets, QuizFilter, Repository};
+use diesel::QueryResult;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// How many of each kind a suggestion hands back
+pub const MAX_SUGGESTIONS: usize = 5;
+// How many matches of a kind are ranked at most. A prefix of a letter or two matches most of
+// everything, this keeps those as quick as the rest.
+const MAX_CANDIDATES: usize = 200;
+
+// Text the way it's looked up, its words lowercased and split on anything that isn't a letter or
+// a digit
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+// Sorted text keys, so everything starting with a prefix sits together. Each item is kept under
+// every word of its text onwards, 'Which cat are you' under 'which cat are you', 'cat are you',
+// 'are you' and 'you', so a prefix finds it by any of its words. Whole texts are kept apart from
+// the ones starting at a later word, and under their length first, so the shortest texts
+// starting with a prefix are found before anything else.
+struct PrefixMap<T: Ord> {
+    whole: BTreeMap<(usize, String), BTreeSet<T>>,
+    later_words: BTreeMap<String, BTreeSet<T>>,
+}
+
+impl<T: Ord + Clone> PrefixMap<T> {
+    fn new() -> Self {
+        Self {
+            whole: BTreeMap::new(),
+            later_words: BTreeMap::new(),
+        }
+    }
+
+    // The whole text under its length as given, then the keys from each later word onwards
+    fn keys_of(text: &str) -> (Option<(usize, String)>, Vec<String>) {
+        let words = words(text);
+        let whole = words.first().map(|_| (text.len(), words.join(" ")));
+        let later = (1..words.len()).map(|i| words[i..].join(" ")).collect();
+        (whole, later)
+    }
+
+    fn insert(&mut self, text: &str, item: T) {
+        let (whole, later) = Self::keys_of(text);
+        if let Some(key) = whole {
+            self.whole.entry(key).or_default().insert(item.clone());
+        }
+        for key in later {
+            self.later_words
+                .entry(key)
+                .or_default()
+                .insert(item.clone());
+        }
+    }
+
+    fn remove(&mut self, text: &str, item: &T) {
+        let (whole, later) = Self::keys_of(text);
+        if let Some(key) = whole {
+            remove_item(&mut self.whole, key, item);
+        }
+        for key in later {
+            remove_item(&mut self.later_words, key, item);
+        }
+    }
+
+    // Items with a word starting with 'prefix', each once, up to MAX_CANDIDATES of them. They're
+    // gathered the way Suggester::suggest ranks names, the ones starting with it shortest first,
+    // so those are never cut off for a longer one. The ones with a later word starting with it
+    // come after, alphabetically by the rest of the text from that word.
+    fn find(&self, prefix: &str) -> Vec<T> {
+        let mut found = BTreeSet::new();
+        // A length at a time, each one ranged over like the later words are
+        let mut next = self.whole.keys().next().map(|(len, _)| *len);
+        while let Some(len) = next {
+            if found.len() >= MAX_CANDIDATES {
+                return found.into_iter().collect();
+            }
+            let matching = self
+                .whole
+                .range((len, prefix.to_string())..)
+                .take_while(|((key_len, key), _)| *key_len == len && key.starts_with(prefix));
+            for (_, items) in matching {
+                found.extend(items.iter().cloned());
+            }
+            next = self
+                .whole
+                .range((len + 1, String::new())..)
+                .next()
+                .map(|((len, _), _)| *len);
+        }
+
+        let matching = self
+            .later_words
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix));
+        for (_, items) in matching {
+            if found.len() >= MAX_CANDIDATES {
+                break;
+            }
+            found.extend(items.iter().cloned());
+        }
+        found.into_iter().collect()
+    }
+}
+
+fn remove_item<K: Ord, T: Ord>(keys: &mut BTreeMap<K, BTreeSet<T>>, key: K, item: &T) {
+    if let Some(items) = keys.get_mut(&key) {
+        items.remove(item);
+        if items.is_empty() {
+            keys.remove(&key);
+        }
+    }
+}
+
+// What a published quiz put in, so it can be taken back out again
+struct SuggestedQuiz {
+    name: String,
+    u_id: i32,
+    tags: Vec<String>,
+}
+
+// Where search box suggestions come from: the names of published quizzes, the tags on them and
+// the names of their authors. Kept in memory and changed a quiz at a time as quizzes are saved,
+// see SearchIndex::update_suggestions.
+pub struct Suggester {
+    quizzes: HashMap<i32, SuggestedQuiz>,
+    quiz_names: PrefixMap<i32>,
+    tags: PrefixMap<String>,
+    authors: PrefixMap<i32>,
+    // How many published quizzes carry each tag, and each author has written
+    tag_counts: HashMap<String, i64>,
+    author_counts: HashMap<i32, (String, i64)>,
+}
+
+impl Default for Suggester {
+    fn default() -> Self {
+        Self {
+            quizzes: HashMap::new(),
+            quiz_names: PrefixMap::new(),
+            tags: PrefixMap::new(),
+            authors: PrefixMap::new(),
+            tag_counts: HashMap::new(),
+            author_counts: HashMap::new(),
+        }
+    }
+}
+
+impl Suggester {
+    // Brings what's suggested for a quiz up to date, 'author' being its author's name. Quizzes
+    // that aren't published are taken out.
+    pub fn put(&mut self, quiz: &Quiz, author: &str) {
+        self.remove(quiz.id);
+        if quiz.status != QuizStatus::Published {
+            return;
+        }
+        self.quiz_names.insert(&quiz.name, quiz.id);
+        for tag in &quiz.tags {
+            let count = self.tag_counts.entry(tag.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                self.tags.insert(tag, tag.clone());
+            }
+        }
+        let (_, count) = self
+            .author_counts
+            .entry(quiz.u_id)
+            .or_insert_with(|| (String::from(author), 0));
+        *count += 1;
+        if *count == 1 {
+            self.authors.insert(author, quiz.u_id);
+        }
+        self.quizzes.insert(
+            quiz.id,
+            SuggestedQuiz {
+                name: quiz.name.clone(),
+                u_id: quiz.u_id,
+                tags: quiz.tags.clone(),
+            },
+        );
+    }
+
+    pub fn remove(&mut self, quiz_id: i32) {
+        let suggested = match self.quizzes.remove(&quiz_id) {
+            Some(suggested) => suggested,
+            None => return,
+        };
+        self.quiz_names.remove(&suggested.name, &quiz_id);
+        for tag in &suggested.tags {
+            if let Some(count) = self.tag_counts.get_mut(tag) {
+                *count -= 1;
+                if *count == 0 {
+                    self.tag_counts.remove(tag);
+                    self.tags.remove(tag, tag);
+                }
+            }
+        }
+        if let Some((author, count)) = self.author_counts.get_mut(&suggested.u_id) {
+            *count -= 1;
+            if *count == 0 {
+                let author = author.clone();
+                self.author_counts.remove(&suggested.u_id);
+                self.authors.remove(&author, &suggested.u_id);
+            }
+        }
+    }
+
+    // The best few of each kind for what's been typed so far. Quiz names starting with it come
+    // first, then the shortest. Tags and authors with the most quizzes come first, out of the
+    // candidates PrefixMap::find picks.
+    pub fn suggest(&self, prefix: &str) -> Suggestions {
+        let prefix = words(prefix).join(" ");
+        if prefix.is_empty() {
+            return Suggestions::default();
+        }
+
+        let mut quizzes: Vec<QuizSuggestion> = self
+            .quiz_names
+            .find(&prefix)
+            .into_iter()
+            .filter_map(|quiz_id| {
+                let suggested = self.quizzes.get(&quiz_id)?;
+                Some(QuizSuggestion {
+                    id: quiz_id,
+                    name: suggested.name.clone(),
+                })
+            })
+            .collect();
+        quizzes.sort_by_cached_key(|qz| {
+            let from_start = words(&qz.name).join(" ").starts_with(&prefix);
+            (!from_start, qz.name.len(), qz.name.clone(), qz.id)
+        });
+        quizzes.truncate(MAX_SUGGESTIONS);
+
+        let mut tags: Vec<TagCount> = self
+            .tags
+            .find(&prefix)
+            .into_iter()
+            .map(|name| TagCount {
+                count: self.tag_counts.get(&name).copied().unwrap_or(0),
+                name,
+            })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+        tags.truncate(MAX_SUGGESTIONS);
+
+        let mut authors: Vec<AuthorSuggestion> = self
+            .authors
+            .find(&prefix)
+            .into_iter()
+            .filter_map(|u_id| {
+                let (name, quiz_count) = self.author_counts.get(&u_id)?;
+                Some(AuthorSuggestion {
+                    id: u_id,
+                    name: name.clone(),
+                    quiz_count: *quiz_count,
+                })
+            })
+            .collect();
+        authors.sort_by(|a, b| {
+            b.quiz_count
+                .cmp(&a.quiz_count)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        authors.truncate(MAX_SUGGESTIONS);
+
+        Suggestions {
+            quizzes,
+            tags,
+            authors,
+        }
+    }
+}
+
+// The name of a quiz's author, for putting it in the suggestions
+pub fn author_name(u_id: i32, repo: &dyn Repository) -> QueryResult<String> {
+    Ok(repo
+        .fetch_user_by_id(u_id)?
+        .map(|user| user.name)
+        .unwrap_or_default())
+}
+
+// Fills a Suggester from every published quiz in the database.
+pub fn load_suggester(repo: &dyn Repository) -> QueryResult<Suggester> {
+    let mut suggester = Suggester::default();
+    let mut authors: HashMap<i32, String> = HashMap::new();
+    let mut page = PageRequest {
+        limit: MAX_PAGE_SIZE,
+        sort: SortOrder::Newest,
+        after: None,
+    };
+    loop {
+        let (mut quizzes, _, next) =
+            repo.list_quizzes(&QuizFilter::Published, &QuizFacets::default(), &page)?;
+        attach_tags(&mut quizzes, repo)?;
+        for qz in &quizzes {
+            if !authors.contains_key(&qz.u_id) {
+                authors.insert(qz.u_id, author_name(qz.u_id, repo)?);
+            }
+            suggester.put(qz, &authors[&qz.u_id]);
+        }
+        page.after = next;
+        if page.after.is_none() {
+            break;
+        }
+    }
+    Ok(suggester)
+}
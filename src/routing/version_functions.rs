@@ -0,0 +1,233 @@
+use super::quiz_functions::get_full_quiz;
+use super::quiz_types::*;
+use super::version_types::*;
+use crate::models::version_models::*;
+use crate::repository::Repository;
+use diesel::result::{Error, QueryResult};
+
+// The quiz a version saved. Snapshots are written by record_version, so one that doesn't parse
+// is treated like any other row that can't be read.
+pub fn snapshot_of(version: &QuizVersion) -> QueryResult<FullQuiz> {
+    serde_json::from_str(&version.snapshot)
+        .map_err(|err| Error::DeserializationError(Box::new(err)))
+}
+
+// Records the quiz as it stands as its next version, unless nothing changed since the last one.
+// 'summary' defaults to one worked out from the changes. Should be called inside the transaction
+// that saved the quiz, so there's never a save without its version.
+pub fn record_version(
+    quiz_id: i32,
+    u_id: i32,
+    summary: Option<String>,
+    repo: &dyn Repository,
+) -> QueryResult<()> {
+    let current = get_full_quiz(quiz_id, repo)?;
+    let (num, changes) = match repo.get_versions(quiz_id)?.first() {
+        Some(latest) => {
+            let previous = snapshot_of(&repo.get_version(quiz_id, latest.num)?)?;
+            let diff = diff_quizzes(&previous, &current);
+            if diff.is_empty() {
+                return Ok(());
+            }
+            (latest.num + 1, diff.summary())
+        }
+        None => (1, String::from("First version")),
+    };
+
+    let snapshot =
+        serde_json::to_string(&current).map_err(|err| Error::SerializationError(Box::new(err)))?;
+    repo.insert_version(NewQuizVersion {
+        qz_id: quiz_id,
+        num,
+        u_id: Some(u_id),
+        summary: summary.unwrap_or(changes),
+        snapshot,
+    })?;
+    Ok(())
+}
+
+// Compares two versions of a quiz question by question. Only what the author wrote is
+// compared, a quiz's status has nothing to do with its versions. Moving things around doesn't
+// change them, it's reported once for the whole quiz.
+pub fn diff_quizzes(before: &FullQuiz, after: &FullQuiz) -> QuizDiff {
+    let text_change = |before: &str, after: &str| {
+        if before == after {
+            None
+        } else {
+            Some(TextChange {
+                before: String::from(before),
+                after: String::from(after),
+            })
+        }
+    };
+    QuizDiff {
+        name: text_change(&before.quiz.name, &after.quiz.name),
+        description: text_change(&before.quiz.description, &after.quiz.description),
+        mode: text_change(before.quiz.mode.as_str(), after.quiz.mode.as_str()),
+        media: before.quiz.media_id != after.quiz.media_id,
+        category: text_change(
+            before.quiz.category.as_deref().unwrap_or(""),
+            after.quiz.category.as_deref().unwrap_or(""),
+        ),
+        tags: text_change(&before.quiz.tags.join(", "), &after.quiz.tags.join(", ")),
+        reordered: shared_order(before, after) != shared_order(after, before),
+        questions: diff_by_id(
+            nested_questions(before),
+            nested_questions(after),
+            |qs| qs.id,
+            same_question,
+        )
+        .into_iter()
+        .map(|(change, before, after)| QuestionDiff {
+            change,
+            before,
+            after,
+        })
+        .collect(),
+        results: diff_by_id(
+            before.results.clone(),
+            after.results.clone(),
+            |res| res.id,
+            |a, b| a == b,
+        )
+        .into_iter()
+        .map(|(change, before, after)| ResultDiff {
+            change,
+            before,
+            after,
+        })
+        .collect(),
+    }
+}
+
+// An old version as an edit on top of the current quiz, for edit_full_quiz. Records the current
+// quiz still has keep their ids, ones deleted since then come back as new records.
+pub fn restore_edit(old: &FullQuiz, current: &FullQuiz) -> IncomingFullQuiz {
+    let mut edit = IncomingFullQuiz::from(old);
+    for (qs, answers) in edit.questions.iter_mut().zip(edit.answers.iter_mut()) {
+        let position = current
+            .questions
+            .iter()
+            .position(|stored| Some(stored.id) == qs.id);
+        match position {
+            Some(position) => {
+                for ans in answers.iter_mut() {
+                    if !current.answers[position]
+                        .iter()
+                        .any(|stored| Some(stored.id) == ans.id)
+                    {
+                        ans.id = None;
+                    }
+                }
+            }
+            None => {
+                qs.id = None;
+                for ans in answers.iter_mut() {
+                    ans.id = None;
+                }
+            }
+        }
+    }
+    for res in edit.results.iter_mut() {
+        if !current
+            .results
+            .iter()
+            .any(|stored| Some(stored.id) == res.id)
+        {
+            res.id = None;
+        }
+    }
+    edit
+}
+
+fn nested_questions(full_quiz: &FullQuiz) -> Vec<NestedQuestion> {
+    full_quiz
+        .questions
+        .iter()
+        .zip(&full_quiz.answers)
+        .map(|(qs, answers)| NestedQuestion {
+            id: qs.id,
+            description: qs.description.clone(),
+            qz_id: qs.qz_id,
+            position: qs.position,
+            kind: qs.kind,
+            config: qs.config.clone(),
+            media_id: qs.media_id,
+            answers: answers.clone(),
+        })
+        .collect()
+}
+
+// Same question and answers, wherever they sit
+fn same_question(a: &NestedQuestion, b: &NestedQuestion) -> bool {
+    a.description == b.description
+        && a.kind == b.kind
+        && a.config == b.config
+        && a.media_id == b.media_id
+        && a.answers.len() == b.answers.len()
+        && a.answers.iter().all(|ans| {
+            b.answers.iter().any(|other| {
+                other.id == ans.id
+                    && other.description == ans.description
+                    && other.val == ans.val
+                    && other.is_correct == ans.is_correct
+                    && other.weights == ans.weights
+                    && other.next_question == ans.next_question
+                    && other.media_id == ans.media_id
+            })
+        })
+}
+
+// The ids of the questions, each with the ids of its answers, in order. Leaves out whatever
+// 'other' doesn't have, so adding or removing something doesn't count as moving the rest.
+fn shared_order(full_quiz: &FullQuiz, other: &FullQuiz) -> Vec<(i32, Vec<i32>)> {
+    let other_answer_ids: Vec<i32> = other.answers.iter().flatten().map(|ans| ans.id).collect();
+    full_quiz
+        .questions
+        .iter()
+        .zip(&full_quiz.answers)
+        .filter(|(qs, _)| other.questions.iter().any(|o| o.id == qs.id))
+        .map(|(qs, answers)| {
+            let answer_ids = answers
+                .iter()
+                .map(|ans| ans.id)
+                .filter(|a_id| other_answer_ids.contains(a_id))
+                .collect();
+            (qs.id, answer_ids)
+        })
+        .collect()
+}
+
+// Pairs records up by id. Changed and added ones come in the order of 'after', followed by
+// whatever was removed.
+fn diff_by_id<T, F, S>(
+    before: Vec<T>,
+    after: Vec<T>,
+    id: F,
+    same: S,
+) -> Vec<(Change, Option<T>, Option<T>)>
+where
+    F: Fn(&T) -> i32,
+    S: Fn(&T, &T) -> bool,
+{
+    let mut before: Vec<Option<T>> = before.into_iter().map(Some).collect();
+    let mut changes = Vec::new();
+    for item in after {
+        let matched = before
+            .iter_mut()
+            .find(|old| old.as_ref().map(&id) == Some(id(&item)))
+            .and_then(Option::take);
+        match matched {
+            Some(old) if same(&old, &item) => {}
+            Some(old) => changes.push((Change::Changed, Some(old), Some(item))),
+            None => changes.push((Change::Added, None, Some(item))),
+        }
+    }
+    changes.extend(
+        before
+            .into_iter()
+            .flatten()
+            .map(|old| (Change::Removed, Some(old), None)),
+    );
+    changes
+}
@@ -0,0 +1,87 @@
+use rocket::State;
+use rocket_contrib::json::Json; // Easy Json coercion
+
+use crate::models::quiz_models::QuizStatus;
+use crate::models::version_models::QuizVersionInfo;
+use crate::repository::{self, Repo};
+
+use super::api_error::ApiError;
+use super::quiz_functions::{edit_full_quiz, ensure_quiz_owner, get_full_quiz};
+use super::quiz_types::*;
+use super::quiz_validation::{validate_draft, validate_full_quiz};
+use super::search_functions::{index_quiz, SearchIndex};
+use super::version_functions::*;
+use super::version_types::*;
+
+// A quiz's history is for its author only, it includes every draft they ever saved.
+
+// Every saved version of a quiz, newest first.
+#[get("/quiz/<quiz_id>/versions")]
+pub fn list_versions(
+    quiz_id: i32,
+    user_id: LoggedInUserID,
+    repo: Repo,
+) -> Result<Json<Vec<QuizVersionInfo>>, ApiError> {
+    ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    Ok(Json(repo.get_versions(quiz_id)?))
+}
+
+#[get("/quiz/<quiz_id>/versions/<num>")]
+pub fn get_version(
+    quiz_id: i32,
+    num: i32,
+    user_id: LoggedInUserID,
+    repo: Repo,
+) -> Result<Json<QuizVersionView>, ApiError> {
+    ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    let version = repo.get_version(quiz_id, num)?;
+    let quiz = snapshot_of(&version)?;
+    Ok(Json(QuizVersionView {
+        version: QuizVersionInfo::from(version),
+        quiz,
+    }))
+}
+
+// What changed between versions 'from' and 'to', question by question.
+#[get("/quiz/<quiz_id>/diff?<from>&<to>")]
+pub fn diff_versions(
+    quiz_id: i32,
+    from: i32,
+    to: i32,
+    user_id: LoggedInUserID,
+    repo: Repo,
+) -> Result<Json<QuizDiff>, ApiError> {
+    ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    let before = snapshot_of(&repo.get_version(quiz_id, from)?)?;
+    let after = snapshot_of(&repo.get_version(quiz_id, to)?)?;
+    Ok(Json(diff_quizzes(&before, &after)))
+}
+
+// Makes an old version the current one. The restore is a save like any other, so it becomes the
+// newest version and can itself be undone. Hands back the quiz as restored.
+#[post("/quiz/<quiz_id>/versions/<num>/restore")]
+pub fn restore_version(
+    quiz_id: i32,
+    num: i32,
+    user_id: LoggedInUserID,
+    index: State<SearchIndex>,
+    repo: Repo,
+) -> Result<Json<FullQuiz>, ApiError> {
+    let quiz = ensure_quiz_owner(quiz_id, &user_id, &*repo)?;
+    let old = snapshot_of(&repo.get_version(quiz_id, num)?)?;
+    let current = get_full_quiz(quiz_id, &*repo)?;
+    let edit = restore_edit(&old, &current);
+    // The version was checked against the rules of its day, what goes out has to pass today's
+    match quiz.status {
+        QuizStatus::Draft => validate_draft(&edit)?,
+        _ => validate_full_quiz(&edit)?,
+    }
+
+    repository::transaction(&*repo, || {
+        edit_full_quiz(&current, edit, &*repo)?;
+        let summary = format!("Restored version {}", num);
+        record_version(quiz_id, user_id.0, Some(summary), &*repo)
+    })?;
+    index_quiz(quiz_id, &index, &*repo);
+    Ok(Json(get_full_quiz(quiz_id, &*repo)?))
+}
@@ -0,0 +1,121 @@
+use super::quiz_types::{FullQuiz, NestedQuestion};
+use crate::models::quiz_models::QuizResult;
+use crate::models::version_models::QuizVersionInfo;
+
+// A single version with the quiz as it was saved.
+#[derive(Serialize, Debug)]
+pub struct QuizVersionView {
+    #[serde(flatten)]
+    pub version: QuizVersionInfo,
+    pub quiz: FullQuiz,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TextChange {
+    pub before: String,
+    pub after: String,
+}
+
+// A question that differs between two versions, with its answers. Questions are matched up by
+// id, so one that was deleted and written again shows up as removed and added.
+#[derive(Serialize, Debug)]
+pub struct QuestionDiff {
+    pub change: Change,
+    pub before: Option<NestedQuestion>,
+    pub after: Option<NestedQuestion>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResultDiff {
+    pub change: Change,
+    pub before: Option<QuizResult>,
+    pub after: Option<QuizResult>,
+}
+
+// What changed going from one version of a quiz to another. Anything left out is the same in
+// both.
+#[derive(Serialize, Debug)]
+pub struct QuizDiff {
+    pub name: Option<TextChange>,
+    pub description: Option<TextChange>,
+    pub mode: Option<TextChange>,
+    pub media: bool,                  // whether the quiz's own picture was swapped
+    pub category: Option<TextChange>, // empty for no category
+    pub tags: Option<TextChange>,     // comma separated
+    pub reordered: bool,              // whether questions, or answers within one, were moved
+    pub questions: Vec<QuestionDiff>,
+    pub results: Vec<ResultDiff>,
+}
+
+impl QuizDiff {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.description.is_none()
+            && self.mode.is_none()
+            && !self.media
+            && self.category.is_none()
+            && self.tags.is_none()
+            && !self.reordered
+            && self.questions.is_empty()
+            && self.results.is_empty()
+    }
+
+    // One line for the version history, e.g. "Renamed, reordered, 1 result added".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if self.name.is_some() {
+            parts.push(String::from("renamed"));
+        }
+        if self.description.is_some() {
+            parts.push(String::from("description changed"));
+        }
+        if let Some(mode) = &self.mode {
+            parts.push(format!("switched to {}", mode.after));
+        }
+        if self.category.is_some() {
+            parts.push(String::from("recategorized"));
+        }
+        if self.tags.is_some() {
+            parts.push(String::from("retagged"));
+        }
+        if self.media {
+            parts.push(String::from("picture changed"));
+        }
+        if self.reordered {
+            parts.push(String::from("reordered"));
+        }
+        let question_changes: Vec<Change> = self.questions.iter().map(|qs| qs.change).collect();
+        let result_changes: Vec<Change> = self.results.iter().map(|res| res.change).collect();
+        count_changes(&mut parts, &question_changes, "question");
+        count_changes(&mut parts, &result_changes, "result");
+
+        let summary = parts.join(", ");
+        let mut chars = summary.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::from("No changes"),
+        }
+    }
+}
+
+fn count_changes(parts: &mut Vec<String>, changes: &[Change], noun: &str) {
+    for (change, verb) in &[
+        (Change::Added, "added"),
+        (Change::Changed, "changed"),
+        (Change::Removed, "removed"),
+    ] {
+        let count = changes.iter().filter(|c| *c == change).count();
+        if count > 0 {
+            let plural = if count == 1 { "" } else { "s" };
+            parts.push(format!("{} {}{} {}", count, noun, plural, verb));
+        }
+    }
+}
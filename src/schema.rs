@@ -4,6 +4,56 @@ table! {
         description -> Varchar,
         val -> Integer,
         q_id -> Integer,
+        position -> Integer,
+        is_correct -> Bool,
+        media_id -> Nullable<Integer>,
+    }
+}
+
+table! {
+    answer_rule (id) {
+        id -> Integer,
+        a_id -> Integer,
+        next_q_id -> Nullable<Integer>,
+    }
+}
+
+table! {
+    answer_weight (id) {
+        id -> Integer,
+        a_id -> Integer,
+        r_id -> Integer,
+        weight -> Integer,
+    }
+}
+
+table! {
+    attempt (id) {
+        id -> Integer,
+        qz_id -> Integer,
+        u_id -> Nullable<Integer>,
+        r_id -> Nullable<Integer>,
+        result_header -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    attempt_answer (id) {
+        id -> Integer,
+        at_id -> Integer,
+        a_id -> Nullable<Integer>,
+        description -> Varchar,
+    }
+}
+
+table! {
+    attempt_response (id) {
+        id -> Integer,
+        at_id -> Integer,
+        q_id -> Integer,
+        scale_value -> Nullable<Integer>,
+        free_text -> Nullable<Varchar>,
     }
 }
 
@@ -15,11 +65,27 @@ table! {
     }
 }
 
+table! {
+    media (id) {
+        id -> Integer,
+        u_id -> Integer,
+        content_type -> Varchar,
+        byte_size -> Integer,
+        width -> Integer,
+        height -> Integer,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     question (id) {
         id -> Integer,
         description -> Varchar,
         qz_id -> Integer,
+        position -> Integer,
+        kind -> Varchar,
+        config -> Varchar,
+        media_id -> Nullable<Integer>,
     }
 }
 
@@ -29,6 +95,32 @@ table! {
         name -> Varchar,
         description -> Varchar,
         u_id -> Integer,
+        status -> Varchar,
+        published_at -> Nullable<Timestamp>,
+        forked_from_id -> Nullable<Integer>,
+        allow_forks -> Bool,
+        mode -> Varchar,
+        media_id -> Nullable<Integer>,
+        category -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    quiz_tag (qz_id, tag_id) {
+        qz_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
+table! {
+    quiz_version (id) {
+        id -> Integer,
+        qz_id -> Integer,
+        num -> Integer,
+        u_id -> Nullable<Integer>,
+        created_at -> Timestamp,
+        summary -> Varchar,
+        snapshot -> Text,
     }
 }
 
@@ -39,6 +131,27 @@ table! {
         header -> Varchar,
         description -> Varchar,
         qz_id -> Integer,
+        min_percent -> Nullable<Integer>,
+        max_percent -> Nullable<Integer>,
+        media_id -> Nullable<Integer>,
+    }
+}
+
+table! {
+    session (id) {
+        id -> Varchar,
+        u_id -> Integer,
+        created_at -> Timestamp,
+        last_seen -> Timestamp,
+        expires_at -> Timestamp,
+        user_agent -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    tag (id) {
+        id -> Integer,
+        name -> Varchar,
     }
 }
 
@@ -51,15 +164,39 @@ table! {
 }
 
 joinable!(answer -> question (q_id));
+joinable!(answer_rule -> answer (a_id));
+joinable!(answer_weight -> answer (a_id));
+joinable!(answer_weight -> result (r_id));
+joinable!(attempt -> quiz (qz_id));
+joinable!(attempt_answer -> answer (a_id));
+joinable!(attempt_answer -> attempt (at_id));
+joinable!(attempt_response -> attempt (at_id));
+joinable!(attempt_response -> question (q_id));
+joinable!(media -> user (u_id));
 joinable!(question -> quiz (qz_id));
 joinable!(quiz -> user (u_id));
+joinable!(quiz_tag -> quiz (qz_id));
+joinable!(quiz_tag -> tag (tag_id));
+joinable!(quiz_version -> quiz (qz_id));
+joinable!(quiz_version -> user (u_id));
 joinable!(result -> quiz (qz_id));
+joinable!(session -> user (u_id));
 
 allow_tables_to_appear_in_same_query!(
     answer,
+    answer_rule,
+    answer_weight,
+    attempt,
+    attempt_answer,
+    attempt_response,
     auth_info,
+    media,
     question,
     quiz,
+    quiz_tag,
+    quiz_version,
     result,
+    session,
+    tag,
     user,
 );
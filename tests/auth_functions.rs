@@ -0,0 +1,23 @@
+use quizzes_backend::routing::auth_functions::{hash_password, verify_password, PasswordCheck};
+
+#[test]
+fn test_hash_password() {
+    let hash = hash_password("hunter2").unwrap();
+    assert!(hash.starts_with("$argon2id$"));
+    // Salted, so the same password never hashes the same way twice
+    assert_ne!(hash, hash_password("hunter2").unwrap());
+
+    assert_eq!(verify_password("hunter2", &hash), PasswordCheck::Valid);
+    assert_eq!(verify_password("hunter3", &hash), PasswordCheck::Invalid);
+}
+
+#[test]
+fn test_verify_legacy_password() {
+    // SHA3-256 of 'hunter2', as stored before the switch to Argon2
+    let legacy = "3ec80fc0faa8db8b7e6e99c74136054965901a8adf7fd6f51b1962a4c0dcab1c";
+    assert_eq!(
+        verify_password("hunter2", legacy),
+        PasswordCheck::ValidNeedsRehash
+    );
+    assert_eq!(verify_password("hunter3", legacy), PasswordCheck::Invalid);
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use common::{client_for, json_body, log_in, memory_client, post_json, sign_up};
+use quizzes_backend::repository::Repository;
+use rocket::http::Status;
+use rocket::local::Client;
+use serde_json::json;
+
+#[test]
+fn test_create_and_login() {
+    let (client, repo) = memory_client();
+    let uid = sign_up(&client, "Someone");
+
+    // The password is stored hashed
+    let auth_info = repo.fetch_auth_info_by_user_id(uid).unwrap().unwrap();
+    assert_ne!(auth_info.password_hash, "hunter22");
+
+    let wrong_password = json!({ "username": "someone@example.com", "password": "hunter23" });
+    let unknown_email = json!({ "username": "nobody@example.com", "password": "hunter22" });
+    for credentials in &[wrong_password, unknown_email] {
+        let mut response = post_json(&client, "/users/login", credentials);
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(json_body(&mut response)["code"], "invalid_credentials");
+    }
+
+    let mut response = client.get(format!("/users/cookies/{}", uid)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(json_body(&mut response)["email"], "someone@example.com");
+    let response = client.get(format!("/users/cookies/{}", uid + 1)).dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[test]
+fn test_logout() {
+    let (client, _repo) = memory_client();
+    let uid = sign_up(&client, "Someone");
+
+    let response = post_json(&client, "/users/logout", &json!({}));
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get(format!("/users/cookies/{}", uid)).dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    // Logging out twice is harmless
+    let response = post_json(&client, "/users/logout", &json!({}));
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+fn test_sessions() {
+    let (client, repo) = memory_client();
+    let uid = sign_up(&client, "Someone");
+    // A second device, the client only keeps the newest cookie
+    log_in(&client, "someone@example.com", "hunter22");
+
+    let mut response = client.get("/users/sessions").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let sessions = json_body(&mut response);
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    let other = sessions
+        .iter()
+        .find(|session| session["current"] == false)
+        .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response = client
+        .delete(format!("/users/sessions/{}", other))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(repo.fetch_session(&other).unwrap().is_none());
+    let response = client
+        .delete(format!("/users/sessions/{}", other))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client.delete("/users/sessions").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(repo
+        .fetch_sessions_by_user_id(uid, chrono::Utc::now().naive_utc())
+        .unwrap()
+        .is_empty());
+    let response = client.get("/users/sessions").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+// The id of the session a client is making requests with
+fn current_session(client: &Client) -> String {
+    let mut response = client.get("/users/sessions").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    json_body(&mut response)
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|session| session["current"] == true)
+        .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_sessions_across_devices() {
+    let (laptop, repo) = memory_client();
+    sign_up(&laptop, "Someone");
+    let phone = client_for(repo.clone());
+    log_in(&phone, "someone@example.com", "hunter22");
+    let tablet = client_for(repo.clone());
+    log_in(&tablet, "someone@example.com", "hunter22");
+    let stranger = client_for(repo);
+    sign_up(&stranger, "Stranger");
+
+    // Nobody else's sessions can be revoked, and trying doesn't give away that they exist
+    let strangers = current_session(&stranger);
+    let response = laptop
+        .delete(format!("/users/sessions/{}", strangers))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(current_session(&stranger), strangers);
+
+    // Revoking a device signs it out there
+    let phones = current_session(&phone);
+    let response = laptop
+        .delete(format!("/users/sessions/{}", phones))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = phone.get("/users/sessions").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    current_session(&tablet);
+
+    // Logging out everywhere leaves nothing signed in but other people
+    let response = laptop.delete("/users/sessions").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    for client in &[&laptop, &phone, &tablet] {
+        let response = client.get("/users/sessions").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+    assert_eq!(current_session(&stranger), strangers);
+}
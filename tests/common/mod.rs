@@ -0,0 +1,176 @@
+// Helpers shared by the integration tests. Not every test uses every helper.
+#![allow(dead_code)]
+
+use quizzes_backend::models::auth_models::NewUser;
+use quizzes_backend::models::quiz_models::*;
+use quizzes_backend::repository::memory::MemoryRepository;
+use quizzes_backend::repository::{Repository, Storage};
+use quizzes_backend::routing;
+use quizzes_backend::routing::media_functions::MediaStore;
+use quizzes_backend::routing::quiz_types::IncomingFullQuiz;
+use quizzes_backend::routing::search_functions::SearchIndex;
+use quizzes_backend::routing::submission_functions::AttemptSigner;
+use rocket::http::{ContentType, Status};
+use rocket::local::{Client, LocalResponse};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// The whole app, running off an empty in-memory repository. The repository is handed back too,
+// for tests that want to look behind the routes. Uploads go to a fresh temporary directory that's
+// left behind, and are capped at 64 KiB and a megapixel. The search index is kept in memory.
+pub fn memory_client() -> (Client, Arc<MemoryRepository>) {
+    let repo = Arc::new(MemoryRepository::new());
+    (client_for(repo.clone()), repo)
+}
+
+// Another client on the same repository, like a second device or a second person. Each keeps
+// its own cookies.
+pub fn client_for(repo: Arc<MemoryRepository>) -> Client {
+    let rocket = rocket::ignite()
+        .mount("/", routing::routes())
+        .register(routing::catchers())
+        .manage(Storage::Shared(repo))
+        .manage(AttemptSigner::new(b"test signing key"))
+        .manage(MediaStore::new(
+            tempfile::tempdir().unwrap().keep(),
+            64 * 1024,
+            1_000_000,
+        ))
+        .manage(SearchIndex::in_memory());
+    Client::new(rocket).unwrap()
+}
+
+pub fn post_json<'c>(client: &'c Client, path: &str, body: &Value) -> LocalResponse<'c> {
+    client
+        .post(path.to_string())
+        .header(ContentType::JSON)
+        .body(body.to_string())
+        .dispatch()
+}
+
+pub fn put_json<'c>(client: &'c Client, path: &str, body: &Value) -> LocalResponse<'c> {
+    client
+        .put(path.to_string())
+        .header(ContentType::JSON)
+        .body(body.to_string())
+        .dispatch()
+}
+
+pub fn patch_json<'c>(client: &'c Client, path: &str, body: &Value) -> LocalResponse<'c> {
+    client
+        .patch(path.to_string())
+        .header(ContentType::JSON)
+        .body(body.to_string())
+        .dispatch()
+}
+
+pub fn json_body(response: &mut LocalResponse) -> Value {
+    serde_json::from_str(&response.body_string().unwrap()).unwrap()
+}
+
+// Creates an account named 'name' and logs the client in as it, returning the user's id
+pub fn sign_up(client: &Client, name: &str) -> i32 {
+    let email = format!("{}@example.com", name.to_lowercase());
+    let credentials = json!({ "name": name, "email": email, "password": "hunter22" });
+    let mut response = post_json(client, "/users/create", &credentials);
+    assert_eq!(response.status(), Status::Ok);
+    let uid = json_body(&mut response).as_i64().unwrap() as i32;
+    log_in(client, &email, "hunter22");
+    uid
+}
+
+pub fn log_in(client: &Client, email: &str, password: &str) {
+    let credentials = json!({ "username": email, "password": password });
+    let response = post_json(client, "/users/login", &credentials);
+    assert_eq!(response.status(), Status::Ok);
+}
+
+// The JSON body for 'incoming_quiz'
+pub fn quiz_json(name: &str, questions: usize) -> Value {
+    json!({
+        "quiz": { "name": name, "description": format!("All about {}", name) },
+        "questions": (0..questions)
+            .map(|i| json!({ "id": null, "description": format!("Question {}", i) }))
+            .collect::<Vec<Value>>(),
+        "answers": (0..questions)
+            .map(|_| {
+                (0..2)
+                    .map(|val| json!({ "id": null, "description": format!("Answer {}", val), "val": val }))
+                    .collect::<Vec<Value>>()
+            })
+            .collect::<Vec<Vec<Value>>>(),
+        "results": (0..2)
+            .map(|num| json!({ "id": null, "header": format!("Result {}", num), "description": "" }))
+            .collect::<Vec<Value>>(),
+    })
+}
+
+// A freshly migrated SQLite database on disk. It lives as long as the TempDir does.
+#[cfg(feature = "sqlite")]
+pub fn sqlite_db() -> (tempfile::TempDir, diesel::SqliteConnection) {
+    use diesel::Connection;
+    use quizzes_backend::repository::sqlite::{enable_foreign_keys, run_migrations};
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("quizzes.db");
+    let conn = diesel::SqliteConnection::establish(path.to_str().unwrap()).unwrap();
+    enable_foreign_keys(&conn).unwrap();
+    run_migrations(&conn).unwrap();
+    (dir, conn)
+}
+
+pub fn insert_user(repo: &dyn Repository, name: &str) -> i32 {
+    repo.insert_user(NewUser {
+        name: String::from(name),
+        email: format!("{}@example.com", name.to_lowercase()),
+    })
+    .unwrap()
+}
+
+// 'questions' questions with an answer for each of two results
+pub fn incoming_quiz(name: &str, questions: usize) -> IncomingFullQuiz {
+    IncomingFullQuiz {
+        quiz: IncomingQuiz {
+            name: String::from(name),
+            description: format!("All about {}", name),
+            mode: QuizMode::Personality,
+            media_id: None,
+            category: None,
+            tags: Vec::new(),
+        },
+        questions: (0..questions)
+            .map(|i| IncomingQuestion {
+                id: None,
+                description: format!("Question {}", i),
+                kind: QuestionKind::SingleChoice,
+                config: QuestionConfig::default(),
+                media_id: None,
+            })
+            .collect(),
+        answers: (0..questions)
+            .map(|_| {
+                (0..2)
+                    .map(|val| IncomingAnswer {
+                        id: None,
+                        description: format!("Answer {}", val),
+                        val,
+                        is_correct: false,
+                        weights: BTreeMap::new(),
+                        next_question: None,
+                        media_id: None,
+                    })
+                    .collect()
+            })
+            .collect(),
+        results: (0..2)
+            .map(|num| IncomingQuizResult {
+                id: None,
+                header: format!("Result {}", num),
+                description: String::new(),
+                min_percent: None,
+                max_percent: None,
+                media_id: None,
+            })
+            .collect(),
+    }
+}
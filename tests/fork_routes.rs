@@ -0,0 +1,110 @@
+mod common;
+
+use common::{json_body, log_in, memory_client, post_json, put_json, quiz_json, sign_up};
+use rocket::http::Status;
+use serde_json::json;
+
+#[test]
+fn test_fork_and_lineage() {
+    let (client, _repo) = memory_client();
+    let author = sign_up(&client, "Author");
+    let mut response = post_json(&client, "/quiz", &quiz_json("Cats", 2));
+    let original = json_body(&mut response).as_i64().unwrap();
+
+    let someone = sign_up(&client, "Someone");
+    let mut response = client.post(format!("/quiz/{}/fork", original)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let fork = json_body(&mut response).as_i64().unwrap();
+
+    // A full copy, under its new owner and out of sight until they publish it
+    let mut response = client.get(format!("/quiz/{}", original)).dispatch();
+    let copied = json_body(&mut response);
+    let mut response = client.get(format!("/quiz/{}", fork)).dispatch();
+    let forked = json_body(&mut response);
+    assert_eq!(forked["quiz"]["u_id"], someone);
+    assert_eq!(forked["quiz"]["status"], "draft");
+    assert_eq!(forked["quiz"]["forked_from_id"], original);
+    assert_eq!(forked["quiz"]["name"], copied["quiz"]["name"]);
+    assert_eq!(forked["answers"].as_array().unwrap().len(), 2);
+    assert_ne!(forked["questions"][0]["id"], copied["questions"][0]["id"]);
+    let mut response = client.get(format!("/quiz/{}/versions", fork)).dispatch();
+    assert_eq!(
+        json_body(&mut response)[0]["summary"],
+        format!("Forked from quiz {}", original)
+    );
+
+    // Forks of forks keep the whole line
+    client.post(format!("/quiz/{}/publish", fork)).dispatch();
+    let mut response = client.post(format!("/quiz/{}/fork", fork)).dispatch();
+    let grandchild = json_body(&mut response).as_i64().unwrap();
+    let mut response = client.get(format!("/quiz/{}/forks", grandchild)).dispatch();
+    let info = json_body(&mut response);
+    let lineage: Vec<i64> = info["lineage"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|qz| qz["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(lineage, vec![fork, original]);
+    let mut response = client.get(format!("/quiz/{}/forks", original)).dispatch();
+    assert_eq!(json_body(&mut response)["fork_count"], 1);
+
+    // The author can turn it off, but only for other people
+    log_in(&client, "author@example.com", "hunter22");
+    let forking_path = format!("/quiz/{}/forking", original);
+    let mut response = put_json(&client, &forking_path, &json!({ "allow_forks": false }));
+    assert_eq!(json_body(&mut response)["allow_forks"], false);
+    let mut response = client.post(format!("/quiz/{}/fork", original)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let own = json_body(&mut response).as_i64().unwrap();
+    let mut response = client.get(format!("/quiz/{}", own)).dispatch();
+    assert_eq!(json_body(&mut response)["quiz"]["u_id"], author);
+
+    log_in(&client, "someone@example.com", "hunter22");
+    let mut response = client.post(format!("/quiz/{}/fork", original)).dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+    assert_eq!(json_body(&mut response)["code"], "forking_disabled");
+    let response = put_json(&client, &forking_path, &json!({ "allow_forks": true }));
+    assert_eq!(response.status(), Status::Forbidden);
+    // Nor can anyone fork a draft they can't see
+    let response = client.post(format!("/quiz/{}/fork", own)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn test_fork_keeps_the_answer_key_to_its_author() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    let mut body = quiz_json("Capitals", 2);
+    body["quiz"]["mode"] = json!("trivia");
+    for i in 0..2 {
+        body["answers"][i][0]["is_correct"] = json!(true);
+    }
+    body["results"][0]["min_percent"] = json!(0);
+    body["results"][0]["max_percent"] = json!(99);
+    body["results"][1]["min_percent"] = json!(100);
+    body["results"][1]["max_percent"] = json!(100);
+    let mut response = post_json(&client, "/quiz", &body);
+    let original = json_body(&mut response).as_i64().unwrap();
+
+    // The author's own copy keeps it
+    let mut response = client.post(format!("/quiz/{}/fork", original)).dispatch();
+    let own = json_body(&mut response).as_i64().unwrap();
+    let mut response = client.get(format!("/quiz/{}", own)).dispatch();
+    assert_eq!(
+        json_body(&mut response)["answers"][0][0]["is_correct"],
+        true
+    );
+
+    // Anyone else's copy is theirs to look at in full, so it can't carry the answers over
+    sign_up(&client, "Someone");
+    let mut response = client.post(format!("/quiz/{}/fork", original)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let fork = json_body(&mut response).as_i64().unwrap();
+    for path in &[format!("/quiz/{}", fork), format!("/v2/quiz/{}", fork)] {
+        let mut response = client.get(path.clone()).dispatch();
+        let forked = json_body(&mut response);
+        assert_eq!(forked["quiz"]["mode"], "trivia");
+        assert!(!forked.to_string().contains("is_correct"));
+    }
+}
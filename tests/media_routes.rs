@@ -0,0 +1,144 @@
+mod common;
+
+use common::{json_body, memory_client, post_json, put_json, quiz_json, sign_up};
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::Client;
+use serde_json::Value;
+
+fn picture(width: u32, height: u32, format: ImageOutputFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    DynamicImage::new_rgb8(width, height)
+        .write_to(&mut bytes, format)
+        .unwrap();
+    bytes
+}
+
+fn upload(client: &Client, bytes: Vec<u8>) -> (Status, Value) {
+    // Whatever the client claims the upload is doesn't count
+    let mut response = client
+        .post("/media")
+        .header(ContentType::Plain)
+        .body(bytes)
+        .dispatch();
+    (response.status(), json_body(&mut response))
+}
+
+#[test]
+fn test_upload_and_serve() {
+    let (client, _repo) = memory_client();
+    let (status, _) = upload(&client, picture(4, 4, ImageOutputFormat::Png));
+    assert_eq!(status, Status::Unauthorized);
+
+    let author = sign_up(&client, "Author");
+    let (status, media) = upload(&client, picture(600, 300, ImageOutputFormat::Jpeg(80)));
+    assert_eq!(status, Status::Ok);
+    assert_eq!(media["u_id"], author);
+    assert_eq!(media["content_type"], "image/jpeg");
+    assert_eq!(
+        (media["width"].as_i64(), media["height"].as_i64()),
+        (Some(600), Some(300))
+    );
+
+    let path = format!("/media/{}", media["id"]);
+    let mut response = client.get(path.clone()).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JPEG));
+    assert!(response
+        .headers()
+        .get_one("Cache-Control")
+        .unwrap()
+        .contains("immutable"));
+    let etag = response.headers().get_one("ETag").unwrap().to_string();
+    let body = response.body_bytes().unwrap();
+    assert_eq!(body.len() as i64, media["byte_size"].as_i64().unwrap());
+
+    let response = client
+        .get(path.clone())
+        .header(Header::new("If-None-Match", etag))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotModified);
+
+    // Thumbnails keep the shape of the picture
+    let mut response = client.get(format!("{}/thumbnail", path)).dispatch();
+    assert_eq!(response.content_type(), Some(ContentType::PNG));
+    let thumbnail = image::load_from_memory(&response.body_bytes().unwrap()).unwrap();
+    assert_eq!(thumbnail.dimensions(), (256, 128));
+
+    let response = client.get("/media/9999").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn test_rejected_uploads() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+
+    let (status, error) = upload(&client, b"<html>not a picture</html>".to_vec());
+    assert_eq!(status, Status::UnsupportedMediaType);
+    assert_eq!(error["code"], "unsupported_media");
+
+    // Looks like a PNG but isn't one
+    let mut broken = picture(8, 8, ImageOutputFormat::Png);
+    broken.truncate(20);
+    let (status, error) = upload(&client, broken);
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(error["code"], "invalid_image");
+
+    let mut huge = picture(8, 8, ImageOutputFormat::Png);
+    huge.resize(65 * 1024, 0);
+    let (status, error) = upload(&client, huge);
+    assert_eq!(status, Status::PayloadTooLarge);
+    assert_eq!(error["details"]["max_bytes"], 64 * 1024);
+
+    // Small on disk, but far too big once decoded
+    let (status, error) = upload(&client, picture(1001, 1000, ImageOutputFormat::Png));
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(error["code"], "too_many_pixels");
+    assert_eq!(error["details"]["max_pixels"], 1_000_000);
+}
+
+#[test]
+fn test_quiz_media() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Someone");
+    let (_, theirs) = upload(&client, picture(4, 4, ImageOutputFormat::Png));
+    sign_up(&client, "Author");
+    let (_, mine) = upload(&client, picture(4, 4, ImageOutputFormat::Png));
+
+    let mut quiz = quiz_json("Cats", 2);
+    quiz["quiz"]["media_id"] = mine["id"].clone();
+    quiz["questions"][1]["media_id"] = mine["id"].clone();
+    quiz["answers"][0][0]["media_id"] = mine["id"].clone();
+    quiz["results"][0]["media_id"] = mine["id"].clone();
+    let mut response = post_json(&client, "/quiz", &quiz);
+    assert_eq!(response.status(), Status::Ok);
+    let quiz_id = json_body(&mut response);
+
+    let mut response = client.get(format!("/quiz/{}", quiz_id)).dispatch();
+    let mut stored = json_body(&mut response);
+    assert_eq!(stored["quiz"]["media_id"], mine["id"]);
+    assert_eq!(stored["questions"][0]["media_id"], Value::Null);
+    assert_eq!(stored["questions"][1]["media_id"], mine["id"]);
+    assert_eq!(stored["answers"][0][0]["media_id"], mine["id"]);
+    assert_eq!(stored["results"][0]["media_id"], mine["id"]);
+
+    // Someone else's upload can't be borrowed
+    stored["questions"][0]["media_id"] = theirs["id"].clone();
+    let mut response = put_json(&client, &format!("/quiz/{}", quiz_id), &stored);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let error = json_body(&mut response);
+    assert_eq!(error["code"], "unknown_media");
+    assert_eq!(error["details"]["media_ids"][0], theirs["id"]);
+
+    stored["questions"][0]["media_id"] = Value::Null;
+    stored["quiz"]["media_id"] = Value::Null;
+    let response = put_json(&client, &format!("/quiz/{}", quiz_id), &stored);
+    assert_eq!(response.status(), Status::Ok);
+    let mut response = client.get(format!("/quiz/{}", quiz_id)).dispatch();
+    let edited = json_body(&mut response);
+    assert_eq!(edited["quiz"]["media_id"], Value::Null);
+    assert_eq!(edited["questions"][1]["media_id"], mine["id"]);
+    let mut response = client.get(format!("/quiz/{}/versions", quiz_id)).dispatch();
+    assert_eq!(json_body(&mut response)[0]["summary"], "Picture changed");
+}
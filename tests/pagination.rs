@@ -0,0 +1,119 @@
+use quizzes_backend::routing::pagination::*;
+
+#[test]
+fn test_cursor_round_trip() {
+    for cursor in &[
+        Cursor::Name(String::from("Which cat are you?"), 3),
+        Cursor::Newest(40),
+        Cursor::Popular(12, 7),
+        Cursor::Relevance(1.75, 9),
+    ] {
+        assert_eq!(decode_cursor(&encode_cursor(cursor)).as_ref(), Some(cursor));
+    }
+    assert_eq!(decode_cursor("not a cursor"), None);
+    assert_eq!(decode_cursor(&base64::encode("offset:20")), None);
+}
+
+#[test]
+fn test_cursor_positions() {
+    let last = Cursor::Name(String::from("Beta"), 5);
+    assert!(Cursor::Name(String::from("Beta"), 6).is_past(&last));
+    assert!(Cursor::Name(String::from("Gamma"), 1).is_past(&last));
+    assert!(!Cursor::Name(String::from("Beta"), 5).is_past(&last));
+    assert!(!Cursor::Name(String::from("Alpha"), 9).is_past(&last));
+    // Newest and most popular first, ties newest first
+    assert!(Cursor::Newest(4).is_past(&Cursor::Newest(5)));
+    assert!(Cursor::Popular(3, 9).is_past(&Cursor::Popular(4, 1)));
+    assert!(Cursor::Popular(4, 1).is_past(&Cursor::Popular(4, 2)));
+    // Best match first, ties in the order they were made
+    assert!(Cursor::Relevance(0.5, 1).is_past(&Cursor::Relevance(1.0, 2)));
+    assert!(Cursor::Relevance(1.0, 3).is_past(&Cursor::Relevance(1.0, 2)));
+    // Positions in different orders never compare
+    assert!(!Cursor::Newest(1).is_past(&Cursor::Popular(4, 2)));
+}
+
+#[test]
+fn test_page_params_resolve() {
+    let params = PageParams {
+        limit: None,
+        cursor: Some(encode_cursor(&Cursor::Popular(12, 7))),
+        sort: Some(SortOrder::Popular),
+    };
+    assert_eq!(
+        params
+            .resolve(DEFAULT_PAGE_SIZE, SortOrder::Name, false)
+            .unwrap(),
+        PageRequest {
+            limit: DEFAULT_PAGE_SIZE,
+            sort: SortOrder::Popular,
+            after: Some(Cursor::Popular(12, 7)),
+        }
+    );
+
+    // A cursor has to come from a listing in the same order
+    let params = PageParams {
+        cursor: Some(encode_cursor(&Cursor::Newest(40))),
+        ..PageParams::default()
+    };
+    assert_eq!(
+        params
+            .resolve(DEFAULT_PAGE_SIZE, SortOrder::Name, false)
+            .unwrap_err()
+            .code,
+        "invalid_cursor"
+    );
+
+    // Only search lists by relevance, and then it takes nothing else
+    let params = PageParams {
+        cursor: Some(encode_cursor(&Cursor::Relevance(1.75, 9))),
+        ..PageParams::default()
+    };
+    assert_eq!(
+        params
+            .resolve(DEFAULT_PAGE_SIZE, SortOrder::Name, true)
+            .unwrap()
+            .after,
+        Some(Cursor::Relevance(1.75, 9))
+    );
+    assert_eq!(
+        params
+            .resolve(DEFAULT_PAGE_SIZE, SortOrder::Name, false)
+            .unwrap_err()
+            .code,
+        "invalid_cursor"
+    );
+    let params = PageParams {
+        cursor: Some(encode_cursor(&Cursor::Name(String::from("Beta"), 5))),
+        ..PageParams::default()
+    };
+    assert_eq!(
+        params
+            .resolve(DEFAULT_PAGE_SIZE, SortOrder::Name, true)
+            .unwrap_err()
+            .code,
+        "invalid_cursor"
+    );
+
+    let params = PageParams {
+        limit: Some(MAX_PAGE_SIZE + 1),
+        ..PageParams::default()
+    };
+    assert_eq!(
+        params
+            .resolve(DEFAULT_PAGE_SIZE, SortOrder::Name, false)
+            .unwrap_err()
+            .code,
+        "invalid_limit"
+    );
+}
+
+#[test]
+fn test_page_next_cursor() {
+    let page = Page::new(vec![3, 4], Some(Cursor::Newest(4)), 5);
+    assert_eq!(
+        page.next_cursor.as_deref().and_then(decode_cursor),
+        Some(Cursor::Newest(4))
+    );
+    let page = Page::new(vec![5], None, 3);
+    assert_eq!(page.next_cursor, None);
+}
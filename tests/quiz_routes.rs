@@ -0,0 +1,714 @@
+mod common;
+
+use common::{json_body, memory_client, patch_json, post_json, put_json, quiz_json, sign_up};
+use quizzes_backend::repository::{QuizFacets, QuizFilter, Repository};
+use quizzes_backend::routing::pagination::{PageRequest, SortOrder};
+use rocket::http::{Header, Status};
+use serde_json::{json, Value};
+
+#[test]
+fn test_insert_and_get_quiz() {
+    let (client, _repo) = memory_client();
+
+    // Only logged in users can create quizzes
+    let mut response = post_json(&client, "/quiz", &quiz_json("Cats", 2));
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(json_body(&mut response)["code"], "unauthorized");
+
+    let uid = sign_up(&client, "Author");
+    let mut response = post_json(&client, "/quiz", &quiz_json("Cats", 2));
+    assert_eq!(response.status(), Status::Ok);
+    let quiz_id = json_body(&mut response).as_i64().unwrap();
+
+    let mut response = client.get(format!("/quiz/{}", quiz_id)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("Vary"), Some("Accept"));
+    let full_quiz = json_body(&mut response);
+    assert_eq!(full_quiz["quiz"]["name"], "Cats");
+    assert_eq!(full_quiz["quiz"]["u_id"], uid);
+    assert_eq!(full_quiz["answers"].as_array().unwrap().len(), 2);
+
+    // The nested shape, asked for either way
+    let mut response = client.get(format!("/v2/quiz/{}", quiz_id)).dispatch();
+    let nested = json_body(&mut response);
+    assert_eq!(
+        nested["questions"][0]["answers"].as_array().unwrap().len(),
+        2
+    );
+    let mut response = client
+        .get(format!("/quiz/{}", quiz_id))
+        .header(Header::new("Accept", "application/json; version=2"))
+        .dispatch();
+    // Caches have to tell the two shapes apart
+    assert_eq!(response.headers().get_one("Vary"), Some("Accept"));
+    assert_eq!(json_body(&mut response), nested);
+
+    let mut response = client.get(format!("/quiz/{}", quiz_id + 1)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(json_body(&mut response)["code"], "not_found");
+}
+
+#[test]
+fn test_invalid_quizzes_are_rejected() {
+    let (client, repo) = memory_client();
+    let uid = sign_up(&client, "Author");
+    let mut invalid = quiz_json("Cats", 2);
+    invalid["quiz"]["name"] = json!("");
+
+    let response = post_json(&client, "/quiz/validate", &quiz_json("Cats", 2));
+    assert_eq!(response.status(), Status::NoContent);
+    let mut response = post_json(&client, "/quiz/validate", &invalid);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(json_body(&mut response)["code"], "invalid_quiz");
+
+    let mut response = post_json(&client, "/quiz", &invalid);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(
+        json_body(&mut response)["details"]["violations"][0]["field"],
+        "quiz.name"
+    );
+    // Nothing was stored
+    let page = PageRequest {
+        limit: 10,
+        sort: SortOrder::Newest,
+        after: None,
+    };
+    let (_, total, _) = repo
+        .list_quizzes(&QuizFilter::ByUser(uid), &QuizFacets::default(), &page)
+        .unwrap();
+    assert_eq!(total, 0);
+}
+
+#[test]
+fn test_edit_and_delete_need_the_owner() {
+    let (client, repo) = memory_client();
+    sign_up(&client, "Author");
+    let mut response = post_json(&client, "/quiz", &quiz_json("Cats", 2));
+    let quiz_id = json_body(&mut response).as_i64().unwrap();
+
+    let response = put_json(
+        &client,
+        &format!("/quiz/{}", quiz_id),
+        &quiz_json("Dogs", 1),
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let quiz = repo.get_quiz(quiz_id as i32).unwrap();
+    assert_eq!(quiz.name, "Dogs");
+    assert_eq!(repo.get_questions(quiz.id).unwrap().len(), 1);
+
+    sign_up(&client, "Someone");
+    let response = put_json(
+        &client,
+        &format!("/quiz/{}", quiz_id),
+        &quiz_json("Birds", 1),
+    );
+    assert_eq!(response.status(), Status::Forbidden);
+    let response = client
+        .delete(format!("/quiz?quiz_id={}", quiz_id))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+
+    common::log_in(&client, "author@example.com", "hunter22");
+    let response = client
+        .delete(format!("/quiz?quiz_id={}", quiz_id))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get(format!("/quiz/{}", quiz_id)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn test_edit_ids_are_checked() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    let mut response = post_json(&client, "/quiz", &quiz_json("Cats", 2));
+    let path = format!("/quiz/{}", json_body(&mut response));
+    let mut response = client.get(path.clone()).dispatch();
+    let stored = json_body(&mut response);
+    let mut response = post_json(&client, "/quiz", &quiz_json("Dogs", 1));
+    let mut response = client
+        .get(format!("/quiz/{}", json_body(&mut response)))
+        .dispatch();
+    let other = json_body(&mut response);
+    let violations = |body: &Value| -> Vec<(String, String)> {
+        let mut response = put_json(&client, &path, body);
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        json_body(&mut response)["details"]["violations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                (
+                    v["field"].as_str().unwrap().to_string(),
+                    v["message"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    };
+
+    let mut repeated = stored.clone();
+    repeated["questions"][1]["id"] = stored["questions"][0]["id"].clone();
+    repeated["answers"][0][1]["id"] = stored["answers"][0][0]["id"].clone();
+    assert_eq!(
+        violations(&repeated),
+        vec![
+            (
+                String::from("answers[0][1].id"),
+                String::from("listed twice")
+            ),
+            (
+                String::from("questions[1].id"),
+                String::from("listed twice")
+            ),
+        ]
+    );
+
+    // Ids from another quiz, or from another question of this one
+    let mut foreign = stored.clone();
+    foreign["questions"][1]["id"] = other["questions"][0]["id"].clone();
+    foreign["answers"][0][0]["id"] = stored["answers"][1][0]["id"].clone();
+    foreign["results"][0]["id"] = other["results"][0]["id"].clone();
+    assert_eq!(
+        violations(&foreign),
+        vec![
+            (
+                String::from("answers[0][0].id"),
+                String::from("not part of this question")
+            ),
+            (
+                String::from("questions[1].id"),
+                String::from("not part of this quiz")
+            ),
+            (
+                String::from("results[0].id"),
+                String::from("not part of this quiz")
+            ),
+        ]
+    );
+
+    // Nothing was touched
+    let mut response = client.get(path.clone()).dispatch();
+    assert_eq!(json_body(&mut response), stored);
+}
+
+#[test]
+fn test_listings() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Someone");
+    post_json(&client, "/quiz", &quiz_json("Not mine", 1));
+    sign_up(&client, "Author");
+    for name in &[
+        "Which cat are you",
+        "Which dog are you",
+        "Capitals of Europe",
+    ] {
+        post_json(&client, "/quiz", &quiz_json(name, 1));
+    }
+    let names = |path: &str| {
+        let mut response = client.get(path.to_string()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let page = json_body(&mut response);
+        let names: Vec<String> = page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|qz| qz["name"].as_str().unwrap().to_string())
+            .collect();
+        (names, page["next_cursor"].as_str().map(String::from))
+    };
+
+    let (first, cursor) = names("/browse?limit=2");
+    assert_eq!(first, vec!["Capitals of Europe", "Not mine"]);
+    let cursor = cursor.unwrap();
+    // A quiz turning up on an earlier page doesn't push anything onto the next one twice
+    post_json(&client, "/quiz", &quiz_json("Apples or pears", 1));
+    let (rest, last) = names(&format!("/browse?limit=2&cursor={}", cursor));
+    assert_eq!(rest, vec!["Which cat are you", "Which dog are you"]);
+    assert_eq!(last, None);
+    // The cursor belongs to the order it came from
+    let mut response = client
+        .get(format!("/browse?limit=2&sort=newest&cursor={}", cursor))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(json_body(&mut response)["code"], "invalid_cursor");
+
+    assert_eq!(names("/?limit=1").0, vec!["Apples or pears"]);
+    assert_eq!(
+        names("/search?query=which%20ca").0,
+        vec!["Which cat are you"]
+    );
+    // Equally good matches page in the order they were made
+    let (first, cursor) = names("/search?query=which&limit=1");
+    assert_eq!(first, vec!["Which cat are you"]);
+    let cursor = cursor.unwrap();
+    // Only a search in order of relevance takes them back
+    let mut response = client
+        .get(format!("/browse?limit=1&cursor={}", cursor))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(json_body(&mut response)["code"], "invalid_cursor");
+    let (rest, cursor) = names(&format!("/search?query=which&limit=1&cursor={}", cursor));
+    assert_eq!(rest, vec!["Which dog are you"]);
+    assert_eq!(cursor, None);
+    // Forgiving of typos, with the matching words picked out
+    let mut response = client.get("/search?query=wich%20dogg").dispatch();
+    let found = json_body(&mut response);
+    assert_eq!(found["total"], 1);
+    assert_eq!(
+        found["items"][0]["highlights"]["name"],
+        "<b>Which</b> <b>dog</b> are you"
+    );
+    assert!(found["items"][0]["score"].as_f64().unwrap() > 0.0);
+    assert_eq!(
+        names("/quizzes?sort=name").0,
+        vec![
+            "Apples or pears",
+            "Capitals of Europe",
+            "Which cat are you",
+            "Which dog are you"
+        ]
+    );
+
+    let mut response = client.get("/browse?limit=0").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(json_body(&mut response)["code"], "invalid_limit");
+}
+
+#[test]
+fn test_drafts_and_publishing() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Someone");
+    sign_up(&client, "Author");
+    let browse_total = || {
+        let mut response = client.get("/browse").dispatch();
+        json_body(&mut response)["total"].as_i64().unwrap()
+    };
+
+    // Half written drafts save fine, but can't go out
+    let unfinished = json!({
+        "quiz": { "name": "", "description": "" },
+        "questions": [{ "id": null, "description": "Only question" }],
+        "answers": [[]],
+        "results": [],
+    });
+    let mut response = post_json(&client, "/quiz/draft", &unfinished);
+    assert_eq!(response.status(), Status::Ok);
+    let quiz_id = json_body(&mut response).as_i64().unwrap();
+    let mut response = client.post(format!("/quiz/{}/publish", quiz_id)).dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(json_body(&mut response)["code"], "invalid_quiz");
+
+    // Only the author sees it
+    assert_eq!(browse_total(), 0);
+    let mut response = client.get("/quizzes").dispatch();
+    assert_eq!(json_body(&mut response)["items"][0]["status"], "draft");
+    common::log_in(&client, "someone@example.com", "hunter22");
+    let response = client.get(format!("/quiz/{}", quiz_id)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = post_json(
+        &client,
+        &format!("/quiz/{}/submit", quiz_id),
+        &json!({ "answers": [] }),
+    );
+    assert_eq!(response.status(), Status::NotFound);
+
+    common::log_in(&client, "author@example.com", "hunter22");
+    let draft_path = format!("/quiz/{}/draft", quiz_id);
+    let response = put_json(&client, &draft_path, &quiz_json("Cats", 2));
+    assert_eq!(response.status(), Status::Ok);
+    let mut response = client.post(format!("/quiz/{}/publish", quiz_id)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let quiz = json_body(&mut response);
+    assert_eq!(quiz["status"], "published");
+    assert!(quiz["published_at"].is_string());
+    assert_eq!(browse_total(), 1);
+
+    // Out for good, so no more autosaves or going back
+    let mut response = put_json(&client, &draft_path, &quiz_json("Cats", 2));
+    assert_eq!(response.status(), Status::Conflict);
+    assert_eq!(json_body(&mut response)["code"], "not_a_draft");
+    let status_path = format!("/quiz/{}/status", quiz_id);
+    let mut response = put_json(&client, &status_path, &json!({ "status": "draft" }));
+    assert_eq!(json_body(&mut response)["code"], "already_published");
+
+    let response = put_json(&client, &status_path, &json!({ "status": "unlisted" }));
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(browse_total(), 0);
+    let response = client.get(format!("/quiz/{}", quiz_id)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    put_json(&client, &status_path, &json!({ "status": "archived" }));
+    let mut response = post_json(
+        &client,
+        &format!("/quiz/{}/attempts", quiz_id),
+        &json!({ "answers": [] }),
+    );
+    assert_eq!(response.status(), Status::Conflict);
+    assert_eq!(json_body(&mut response)["code"], "not_accepting_attempts");
+}
+
+#[test]
+fn test_reorder() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    let mut response = post_json(&client, "/quiz", &quiz_json("Cats", 3));
+    let quiz_id = json_body(&mut response).as_i64().unwrap();
+    let quiz_path = format!("/quiz/{}", quiz_id);
+    let order_path = format!("{}/order", quiz_path);
+    let mut response = client.get(quiz_path.clone()).dispatch();
+    let full_quiz = json_body(&mut response);
+    let ids = |records: &serde_json::Value| -> Vec<i64> {
+        records
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["id"].as_i64().unwrap())
+            .collect()
+    };
+    let question_ids = ids(&full_quiz["questions"]);
+    let answer_ids = ids(&full_quiz["answers"][0]);
+
+    let reversed: Vec<i64> = question_ids.iter().rev().cloned().collect();
+    let response = patch_json(&client, &order_path, &json!({ "questions": reversed }));
+    assert_eq!(response.status(), Status::Ok);
+    let swapped =
+        json!({ "question_id": question_ids[0], "answers": [answer_ids[1], answer_ids[0]] });
+    let response = patch_json(&client, &order_path, &swapped);
+    assert_eq!(response.status(), Status::Ok);
+
+    // Every read sees the new order, answers still lining up with their questions
+    let mut response = client.get(quiz_path.clone()).dispatch();
+    let reordered = json_body(&mut response);
+    assert_eq!(ids(&reordered["questions"]), reversed);
+    assert_eq!(
+        ids(&reordered["answers"][2]),
+        vec![answer_ids[1], answer_ids[0]]
+    );
+    let mut response = client.get(format!("/v2{}", quiz_path)).dispatch();
+    assert_eq!(
+        json_body(&mut response)["questions"][2]["answers"][0]["id"],
+        answer_ids[1]
+    );
+    let mut response = client.get(format!("{}/versions", quiz_path)).dispatch();
+    assert_eq!(json_body(&mut response)[0]["summary"], "Reordered");
+
+    // Leaving something out, or listing it twice, would lose it
+    for body in &[
+        json!({ "questions": [question_ids[0], question_ids[1]] }),
+        json!({ "questions": [question_ids[0], question_ids[0], question_ids[1]] }),
+    ] {
+        let mut response = patch_json(&client, &order_path, body);
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert_eq!(json_body(&mut response)["code"], "invalid_order");
+    }
+}
+
+#[test]
+fn test_question_kinds() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    let mut body = quiz_json("Cats", 3);
+    body["questions"][0]["kind"] = json!("ranking");
+    body["questions"][1]["kind"] = json!("scale");
+    body["questions"][1]["config"] = json!({ "min": 1, "max": 10, "min_label": "Not at all" });
+    body["questions"][2]["kind"] = json!("free_text");
+    body["questions"][2]["config"] = json!({ "max_length": 20 });
+    body["answers"][2] = json!([]);
+
+    // Config a kind doesn't read is turned away
+    let mut wrong = body.clone();
+    wrong["questions"][0]["config"] = json!({ "max_picks": 2 });
+    let mut response = post_json(&client, "/quiz", &wrong);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(
+        json_body(&mut response)["details"]["violations"][0]["field"],
+        "questions[0].config.max_picks"
+    );
+
+    let mut response = post_json(&client, "/quiz", &body);
+    assert_eq!(response.status(), Status::Ok);
+    let quiz_id = json_body(&mut response).as_i64().unwrap();
+    let mut response = client.get(format!("/quiz/{}", quiz_id)).dispatch();
+    let full_quiz = json_body(&mut response);
+    assert_eq!(full_quiz["questions"][1]["kind"], "scale");
+    assert_eq!(
+        full_quiz["questions"][1]["config"]["min_label"],
+        "Not at all"
+    );
+    let question_id = |i: usize| full_quiz["questions"][i]["id"].as_i64().unwrap();
+    let answer_id = |i: usize, j: usize| full_quiz["answers"][i][j]["id"].as_i64().unwrap();
+
+    // Ranking result 1 first and a high value both count towards result 1
+    let submission = json!({
+        "answers": [answer_id(0, 1), answer_id(0, 0)],
+        "values": { question_id(1).to_string(): 9 },
+        "texts": { question_id(2).to_string(): "Purring" },
+    });
+    let path = format!("/quiz/{}/attempts", quiz_id);
+    let mut response = post_json(&client, &path, &submission);
+    assert_eq!(response.status(), Status::Ok);
+    let receipt = json_body(&mut response);
+    assert_eq!(receipt["result"]["num"], 1);
+    let mut response = client
+        .get(format!("/attempts/{}", receipt["id"]))
+        .dispatch();
+    let attempt = json_body(&mut response);
+    assert_eq!(
+        attempt["answers"],
+        json!([
+            { "id": answer_id(0, 1), "description": "Answer 1" },
+            { "id": answer_id(0, 0), "description": "Answer 0" },
+        ])
+    );
+    assert_eq!(attempt["attempt"]["result_header"], "Result 1");
+    assert_eq!(attempt["values"][question_id(1).to_string()], 9);
+    assert_eq!(attempt["texts"][question_id(2).to_string()], "Purring");
+
+    let mut too_long = submission.clone();
+    too_long["texts"][question_id(2).to_string()] = json!("x".repeat(21));
+    let mut response = post_json(&client, &path, &too_long);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(json_body(&mut response)["code"], "invalid_submission");
+}
+
+#[test]
+fn test_trivia_quiz() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    let mut body = quiz_json("Capitals", 2);
+    body["quiz"]["mode"] = json!("trivia");
+    for i in 0..2 {
+        body["answers"][i][0]["is_correct"] = json!(true);
+    }
+    body["results"][0]["min_percent"] = json!(0);
+    body["results"][0]["max_percent"] = json!(99);
+    body["results"][1]["min_percent"] = json!(100);
+    body["results"][1]["max_percent"] = json!(100);
+    let mut response = post_json(&client, "/quiz", &body);
+    assert_eq!(response.status(), Status::Ok);
+    let quiz_path = format!("/quiz/{}", json_body(&mut response));
+
+    // The author sees which answers are correct, nobody else does
+    let mut response = client.get(quiz_path.clone()).dispatch();
+    let full_quiz = json_body(&mut response);
+    assert_eq!(full_quiz["quiz"]["mode"], "trivia");
+    assert_eq!(full_quiz["answers"][0][0]["is_correct"], true);
+    assert_eq!(full_quiz["results"][1]["min_percent"], 100);
+    sign_up(&client, "Taker");
+    let mut response = client.get(quiz_path.clone()).dispatch();
+    let public = json_body(&mut response);
+    assert!(!public.to_string().contains("is_correct"));
+    let mut response = client
+        .get(quiz_path.clone())
+        .header(Header::new("Accept", "application/json; version=2"))
+        .dispatch();
+    assert!(!json_body(&mut response).to_string().contains("is_correct"));
+
+    let answer_id = |i: usize, j: usize| public["answers"][i][j]["id"].clone();
+    let submission = json!({ "answers": [answer_id(0, 0), answer_id(1, 1)] });
+    let mut response = post_json(&client, &format!("{}/submit", quiz_path), &submission);
+    assert_eq!(response.status(), Status::Ok);
+    let outcome = json_body(&mut response);
+    assert_eq!(outcome["num"], 0);
+    assert_eq!(
+        (outcome["score"].clone(), outcome["total"].clone()),
+        (json!(1), json!(2))
+    );
+    assert_eq!(outcome["percentage"], 50);
+    assert_eq!(outcome["questions"][0]["correct"], true);
+    assert_eq!(outcome["questions"][1]["correct"], false);
+}
+
+#[test]
+fn test_weighted_answers() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    let mut body = quiz_json("Houses", 2);
+    body["answers"][0][0]["weights"] = json!({ "0": 1, "1": 3 });
+    let mut response = post_json(&client, "/quiz", &body);
+    assert_eq!(response.status(), Status::Ok);
+    let quiz_path = format!("/quiz/{}", json_body(&mut response));
+
+    let mut response = client.get(quiz_path.clone()).dispatch();
+    let full_quiz = json_body(&mut response);
+    assert_eq!(
+        full_quiz["answers"][0][0]["weights"],
+        json!({ "0": 1, "1": 3 })
+    );
+    assert!(full_quiz["answers"][1][0].get("weights").is_none());
+
+    // The weighted answer outweighs the plain one, and the split comes back with the winner
+    let answer_id = |i: usize, j: usize| full_quiz["answers"][i][j]["id"].clone();
+    let submission = json!({ "answers": [answer_id(0, 0), answer_id(1, 0)] });
+    let mut response = post_json(&client, &format!("{}/submit", quiz_path), &submission);
+    assert_eq!(response.status(), Status::Ok);
+    let outcome = json_body(&mut response);
+    assert_eq!(outcome["num"], 1);
+    let shares: Vec<(serde_json::Value, serde_json::Value)> = outcome["distribution"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|share| (share["points"].clone(), share["percentage"].clone()))
+        .collect();
+    assert_eq!(
+        shares,
+        vec![(json!(2), json!(40.0)), (json!(3), json!(60.0))]
+    );
+}
+
+#[test]
+fn test_branching() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    // The first answer skips the second question, the last question can end things early
+    let mut body = quiz_json("Cats", 3);
+    body["answers"][0][0]["next_question"] = json!({ "question": 2 });
+    body["answers"][2][0]["next_question"] = json!("end");
+    let mut response = post_json(&client, "/quiz", &body);
+    assert_eq!(response.status(), Status::Ok);
+    let quiz_path = format!("/quiz/{}", json_body(&mut response));
+
+    let mut response = client.get(quiz_path.clone()).dispatch();
+    let full_quiz = json_body(&mut response);
+    assert_eq!(
+        full_quiz["answers"][0][0]["next_question"],
+        json!({ "question": 2 })
+    );
+    assert_eq!(full_quiz["answers"][2][0]["next_question"], "end");
+    assert!(full_quiz["answers"][1][0].get("next_question").is_none());
+
+    let answer_id = |i: usize, j: usize| full_quiz["answers"][i][j]["id"].clone();
+    let submit_path = format!("{}/submit", quiz_path);
+    let submission = json!({ "answers": [answer_id(0, 0), answer_id(2, 1)] });
+    let response = post_json(&client, &submit_path, &submission);
+    assert_eq!(response.status(), Status::Ok);
+    let submission = json!({ "answers": [answer_id(0, 0), answer_id(1, 0), answer_id(2, 1)] });
+    let mut response = post_json(&client, &submit_path, &submission);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(json_body(&mut response)["code"], "invalid_submission");
+
+    // With the last question first, its second answer would lead back round to it
+    let question_id = |i: usize| full_quiz["questions"][i]["id"].clone();
+    let order = json!({ "questions": [question_id(2), question_id(0), question_id(1)] });
+    let mut response = patch_json(&client, &format!("{}/order", quiz_path), &order);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(json_body(&mut response)["code"], "invalid_quiz");
+
+    let mut body = quiz_json("Loop", 2);
+    body["answers"][1][0]["next_question"] = json!({ "question": 0 });
+    let response = post_json(&client, "/quiz", &body);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+fn test_tags_and_categories() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    let tagged = |name: &str, category: &str, tags: Value| {
+        let mut quiz = quiz_json(name, 1);
+        quiz["quiz"]["category"] = json!(category);
+        quiz["quiz"]["tags"] = tags;
+        let mut response = post_json(&client, "/quiz", &quiz);
+        assert_eq!(response.status(), Status::Ok);
+        json_body(&mut response)
+    };
+    let cats = tagged("Cats", " Animals ", json!(["Cute  Pets", "cats", "CATS"]));
+    tagged("Dogs", "animals", json!(["cute pets"]));
+    tagged("Rivers", "", json!(["Maps"]));
+
+    // Labels are stored tidied up, each tag once
+    let mut response = client.get(format!("/quiz/{}", cats)).dispatch();
+    let mut stored = json_body(&mut response);
+    assert_eq!(stored["quiz"]["category"], "animals");
+    assert_eq!(stored["quiz"]["tags"], json!(["cats", "cute pets"]));
+
+    let names = |path: &str| {
+        let mut response = client.get(path.to_string()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let page = json_body(&mut response);
+        let names: Vec<String> = page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|qz| qz["name"].as_str().unwrap().to_string())
+            .collect();
+        (names, page)
+    };
+    assert_eq!(names("/browse?tag=Cute%20Pets").0, vec!["Cats", "Dogs"]);
+    assert_eq!(names("/browse?category=animals&tag=cats").0, vec!["Cats"]);
+    assert_eq!(names("/browse?tag=nothing").0, Vec::<String>::new());
+
+    let (found, page) = names("/search?query=about&sort=name");
+    assert_eq!(found, vec!["Cats", "Dogs", "Rivers"]);
+    assert_eq!(
+        page["facets"]["categories"],
+        json!([{"value": "animals", "count": 2}])
+    );
+    assert_eq!(
+        page["facets"]["tags"][0],
+        json!({"value": "cute pets", "count": 2})
+    );
+    let (found, page) = names("/search?query=about&tag=maps");
+    assert_eq!(found, vec!["Rivers"]);
+    assert_eq!(
+        page["facets"]["tags"],
+        json!([{"value": "maps", "count": 1}])
+    );
+
+    let mut response = client.get("/tags").dispatch();
+    let tags = json_body(&mut response);
+    assert_eq!(tags[0], json!({"name": "cute pets", "count": 2}));
+    assert_eq!(tags.as_array().unwrap().len(), 3);
+
+    // Retagging is an edit like any other
+    stored["quiz"]["tags"] = json!(["cats"]);
+    let response = put_json(&client, &format!("/quiz/{}", cats), &stored);
+    assert_eq!(response.status(), Status::Ok);
+    let mut response = client.get(format!("/quiz/{}/versions", cats)).dispatch();
+    assert_eq!(json_body(&mut response)[0]["summary"], "Retagged");
+    assert_eq!(names("/browse?tag=cute%20pets").0, vec!["Dogs"]);
+
+    let too_many: Vec<String> = (0..11).map(|i| format!("tag {}", i)).collect();
+    stored["quiz"]["tags"] = json!(too_many);
+    let mut response = put_json(&client, &format!("/quiz/{}", cats), &stored);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(
+        json_body(&mut response)["details"]["violations"][0]["field"],
+        "quiz.tags"
+    );
+}
+
+#[test]
+fn test_suggestions() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Walter");
+    let mut ids = Vec::new();
+    for name in &["Which cat are you", "Whales", "Dogs"] {
+        let mut response = post_json(&client, "/quiz", &quiz_json(name, 1));
+        ids.push(json_body(&mut response));
+    }
+    let suggest = |prefix: &str| {
+        let mut response = client
+            .get(format!("/search/suggest?prefix={}", prefix))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        json_body(&mut response)
+    };
+
+    let suggestions = suggest("wh");
+    assert_eq!(suggestions["quizzes"][0]["name"], "Whales");
+    assert_eq!(suggestions["quizzes"][1]["name"], "Which cat are you");
+    assert_eq!(suggestions["authors"], json!([]));
+    assert_eq!(suggest("wal")["authors"][0]["quiz_count"], 3);
+
+    // Gone as soon as the quiz is taken down
+    let response = put_json(
+        &client,
+        &format!("/quiz/{}/status", ids[1]),
+        &json!({"status": "unlisted"}),
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let suggestions = suggest("wh");
+    assert_eq!(suggestions["quizzes"].as_array().unwrap().len(), 1);
+    assert_eq!(suggest("wal")["authors"][0]["quiz_count"], 2);
+}
@@ -0,0 +1,72 @@
+use quizzes_backend::models::quiz_models::*;
+use quizzes_backend::routing::quiz_types::{FullQuiz, NestedFullQuiz};
+use std::collections::BTreeMap;
+
+#[test]
+fn test_nested_full_quiz() {
+    let questions: Vec<Question> = (1..=2)
+        .map(|id| Question {
+            id,
+            description: format!("Question {}", id),
+            qz_id: 1,
+            kind: QuestionKind::SingleChoice,
+            config: QuestionConfig::default(),
+            position: id,
+            media_id: None,
+        })
+        .collect();
+    let answers = questions
+        .iter()
+        .map(|qs| {
+            vec![Answer {
+                id: qs.id * 10,
+                description: String::from("Answer"),
+                val: 1,
+                q_id: qs.id,
+                position: 0,
+                is_correct: false,
+                weights: BTreeMap::new(),
+                next_question: None,
+                media_id: None,
+            }]
+        })
+        .collect();
+    let results = vec![1, 0]
+        .into_iter()
+        .map(|num| QuizResult {
+            id: 100 + num,
+            num,
+            header: format!("Result {}", num),
+            description: String::new(),
+            qz_id: 1,
+            min_percent: None,
+            max_percent: None,
+            media_id: None,
+        })
+        .collect();
+    let full_quiz = FullQuiz {
+        quiz: Quiz {
+            id: 1,
+            name: String::from("Sample"),
+            description: String::new(),
+            u_id: 1,
+            status: QuizStatus::Published,
+            published_at: None,
+            forked_from_id: None,
+            allow_forks: true,
+            mode: QuizMode::Personality,
+            media_id: None,
+            category: None,
+            tags: Vec::new(),
+        },
+        questions,
+        answers,
+        results,
+    };
+
+    let nested = serde_json::to_value(NestedFullQuiz::from(full_quiz)).unwrap();
+    assert_eq!(nested["questions"][1]["answers"][0]["q_id"], 2);
+    assert_eq!(nested["results"]["0"]["id"], 100);
+    assert_eq!(nested["results"]["1"]["id"], 101);
+    assert!(nested.get("answers").is_none());
+}
@@ -0,0 +1,285 @@
+use quizzes_backend::models::quiz_models::*;
+use quizzes_backend::routing::quiz_types::IncomingFullQuiz;
+use quizzes_backend::routing::quiz_validation::{
+    validate_draft, validate_full_quiz, ValidationErrors,
+};
+use std::collections::BTreeMap;
+
+// Two questions with an answer for each of the two results
+fn sample_quiz() -> IncomingFullQuiz {
+    IncomingFullQuiz {
+        quiz: IncomingQuiz {
+            name: String::from("Sample"),
+            description: String::new(),
+            mode: QuizMode::Personality,
+            media_id: None,
+            category: None,
+            tags: Vec::new(),
+        },
+        questions: (0..2)
+            .map(|i| IncomingQuestion {
+                id: None,
+                description: format!("Question {}", i),
+                kind: QuestionKind::SingleChoice,
+                config: QuestionConfig::default(),
+                media_id: None,
+            })
+            .collect(),
+        answers: (0..2)
+            .map(|_| {
+                (0..2)
+                    .map(|val| IncomingAnswer {
+                        id: None,
+                        description: format!("Answer {}", val),
+                        val,
+                        is_correct: false,
+                        weights: BTreeMap::new(),
+                        next_question: None,
+                        media_id: None,
+                    })
+                    .collect()
+            })
+            .collect(),
+        results: (0..2)
+            .map(|num| IncomingQuizResult {
+                id: None,
+                header: format!("Result {}", num),
+                description: String::new(),
+                min_percent: None,
+                max_percent: None,
+                media_id: None,
+            })
+            .collect(),
+    }
+}
+
+fn violated_fields(f_quiz: &IncomingFullQuiz) -> Vec<String> {
+    match validate_full_quiz(f_quiz) {
+        Ok(()) => Vec::new(),
+        Err(ValidationErrors(violations)) => violations.into_iter().map(|v| v.field).collect(),
+    }
+}
+
+#[test]
+fn test_validate_full_quiz() {
+    assert!(validate_full_quiz(&sample_quiz()).is_ok());
+}
+
+#[test]
+fn test_validate_full_quiz_reports_every_violation() {
+    let mut f_quiz = sample_quiz();
+    f_quiz.quiz.name = String::from("  ");
+    f_quiz.questions[1].description = "x".repeat(241);
+    f_quiz.answers[0][1].description = String::from("answer 0");
+    f_quiz.answers[1][0].val = 2;
+    f_quiz.results[0].header = "x".repeat(65);
+
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec![
+            "quiz.name",
+            "questions[1].description",
+            "answers[0][1].description",
+            "answers[1][0].val",
+            "results[0].header",
+        ]
+    );
+}
+
+#[test]
+fn test_validate_full_quiz_checks_shape() {
+    // One list of answers short, which used to panic insert_quiz
+    let mut f_quiz = sample_quiz();
+    f_quiz.answers.pop();
+    assert_eq!(violated_fields(&f_quiz), vec!["answers"]);
+
+    let mut f_quiz = sample_quiz();
+    f_quiz.results.pop();
+    f_quiz.answers = vec![vec![], vec![]];
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec!["answers[0]", "answers[1]", "results"]
+    );
+}
+
+#[test]
+fn test_validate_draft_only_checks_what_fits() {
+    let mut f_quiz = sample_quiz();
+    f_quiz.quiz.name = String::new();
+    f_quiz.answers[0].clear();
+    f_quiz.results.clear();
+    assert!(validate_draft(&f_quiz).is_ok());
+
+    // Still has to be storable
+    f_quiz.questions[1].description = "x".repeat(241);
+    f_quiz.answers.pop();
+    match validate_draft(&f_quiz) {
+        Err(ValidationErrors(violations)) => {
+            let fields: Vec<String> = violations.into_iter().map(|v| v.field).collect();
+            assert_eq!(fields, vec!["questions[1].description", "answers"]);
+        }
+        Ok(()) => panic!("expected violations"),
+    }
+}
+
+#[test]
+fn test_validate_question_kinds() {
+    let mut f_quiz = sample_quiz();
+    f_quiz.questions[0].kind = QuestionKind::Scale;
+    f_quiz.questions[0].config = QuestionConfig {
+        min: Some(1),
+        max: Some(5),
+        ..QuestionConfig::default()
+    };
+    f_quiz.questions[1].kind = QuestionKind::FreeText;
+    f_quiz.answers[1].clear();
+    assert!(validate_full_quiz(&f_quiz).is_ok());
+
+    // A scale needs both ends, free text takes no answers, and nobody takes config they don't read
+    f_quiz.questions[0].config.max = None;
+    f_quiz.questions[1].config.max_picks = Some(2);
+    f_quiz.questions[1].config.max_length = Some(1001);
+    f_quiz.answers[1] = sample_quiz().answers.remove(1);
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec![
+            "questions[0].config.max",
+            "questions[1].config.max_picks",
+            "answers[1]",
+            "questions[1].config.max_length",
+        ]
+    );
+
+    let mut f_quiz = sample_quiz();
+    f_quiz.questions[0].kind = QuestionKind::MultiSelect;
+    f_quiz.questions[0].config.max_picks = Some(3);
+    f_quiz.questions[1].kind = QuestionKind::Scale;
+    f_quiz.questions[1].config.min = Some(1);
+    f_quiz.questions[1].config.max = Some(1);
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec!["questions[0].config.max_picks", "questions[1].config.max"]
+    );
+}
+
+#[test]
+fn test_validate_trivia_quiz() {
+    let mut f_quiz = sample_quiz();
+    f_quiz.quiz.mode = QuizMode::Trivia;
+    for question_answers in f_quiz.answers.iter_mut() {
+        question_answers[1].is_correct = true;
+    }
+    f_quiz.results[0].min_percent = Some(0);
+    f_quiz.results[0].max_percent = Some(49);
+    f_quiz.results[1].min_percent = Some(50);
+    f_quiz.results[1].max_percent = Some(100);
+    assert!(validate_full_quiz(&f_quiz).is_ok());
+
+    // Bands that overlap, a question with two correct answers and one that can't be marked
+    f_quiz.results[1].min_percent = Some(40);
+    f_quiz.answers[0][0].is_correct = true;
+    f_quiz.questions[1].kind = QuestionKind::Ranking;
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec!["answers[0]", "questions[1].kind", "results"]
+    );
+
+    // Personality quizzes don't take any of it
+    f_quiz.quiz.mode = QuizMode::Personality;
+    f_quiz.questions[1].kind = QuestionKind::SingleChoice;
+    f_quiz.answers[0][0].is_correct = false;
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec![
+            "answers[0][1].is_correct",
+            "answers[1][1].is_correct",
+            "results[0].min_percent",
+            "results[0].max_percent",
+            "results[1].min_percent",
+            "results[1].max_percent",
+        ]
+    );
+}
+
+#[test]
+fn test_validate_answer_weights() {
+    let mut f_quiz = sample_quiz();
+    f_quiz.answers[0][0].weights.insert(0, 2);
+    f_quiz.answers[0][0].weights.insert(1, 1);
+    assert!(validate_full_quiz(&f_quiz).is_ok());
+
+    // Only results 0 and 1 exist, and a weight of nothing isn't a weight
+    f_quiz.answers[0][0].weights.insert(2, 1);
+    f_quiz.answers[1][0].weights.insert(0, 0);
+    f_quiz.answers[1][1].weights.insert(1, 101);
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec![
+            "answers[0][0].weights.2",
+            "answers[1][0].weights.0",
+            "answers[1][1].weights.1",
+        ]
+    );
+
+    // Trivia quizzes are marked, not weighed
+    let mut f_quiz = sample_quiz();
+    f_quiz.quiz.mode = QuizMode::Trivia;
+    for question_answers in f_quiz.answers.iter_mut() {
+        question_answers[1].is_correct = true;
+    }
+    f_quiz.results[0].min_percent = Some(0);
+    f_quiz.results[0].max_percent = Some(49);
+    f_quiz.results[1].min_percent = Some(50);
+    f_quiz.results[1].max_percent = Some(100);
+    f_quiz.answers[0][0].weights.insert(0, 2);
+    assert_eq!(violated_fields(&f_quiz), vec!["answers[0][0].weights"]);
+}
+
+#[test]
+fn test_validate_branching() {
+    // The first question either ends the quiz or jumps straight to the second
+    let mut f_quiz = sample_quiz();
+    f_quiz.answers[0][0].next_question = Some(NextQuestion::End);
+    f_quiz.answers[0][1].next_question = Some(NextQuestion::Question(1));
+    assert!(validate_full_quiz(&f_quiz).is_ok());
+
+    // Nowhere to go, and two ways round in circles
+    f_quiz.answers[1][1].next_question = Some(NextQuestion::Question(5));
+    f_quiz.answers[0][0].next_question = Some(NextQuestion::Question(0));
+    f_quiz.answers[1][0].next_question = Some(NextQuestion::Question(0));
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec![
+            "answers[1][1].next_question",
+            "answers[0][0].next_question",
+            "answers[1][0].next_question",
+        ]
+    );
+
+    // Jumping back is fine as long as nothing leads round to it again
+    let mut f_quiz = sample_quiz();
+    f_quiz.questions.push(IncomingQuestion {
+        id: None,
+        description: String::from("Question 2"),
+        kind: QuestionKind::SingleChoice,
+        config: QuestionConfig::default(),
+        media_id: None,
+    });
+    f_quiz.answers.push(sample_quiz().answers.remove(0));
+    for ans in f_quiz.answers[0].iter_mut() {
+        ans.next_question = Some(NextQuestion::Question(2));
+    }
+    f_quiz.answers[2][0].next_question = Some(NextQuestion::Question(1));
+    f_quiz.answers[1][0].next_question = Some(NextQuestion::End);
+    f_quiz.answers[1][1].next_question = Some(NextQuestion::End);
+    assert!(validate_full_quiz(&f_quiz).is_ok());
+
+    // Only single choice answers decide where to go
+    let mut f_quiz = sample_quiz();
+    f_quiz.questions[1].kind = QuestionKind::MultiSelect;
+    f_quiz.answers[1][0].next_question = Some(NextQuestion::End);
+    assert_eq!(
+        violated_fields(&f_quiz),
+        vec!["answers[1][0].next_question"]
+    );
+}
@@ -0,0 +1,263 @@
+mod common;
+
+use quizzes_backend::models::quiz_models::{QuizMode, QuizStatus};
+use quizzes_backend::repository::memory::MemoryRepository;
+use quizzes_backend::repository::{self, QuizFacets, Repository};
+use quizzes_backend::routing::pagination::{PageRequest, SortOrder};
+use quizzes_backend::routing::quiz_functions::insert_full_quiz;
+use quizzes_backend::routing::quiz_types::IncomingFullQuiz;
+use quizzes_backend::routing::search_functions::*;
+
+fn store(repo: &MemoryRepository, uid: i32, f_quiz: IncomingFullQuiz, status: QuizStatus) -> i32 {
+    let mut f_quiz = Some(f_quiz);
+    repository::transaction(repo, || {
+        insert_full_quiz(f_quiz.take().unwrap(), uid, status, repo)
+    })
+    .unwrap()
+}
+
+fn names(index: &SearchIndex, repo: &MemoryRepository, query: &str) -> Vec<String> {
+    let page = PageRequest {
+        limit: 10,
+        sort: SortOrder::Name,
+        after: None,
+    };
+    let results = search_quizzes(query, &QuizFacets::default(), true, &page, index, repo).unwrap();
+    assert_eq!(results.page.total as usize, results.page.items.len());
+    results
+        .page
+        .items
+        .into_iter()
+        .map(|hit| hit.quiz.name)
+        .collect()
+}
+
+#[test]
+fn test_matching_and_ranking() {
+    let repo = MemoryRepository::new();
+    let index = SearchIndex::in_memory();
+    let uid = common::insert_user(&repo, "Author");
+    let mut asked = common::incoming_quiz("Pets", 2);
+    asked.questions[1].description = String::from("Would you rather own a penguin?");
+    store(&repo, uid, asked, QuizStatus::Published);
+    store(
+        &repo,
+        uid,
+        common::incoming_quiz("Which penguin are you", 1),
+        QuizStatus::Published,
+    );
+    let mut resulted = common::incoming_quiz("Birds", 1);
+    resulted.results[0].description = String::from("Emperor penguins huddle together");
+    store(&repo, uid, resulted, QuizStatus::Published);
+    store(
+        &repo,
+        uid,
+        common::incoming_quiz("Penguins in secret", 1),
+        QuizStatus::Draft,
+    );
+    assert_eq!(rebuild_index(&index, &repo).unwrap(), 4);
+
+    // The name counts for most and a word spelled out beats one that's only close, drafts are
+    // never shown
+    assert_eq!(
+        names(&index, &repo, "penguin"),
+        vec!["Which penguin are you", "Pets", "Birds"]
+    );
+    // Typos, unfinished words and case don't matter, every word has to match somewhere
+    assert_eq!(
+        names(&index, &repo, "WHICH pengiun"),
+        vec!["Which penguin are you"]
+    );
+    assert_eq!(names(&index, &repo, "emper"), vec!["Birds"]);
+    assert_eq!(names(&index, &repo, "penguin walrus"), Vec::<String>::new());
+    assert_eq!(names(&index, &repo, "  ?! "), Vec::<String>::new());
+    // Short words have to be spelled right
+    assert_eq!(names(&index, &repo, "pits"), vec!["Pets"]);
+    assert_eq!(names(&index, &repo, "pet"), vec!["Pets"]);
+    assert_eq!(names(&index, &repo, "pat"), Vec::<String>::new());
+}
+
+#[test]
+fn test_scores_and_highlights() {
+    let repo = MemoryRepository::new();
+    let index = SearchIndex::in_memory();
+    let uid = common::insert_user(&repo, "Author");
+    let mut f_quiz = common::incoming_quiz("Which <cat> are you", 2);
+    f_quiz.questions[1].description = String::from("Do cats or dogs make better friends?");
+    let quiz_id = store(&repo, uid, f_quiz, QuizStatus::Published);
+    index_quiz(quiz_id, &index, &repo);
+
+    let page = PageRequest {
+        limit: 10,
+        sort: SortOrder::Name,
+        after: None,
+    };
+    let results =
+        search_quizzes("cat", &QuizFacets::default(), true, &page, &index, &repo).unwrap();
+    let hit = &results.page.items[0];
+    assert!(hit.score > 0.0);
+    assert_eq!(hit.highlights["name"], "Which &lt;<b>cat</b>&gt; are you");
+    assert_eq!(
+        hit.highlights["questions"],
+        "Do <b>cats</b> or dogs make better friends"
+    );
+    assert!(!hit.highlights.contains_key("results"));
+}
+
+#[test]
+fn test_index_follows_the_quizzes() {
+    let repo = MemoryRepository::new();
+    let index = SearchIndex::in_memory();
+    let uid = common::insert_user(&repo, "Author");
+    let quiz_id = store(
+        &repo,
+        uid,
+        common::incoming_quiz("Which cat are you", 1),
+        QuizStatus::Published,
+    );
+    index_quiz(quiz_id, &index, &repo);
+    assert_eq!(names(&index, &repo, "cat"), vec!["Which cat are you"]);
+
+    repo.update_quiz(
+        quiz_id,
+        "Which bird are you",
+        "",
+        QuizMode::Personality,
+        None,
+    )
+    .unwrap();
+    index_quiz(quiz_id, &index, &repo);
+    assert_eq!(names(&index, &repo, "cat"), Vec::<String>::new());
+    assert_eq!(names(&index, &repo, "bird"), vec!["Which bird are you"]);
+
+    unindex_quiz(quiz_id, &index);
+    assert_eq!(names(&index, &repo, "bird"), Vec::<String>::new());
+    // Nothing is lost for good while the database still has it
+    rebuild_index(&index, &repo).unwrap();
+    assert_eq!(names(&index, &repo, "bird"), vec!["Which bird are you"]);
+}
+
+#[test]
+fn test_suggestions() {
+    let repo = MemoryRepository::new();
+    let index = SearchIndex::in_memory();
+    let bob = common::insert_user(&repo, "Bob");
+    let catherine = common::insert_user(&repo, "Catherine");
+    let tagged = |name: &str, tags: &[&str]| {
+        let mut f_quiz = common::incoming_quiz(name, 1);
+        f_quiz.quiz.tags = tags.iter().map(|tag| String::from(*tag)).collect();
+        f_quiz
+    };
+    store(
+        &repo,
+        bob,
+        tagged("Which cat are you", &["cats", "pets"]),
+        QuizStatus::Published,
+    );
+    let catherines = store(
+        &repo,
+        catherine,
+        tagged("Cats of the world", &["cats"]),
+        QuizStatus::Published,
+    );
+    store(
+        &repo,
+        bob,
+        tagged("Scattered thoughts", &[]),
+        QuizStatus::Published,
+    );
+    let draft = store(
+        &repo,
+        bob,
+        tagged("Catapults", &["cats"]),
+        QuizStatus::Draft,
+    );
+    let quiz_names = |prefix: &str| -> Vec<String> {
+        let suggestions = index.suggest(prefix, &repo).unwrap();
+        suggestions.quizzes.into_iter().map(|qz| qz.name).collect()
+    };
+
+    // Names starting with the prefix come first, a word anywhere else in them still counts
+    assert_eq!(
+        quiz_names("cat"),
+        vec!["Cats of the world", "Which cat are you"]
+    );
+    assert_eq!(quiz_names("  WHICH c"), vec!["Which cat are you"]);
+    assert_eq!(quiz_names(""), Vec::<String>::new());
+    let suggestions = index.suggest("cat", &repo).unwrap();
+    assert_eq!(
+        (suggestions.tags[0].name.as_str(), suggestions.tags[0].count),
+        ("cats", 2)
+    );
+    assert_eq!(suggestions.authors.len(), 1);
+    assert_eq!(
+        (suggestions.authors[0].id, suggestions.authors[0].quiz_count),
+        (catherine, 1)
+    );
+
+    // Kept up to date a quiz at a time
+    repo.update_quiz_status(draft, QuizStatus::Published, None)
+        .unwrap();
+    index_quiz(draft, &index, &repo);
+    assert_eq!(
+        quiz_names("cat"),
+        vec!["Catapults", "Cats of the world", "Which cat are you"]
+    );
+    unindex_quiz(catherines, &index);
+    let suggestions = index.suggest("cat", &repo).unwrap();
+    assert_eq!(suggestions.tags[0].count, 2);
+    assert!(suggestions.authors.is_empty());
+    assert_eq!(index.suggest("b", &repo).unwrap().authors[0].quiz_count, 3);
+}
+
+#[test]
+fn test_suggestions_prefer_names_starting_with_the_prefix() {
+    let repo = MemoryRepository::new();
+    let index = SearchIndex::in_memory();
+    let uid = common::insert_user(&repo, "Baker");
+    // More names with the prefix further in than suggest looks at, all sorted before the one
+    // starting with it
+    for n in 0..250 {
+        store(
+            &repo,
+            uid,
+            common::incoming_quiz(&format!("Apple pie {:03}", n), 1),
+            QuizStatus::Published,
+        );
+    }
+    store(
+        &repo,
+        uid,
+        common::incoming_quiz("Pies of the world", 1),
+        QuizStatus::Published,
+    );
+
+    let suggestions = index.suggest("pie", &repo).unwrap();
+    assert_eq!(suggestions.quizzes[0].name, "Pies of the world");
+}
+
+#[test]
+fn test_suggestions_prefer_the_shortest_names() {
+    let repo = MemoryRepository::new();
+    let index = SearchIndex::in_memory();
+    let uid = common::insert_user(&repo, "Asker");
+    // More names starting with the prefix than suggest looks at, all sorted before the shortest
+    for n in 0..250 {
+        store(
+            &repo,
+            uid,
+            common::incoming_quiz(&format!("Which {:03} of these are you", n), 1),
+            QuizStatus::Published,
+        );
+    }
+    store(
+        &repo,
+        uid,
+        common::incoming_quiz("Which cat", 1),
+        QuizStatus::Published,
+    );
+
+    let suggestions = index.suggest("w", &repo).unwrap();
+    assert_eq!(suggestions.quizzes[0].name, "Which cat");
+    assert_eq!(suggestions.quizzes[1].name, "Which 000 of these are you");
+}
@@ -0,0 +1,326 @@
+use quizzes_backend::models::quiz_models::*;
+use quizzes_backend::routing::quiz_types::FullQuiz;
+use quizzes_backend::routing::submission_functions::{score_submission, AttemptSigner};
+use quizzes_backend::routing::submission_types::*;
+use std::collections::BTreeMap;
+
+// Two questions with an answer for each of the two results, answer ids are 10 * question + val
+fn sample_quiz() -> FullQuiz {
+    let questions: Vec<Question> = (1..=2)
+        .map(|id| Question {
+            id,
+            description: format!("Question {}", id),
+            qz_id: 1,
+            kind: QuestionKind::SingleChoice,
+            config: QuestionConfig::default(),
+            position: id,
+            media_id: None,
+        })
+        .collect();
+    let answers = questions
+        .iter()
+        .map(|qs| {
+            (0..2)
+                .map(|val| Answer {
+                    id: qs.id * 10 + val,
+                    description: format!("Answer {}", val),
+                    val,
+                    q_id: qs.id,
+                    position: 0,
+                    is_correct: false,
+                    weights: BTreeMap::new(),
+                    next_question: None,
+                    media_id: None,
+                })
+                .collect()
+        })
+        .collect();
+    let results = (0..2)
+        .map(|num| QuizResult {
+            id: 100 + num,
+            num,
+            header: format!("Result {}", num),
+            description: String::new(),
+            qz_id: 1,
+            min_percent: None,
+            max_percent: None,
+            media_id: None,
+        })
+        .collect();
+    FullQuiz {
+        quiz: Quiz {
+            id: 1,
+            name: String::from("Sample"),
+            description: String::new(),
+            u_id: 1,
+            status: QuizStatus::Published,
+            published_at: None,
+            forked_from_id: None,
+            allow_forks: true,
+            mode: QuizMode::Personality,
+            media_id: None,
+            category: None,
+            tags: Vec::new(),
+        },
+        questions,
+        answers,
+        results,
+    }
+}
+
+fn picks(answer_ids: &[i32]) -> Submission {
+    Submission {
+        answers: answer_ids.to_vec(),
+        ..Submission::default()
+    }
+}
+
+// The sample quiz with its questions turned into 'kinds', the answers vals are 0 and 1 for each
+fn quiz_of_kinds(kinds: &[(QuestionKind, QuestionConfig)]) -> FullQuiz {
+    let mut full_quiz = sample_quiz();
+    for (qs, (kind, config)) in full_quiz.questions.iter_mut().zip(kinds) {
+        qs.kind = *kind;
+        qs.config = config.clone();
+    }
+    full_quiz
+}
+
+#[test]
+fn test_score_submission() {
+    let res = score_submission(sample_quiz(), &picks(&[11, 21])).unwrap();
+    assert_eq!(res.result.num, 1);
+
+    // One point each, so the first listed result wins the tie
+    let res = score_submission(sample_quiz(), &picks(&[11, 20])).unwrap();
+    assert_eq!(res.result.num, 0);
+}
+
+#[test]
+fn test_score_submission_rejects_bad_answers() {
+    assert_eq!(
+        score_submission(sample_quiz(), &picks(&[11, 99])).unwrap_err(),
+        SubmissionError::UnknownAnswer(99)
+    );
+    assert_eq!(
+        score_submission(sample_quiz(), &picks(&[10, 11])).unwrap_err(),
+        SubmissionError::DuplicateQuestion(1)
+    );
+    assert_eq!(
+        score_submission(sample_quiz(), &picks(&[10])).unwrap_err(),
+        SubmissionError::UnansweredQuestion(2)
+    );
+}
+
+#[test]
+fn test_score_each_kind() {
+    let multi = QuestionConfig {
+        max_picks: Some(2),
+        ..QuestionConfig::default()
+    };
+    let quiz = || quiz_of_kinds(&[(QuestionKind::MultiSelect, multi.clone())]);
+    // Both answers of question 1 count against question 2's one
+    let res = score_submission(quiz(), &picks(&[10, 11, 21])).unwrap();
+    assert_eq!(res.result.num, 1);
+    assert_eq!(
+        score_submission(quiz(), &picks(&[10, 10, 21])).unwrap_err(),
+        SubmissionError::DuplicateAnswer(10)
+    );
+
+    // Ranking answer 0 first earns it a point, answer 1 nothing
+    let quiz = || quiz_of_kinds(&[(QuestionKind::Ranking, QuestionConfig::default())]);
+    let res = score_submission(quiz(), &picks(&[10, 11, 21])).unwrap();
+    assert_eq!(res.result.num, 0);
+    assert_eq!(
+        score_submission(quiz(), &picks(&[10, 21])).unwrap_err(),
+        SubmissionError::IncompleteRanking(1)
+    );
+
+    // A 1 to 10 scale, with 1 to 5 counting for answer 0 and 6 to 10 for answer 1
+    let scale = QuestionConfig {
+        min: Some(1),
+        max: Some(10),
+        ..QuestionConfig::default()
+    };
+    let quiz = || {
+        quiz_of_kinds(&[
+            (QuestionKind::Scale, scale.clone()),
+            (QuestionKind::Scale, scale.clone()),
+        ])
+    };
+    let mut submission = Submission::default();
+    submission.values.insert(1, 5);
+    submission.values.insert(2, 6);
+    assert_eq!(score_submission(quiz(), &submission).unwrap().result.num, 0);
+    submission.values.insert(1, 7);
+    assert_eq!(score_submission(quiz(), &submission).unwrap().result.num, 1);
+    submission.values.insert(2, 11);
+    assert_eq!(
+        score_submission(quiz(), &submission).unwrap_err(),
+        SubmissionError::OffTheScale(2)
+    );
+
+    // Free text doesn't count towards anything, it only has to be there
+    let text = QuestionConfig {
+        max_length: Some(5),
+        ..QuestionConfig::default()
+    };
+    let quiz = || quiz_of_kinds(&[(QuestionKind::FreeText, text.clone())]);
+    let mut submission = picks(&[21]);
+    assert_eq!(
+        score_submission(quiz(), &submission).unwrap_err(),
+        SubmissionError::UnansweredQuestion(1)
+    );
+    submission.texts.insert(1, String::from("Meow"));
+    assert_eq!(score_submission(quiz(), &submission).unwrap().result.num, 1);
+    submission.texts.insert(1, String::from("Meow meow"));
+    assert_eq!(
+        score_submission(quiz(), &submission).unwrap_err(),
+        SubmissionError::TextTooLong {
+            question: 1,
+            max: 5
+        }
+    );
+    submission.texts.insert(2, String::from("Meow"));
+    assert_eq!(
+        score_submission(quiz(), &submission).unwrap_err(),
+        SubmissionError::WrongKind(2)
+    );
+}
+
+#[test]
+fn test_score_trivia() {
+    // Answer 1 is the correct one, result 0 covers up to half the questions right
+    let quiz = || {
+        let mut full_quiz = sample_quiz();
+        full_quiz.quiz.mode = QuizMode::Trivia;
+        for question_answers in full_quiz.answers.iter_mut() {
+            question_answers[1].is_correct = true;
+        }
+        full_quiz.results[0].min_percent = Some(0);
+        full_quiz.results[0].max_percent = Some(50);
+        full_quiz.results[1].min_percent = Some(51);
+        full_quiz.results[1].max_percent = Some(100);
+        full_quiz
+    };
+
+    let outcome = score_submission(quiz(), &picks(&[11, 20])).unwrap();
+    assert_eq!(outcome.result.num, 0);
+    assert_eq!(
+        outcome.trivia,
+        Some(TriviaScore {
+            score: 1,
+            total: 2,
+            percentage: 50,
+            questions: vec![
+                QuestionScore {
+                    question_id: 1,
+                    correct: true
+                },
+                QuestionScore {
+                    question_id: 2,
+                    correct: false
+                },
+            ],
+        })
+    );
+    let outcome = score_submission(quiz(), &picks(&[11, 21])).unwrap();
+    assert_eq!(outcome.result.num, 1);
+    assert_eq!(outcome.trivia.unwrap().percentage, 100);
+
+    // Personality quizzes aren't marked
+    assert_eq!(
+        score_submission(sample_quiz(), &picks(&[11, 21]))
+            .unwrap()
+            .trivia,
+        None
+    );
+}
+
+#[test]
+fn test_score_weighted_answers() {
+    // Answer 10 leans two to one towards result 0, the rest count fully for their val
+    let quiz = || {
+        let mut full_quiz = sample_quiz();
+        full_quiz.answers[0][0].weights.insert(0, 2);
+        full_quiz.answers[0][0].weights.insert(1, 1);
+        full_quiz
+    };
+
+    let outcome = score_submission(quiz(), &picks(&[10, 21])).unwrap();
+    assert_eq!(outcome.result.num, 0);
+    let shares: Vec<(i32, usize, f64)> = outcome
+        .distribution
+        .unwrap()
+        .iter()
+        .map(|share| (share.num, share.points, share.percentage))
+        .collect();
+    assert_eq!(shares, vec![(0, 2, 50.0), (1, 2, 50.0)]);
+
+    let outcome = score_submission(quiz(), &picks(&[10, 20])).unwrap();
+    let shares: Vec<f64> = outcome
+        .distribution
+        .unwrap()
+        .iter()
+        .map(|share| share.percentage)
+        .collect();
+    assert_eq!(shares, vec![75.0, 25.0]);
+}
+
+#[test]
+fn test_score_branching() {
+    // Answer 10 ends the quiz right away, answer 11 goes on to question 2 as usual
+    let quiz = |mode: QuizMode| {
+        let mut full_quiz = sample_quiz();
+        full_quiz.quiz.mode = mode;
+        full_quiz.answers[0][0].next_question = Some(NextQuestion::End);
+        for question_answers in full_quiz.answers.iter_mut() {
+            question_answers[1].is_correct = true;
+        }
+        full_quiz.results[0].min_percent = Some(0);
+        full_quiz.results[0].max_percent = Some(50);
+        full_quiz.results[1].min_percent = Some(51);
+        full_quiz.results[1].max_percent = Some(100);
+        full_quiz
+    };
+    let personality = || quiz(QuizMode::Personality);
+    assert_eq!(
+        score_submission(personality(), &picks(&[10]))
+            .unwrap()
+            .result
+            .num,
+        0
+    );
+    assert_eq!(
+        score_submission(personality(), &picks(&[10, 21])).unwrap_err(),
+        SubmissionError::SkippedQuestion(2)
+    );
+    assert_eq!(
+        score_submission(personality(), &picks(&[11])).unwrap_err(),
+        SubmissionError::UnansweredQuestion(2)
+    );
+    assert_eq!(
+        score_submission(personality(), &picks(&[11, 21]))
+            .unwrap()
+            .result
+            .num,
+        1
+    );
+
+    // Only the questions asked are marked
+    let trivia = score_submission(quiz(QuizMode::Trivia), &picks(&[10]))
+        .unwrap()
+        .trivia
+        .unwrap();
+    assert_eq!((trivia.score, trivia.total), (0, 1));
+}
+
+#[test]
+fn test_attempt_tokens() {
+    let signer = AttemptSigner::new(b"secret");
+    let token = signer.sign(42);
+    assert_eq!(signer.verify(&token), Some(42));
+    assert_eq!(AttemptSigner::new(b"other").verify(&token), None);
+
+    let forged = token.replacen("42", "43", 1);
+    assert_eq!(signer.verify(&forged), None);
+}
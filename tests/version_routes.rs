@@ -0,0 +1,83 @@
+mod common;
+
+use common::{json_body, memory_client, post_json, put_json, quiz_json, sign_up};
+use rocket::http::Status;
+use serde_json::json;
+
+#[test]
+fn test_history_diff_and_restore() {
+    let (client, _repo) = memory_client();
+    sign_up(&client, "Author");
+    let mut response = post_json(&client, "/quiz", &quiz_json("Cats", 2));
+    let quiz_id = json_body(&mut response).as_i64().unwrap();
+    let quiz_path = format!("/quiz/{}", quiz_id);
+    let summaries = || {
+        let mut response = client.get(format!("{}/versions", quiz_path)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        json_body(&mut response)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|version| version["summary"].as_str().unwrap().to_string())
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(summaries(), vec!["First version"]);
+
+    // Rename it and change the first question, saving what's there again is no new version
+    let mut response = client.get(quiz_path.clone()).dispatch();
+    let mut edited = json_body(&mut response);
+    put_json(&client, &quiz_path, &edited);
+    assert_eq!(summaries().len(), 1);
+    edited["quiz"]["name"] = json!("Dogs");
+    edited["questions"][0]["description"] = json!("Woof?");
+    let response = put_json(&client, &quiz_path, &edited);
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        summaries(),
+        vec!["Renamed, 1 question changed", "First version"]
+    );
+
+    let mut response = client
+        .get(format!("{}/diff?from=1&to=2", quiz_path))
+        .dispatch();
+    let diff = json_body(&mut response);
+    assert_eq!(diff["name"], json!({ "before": "Cats", "after": "Dogs" }));
+    assert_eq!(diff["description"], json!(null));
+    assert_eq!(diff["questions"].as_array().unwrap().len(), 1);
+    assert_eq!(diff["questions"][0]["change"], "changed");
+    assert_eq!(diff["questions"][0]["before"]["description"], "Question 0");
+    assert_eq!(diff["questions"][0]["after"]["description"], "Woof?");
+
+    let mut response = client.get(format!("{}/versions/1", quiz_path)).dispatch();
+    let version = json_body(&mut response);
+    assert_eq!(version["num"], 1);
+    assert_eq!(version["quiz"]["quiz"]["name"], "Cats");
+
+    // Restoring is a save of its own, the questions it brings back keep their ids
+    let mut response = client
+        .post(format!("{}/versions/1/restore", quiz_path))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let restored = json_body(&mut response);
+    assert_eq!(restored["quiz"]["name"], "Cats");
+    assert_eq!(restored["questions"], version["quiz"]["questions"]);
+    assert_eq!(summaries()[0], "Restored version 1");
+    let mut response = client
+        .get(format!("{}/diff?from=1&to=3", quiz_path))
+        .dispatch();
+    let diff = json_body(&mut response);
+    assert_eq!(diff["name"], json!(null));
+    assert!(diff["questions"].as_array().unwrap().is_empty());
+
+    let response = client.get(format!("{}/versions/9", quiz_path)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    // Nobody else gets to see or touch the history
+    sign_up(&client, "Someone");
+    let response = client.get(format!("{}/versions", quiz_path)).dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+    let response = client
+        .post(format!("{}/versions/1/restore", quiz_path))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+}